@@ -12,10 +12,13 @@ use signal_hook::{consts::SIGHUP, consts::SIGTERM, iterator::Signals};
 
 use crate::command::Command;
 use crate::commands::CommandManager;
+use crate::completion::Completer;
 use crate::config::{Config, PlaybackState};
 use crate::events::{Event, EventManager};
+use crate::hooks::Hooks;
 use crate::library::Library;
 use crate::queue::Queue;
+use crate::scrobbler::Scrobbler;
 use crate::spotify::{PlayerEvent, Spotify};
 use crate::ui::create_cursive;
 use crate::{authentication, ui, utils};
@@ -65,6 +68,8 @@ pub static ASYNC_RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
 pub struct Application {
     /// The music queue which controls playback order.
     queue: Arc<Queue>,
+    /// The user library with all their saved items.
+    library: Arc<Library>,
     /// Internally shared
     spotify: Spotify,
     /// Internally shared
@@ -72,6 +77,10 @@ pub struct Application {
     /// An IPC implementation using the D-Bus MPRIS protocol, used to control and inspect ncspot.
     #[cfg(unix)]
     ipc: Option<IpcSocket>,
+    /// Submits the played tracks to a scrobbling service, if configured.
+    scrobbler: Option<Scrobbler>,
+    /// Runs the user's commands on player and queue events.
+    hooks: Hooks,
     /// The object to render to the terminal.
     cursive: CursiveRunner<Cursive>,
 }
@@ -82,7 +91,11 @@ impl Application {
     /// # Arguments
     ///
     /// * `configuration_file_path` - Relative path to the configuration file inside the base path
-    pub fn new(configuration_file_path: Option<String>) -> Result<Self, Box<dyn Error>> {
+    /// * `mini` - Whether to start in the compact mini-player
+    pub fn new(
+        configuration_file_path: Option<String>,
+        mini: bool,
+    ) -> Result<Self, Box<dyn Error>> {
         // Things here may cause the process to abort; we must do them before creating curses
         // windows otherwise the error message will not be seen by a user
 
@@ -136,6 +149,7 @@ impl Application {
             spotify.clone(),
             configuration.clone(),
             library.clone(),
+            event_manager.clone(),
         ));
 
         #[cfg(feature = "mpris")]
@@ -206,16 +220,36 @@ impl Application {
 
         let queueview = ui::queue::QueueView::new(queue.clone(), library.clone());
 
+        let historyview = ui::history::HistoryView::new(queue.clone(), library.clone());
+
         #[cfg(feature = "cover")]
-        let coverview = ui::cover::CoverView::new(queue.clone(), library.clone(), &configuration);
+        let coverview = ui::cover::CoverView::new(
+            queue.clone(),
+            library.clone(),
+            &configuration,
+            cursive.cb_sink().clone(),
+        );
+
+        let lyricsview = ui::lyrics::LyricsView::new(queue.clone(), &configuration);
 
         let status = ui::statusbar::StatusBar::new(queue.clone(), Arc::clone(&library));
 
-        let mut layout =
-            ui::layout::Layout::new(status, &event_manager, theme, Arc::clone(&configuration))
-                .screen("search", search.with_name("search"))
-                .screen("library", libraryview.with_name("library"))
-                .screen("queue", queueview);
+        let miniplayer =
+            ui::miniplayer::MiniPlayer::new(queue.clone(), library.clone(), &configuration);
+
+        let mut layout = ui::layout::Layout::new(
+            status,
+            miniplayer,
+            &event_manager,
+            theme,
+            Arc::clone(&configuration),
+            Completer::new(queue.clone(), library.clone()),
+        )
+        .screen("search", search.with_name("search"))
+        .screen("library", libraryview.with_name("library"))
+        .screen("queue", queueview)
+        .screen("history", historyview)
+        .screen("lyrics", lyricsview);
 
         #[cfg(feature = "cover")]
         layout.add_screen("cover", coverview.with_name("cover"));
@@ -233,14 +267,29 @@ impl Application {
             layout.set_screen("library");
         }
 
+        layout.set_mini(mini);
+
         cursive.add_fullscreen_layer(layout.with_name("main"));
 
+        let scrobbler = configuration
+            .values()
+            .scrobbling
+            .as_ref()
+            .and_then(|config| {
+                Scrobbler::new(config)
+                    .map_err(|e| error!("could not start scrobbler: {e}"))
+                    .ok()
+            });
+
         Ok(Self {
             queue,
+            library,
             spotify,
             event_manager,
             #[cfg(unix)]
             ipc,
+            scrobbler,
+            hooks: Hooks::new(configuration),
             cursive,
         })
     }
@@ -274,13 +323,25 @@ impl Application {
                             ipc.publish(&state, self.queue.get_current());
                         }
 
+                        if let Some(ref mut scrobbler) = self.scrobbler {
+                            scrobbler.handle(&state, self.queue.get_current());
+                        }
+
+                        self.hooks.handle(&state, self.queue.get_current());
+
                         if state == PlayerEvent::FinishedTrack {
-                            self.queue.next(false);
+                            self.queue.log_played(true);
+                            if self.queue.next(false) {
+                                self.hooks.queue_ended(self.queue.get_current());
+                            }
                         }
                     }
                     Event::Queue(event) => {
                         self.queue.handle_event(event);
                     }
+                    Event::LibraryChanged => {
+                        self.library.refresh_playlists();
+                    }
                     Event::SessionDied => {
                         if self.spotify.start_worker(None).is_err() {
                             let data: UserData = self
@@ -304,6 +365,16 @@ impl Application {
                     },
                 }
             }
+            self.queue.update_sleep_timer();
+            self.hooks
+                .update_volume(self.spotify.volume(), self.queue.get_current());
+
+            #[cfg(feature = "scripting")]
+            for event in self.hooks.take_events() {
+                if let Some(data) = self.cursive.user_data::<UserData>().cloned() {
+                    data.cmd.handle_event(&mut self.cursive, event);
+                }
+            }
         }
         Ok(())
     }
@@ -1,4 +1,9 @@
-use crate::config::{user_cache_directory, user_configuration_directory};
+use chrono::Local;
+use clap::ArgMatches;
+
+use crate::config::{config_path, user_cache_directory, user_configuration_directory};
+use crate::play_history::{PlayHistory, PLAY_HISTORY_FILE_NAME};
+use crate::stats::{Report, StatsPeriod};
 
 /// Print platform info like which platform directories will be used.
 /// 打印平台信息，例如：配置路径、缓存路径
@@ -34,3 +39,18 @@ pub fn info() -> Result<(), String> {
 
     Ok(())
 }
+
+/// Print listening statistics computed from the play history.
+pub fn stats(matches: &ArgMatches) -> Result<(), String> {
+    let argument = |name: &str| matches.get_one::<String>(name).cloned().unwrap_or_default();
+    let period: StatsPeriod = argument("period").parse()?;
+    let order = argument("order").parse().map_err(|e| format!("{e}"))?;
+    let format = argument("format").parse().map_err(|e| format!("{e}"))?;
+    let limit = matches.get_one::<usize>("limit").copied().unwrap_or(10);
+
+    let history = PlayHistory::load(config_path(PLAY_HISTORY_FILE_NAME));
+    let report = Report::new(&history.entries(), &period, order, limit, Local::now());
+    println!("{}", report.export(format));
+
+    Ok(())
+}
@@ -1,10 +1,16 @@
-use crate::queue::RepeatSetting;
+use crate::queue::{RepeatSetting, ShuffleStrategy};
+use crate::sleep::SleepMode;
+use crate::spotify::UriType;
 use crate::spotify_url::SpotifyUrl;
-use std::collections::HashMap;
+use crate::stats::StatsPeriod;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
-use std::sync::OnceLock;
+use std::sync::{OnceLock, RwLock};
 
-use strum_macros::Display;
+use log::warn;
+
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter};
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum SeekInterval {
@@ -13,16 +19,18 @@ pub enum SeekInterval {
     Custom(usize),
 }
 
-#[derive(Display, Clone, Serialize, Deserialize, Debug)]
+#[derive(Display, Clone, Serialize, Deserialize, Debug, Default)]
 #[strum(serialize_all = "lowercase")]
 pub enum TargetMode {
     Current,
+    #[default]
     Selected,
 }
 
-#[derive(Display, Clone, Serialize, Deserialize, Debug)]
+#[derive(Display, Clone, Serialize, Deserialize, Debug, Default)]
 #[strum(serialize_all = "lowercase")]
 pub enum MoveMode {
+    #[default]
     Up,
     Down,
     Left,
@@ -45,41 +53,81 @@ impl Default for MoveAmount {
 }
 
 /// Keys that can be used to sort songs on.
-#[derive(Display, Clone, Serialize, Deserialize, Debug)]
+#[derive(Display, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 #[strum(serialize_all = "lowercase")]
 pub enum SortKey {
+    #[serde(alias = "title")]
     Title,
+    #[serde(alias = "duration")]
     Duration,
+    #[serde(alias = "artist")]
     Artist,
+    #[serde(alias = "album")]
     Album,
+    #[serde(alias = "added")]
     Added,
+    #[serde(alias = "releasedate")]
+    ReleaseDate,
+    #[serde(alias = "tracknumber")]
+    TrackNumber,
+    #[serde(alias = "discnumber")]
+    DiscNumber,
+    #[serde(alias = "popularity")]
+    Popularity,
+    #[serde(alias = "playcount")]
+    PlayCount,
+    #[serde(alias = "random")]
+    Random,
 }
 
-#[derive(Display, Clone, Serialize, Deserialize, Debug)]
+#[derive(Display, Clone, Serialize, Deserialize, Debug, Default)]
 #[strum(serialize_all = "lowercase")]
 pub enum SortDirection {
+    #[default]
+    #[serde(alias = "ascending")]
     Ascending,
+    #[serde(alias = "descending")]
     Descending,
 }
 
-#[derive(Display, Clone, Serialize, Deserialize, Debug)]
+#[derive(Display, Clone, Serialize, Deserialize, Debug, Default, EnumIter)]
 #[strum(serialize_all = "lowercase")]
 pub enum JumpMode {
     Previous,
+    #[default]
     Next,
     Query(String),
 }
 
-#[derive(Display, Clone, Serialize, Deserialize, Debug)]
+#[derive(Display, Clone, Serialize, Deserialize, Debug, Default)]
 #[strum(serialize_all = "lowercase")]
 pub enum ShiftMode {
+    #[default]
     Up,
     Down,
 }
 
-#[derive(Display, Clone, Serialize, Deserialize, Debug)]
+#[derive(Display, Clone, Serialize, Deserialize, Debug, Default)]
+#[strum(serialize_all = "lowercase")]
+pub enum MarkMode {
+    #[default]
+    Toggle,
+    All,
+    None,
+}
+
+#[derive(Display, Clone, Serialize, Deserialize, Debug, Default)]
+#[strum(serialize_all = "lowercase")]
+pub enum PaneMode {
+    #[default]
+    Next,
+    Previous,
+}
+
+#[derive(Display, Clone, Serialize, Deserialize, Debug, Default)]
 #[strum(serialize_all = "lowercase")]
 pub enum GotoMode {
+    #[default]
     Album,
     Artist,
 }
@@ -90,6 +138,12 @@ pub enum SeekDirection {
     Absolute(u32),
 }
 
+impl Default for SeekDirection {
+    fn default() -> Self {
+        Self::Relative(0)
+    }
+}
+
 impl fmt::Display for SeekDirection {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let repr = match self {
@@ -109,6 +163,12 @@ pub enum InsertSource {
     Input(SpotifyUrl),
 }
 
+impl Default for InsertSource {
+    fn default() -> Self {
+        Self::Input(SpotifyUrl::new("", UriType::Track))
+    }
+}
+
 impl fmt::Display for InsertSource {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let repr = match self {
@@ -120,7 +180,7 @@ impl fmt::Display for InsertSource {
     }
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, EnumIter)]
 pub enum Command {
     Quit,
     TogglePlay,
@@ -135,15 +195,30 @@ pub enum Command {
     Save,
     SaveCurrent,
     SaveQueue,
+    ListQueues,
+    StoreQueue(String),
+    LoadQueue(String),
+    AppendQueue(String),
+    DeleteQueue(String),
     Add,
     AddCurrent,
     Delete,
     Focus(String),
+    Pane(PaneMode),
+    Mini(Option<bool>),
     Seek(SeekDirection),
     VolumeUp(u16),
     VolumeDown(u16),
     Repeat(Option<RepeatSetting>),
     Shuffle(Option<bool>),
+    ShuffleStrategy(ShuffleStrategy),
+    Autoplay(Option<bool>),
+    Sleep(SleepMode),
+    StopAfterCurrent(Option<bool>),
+    Stats(Option<StatsPeriod>),
+    Undo,
+    Redo,
+    ClearAutoplay,
     #[cfg(feature = "share_clipboard")]
     Share(TargetMode),
     Back,
@@ -151,6 +226,8 @@ pub enum Command {
     Goto(GotoMode),
     Move(MoveMode, MoveAmount),
     Shift(ShiftMode, Option<i32>),
+    Mark(MarkMode),
+    Visual,
     Search(String),
     Jump(JumpMode),
     Help,
@@ -158,12 +235,14 @@ pub enum Command {
     Noop,
     Insert(InsertSource),
     NewPlaylist(String),
-    Sort(SortKey, SortDirection),
+    Sort(Vec<SortKey>, SortDirection),
     Logout,
     ShowRecommendations(TargetMode),
     Redraw,
     Execute(String),
     Reconnect,
+    #[cfg(feature = "scripting")]
+    Script(String, Vec<String>),
 }
 
 impl fmt::Display for Command {
@@ -171,6 +250,9 @@ impl fmt::Display for Command {
         let mut repr_tokens = vec![self.basename().to_owned()];
         let mut extras_args = match self {
             Self::Focus(tab) => vec![tab.to_owned()],
+            Self::Pane(mode) => vec![mode.to_string()],
+            Self::Mark(MarkMode::Toggle) => vec![],
+            Self::Mark(mode) => vec![mode.to_string()],
             Self::Seek(direction) => vec![direction.to_string()],
             Self::VolumeUp(amount) => vec![amount.to_string()],
             Self::VolumeDown(amount) => vec![amount.to_string()],
@@ -182,6 +264,16 @@ impl fmt::Display for Command {
                 Some(b) => vec![(if *b { "on" } else { "off" }).into()],
                 None => vec![],
             },
+            Self::ShuffleStrategy(strategy) => vec![strategy.to_string()],
+            Self::Autoplay(on) | Self::StopAfterCurrent(on) | Self::Mini(on) => match on {
+                Some(b) => vec![(if *b { "on" } else { "off" }).into()],
+                None => vec![],
+            },
+            Self::Sleep(mode) => vec![mode.to_string()],
+            Self::Stats(period) => match period {
+                Some(period) => vec![period.to_string()],
+                None => vec![],
+            },
             #[cfg(feature = "share_clipboard")]
             Self::Share(mode) => vec![mode.to_string()],
             Self::Open(mode) => vec![mode.to_string()],
@@ -203,15 +295,31 @@ impl fmt::Display for Command {
             },
             Self::Insert(source) => vec![source.to_string()],
             Self::NewPlaylist(name) => vec![name.to_owned()],
-            Self::Sort(key, direction) => vec![key.to_string(), direction.to_string()],
+            Self::StoreQueue(name)
+            | Self::LoadQueue(name)
+            | Self::AppendQueue(name)
+            | Self::DeleteQueue(name) => vec![name.to_owned()],
+            Self::Sort(keys, direction) => vec![
+                keys.iter()
+                    .map(SortKey::to_string)
+                    .collect::<Vec<_>>()
+                    .join(","),
+                direction.to_string(),
+            ],
             Self::ShowRecommendations(mode) => vec![mode.to_string()],
             Self::Execute(cmd) => vec![cmd.to_owned()],
+            #[cfg(feature = "scripting")]
+            Self::Script(_, args) => args.clone(),
             Self::Quit
             | Self::TogglePlay
             | Self::Stop
             | Self::Previous
             | Self::Next
             | Self::Clear
+            | Self::ClearAutoplay
+            | Self::Undo
+            | Self::Redo
+            | Self::ListQueues
             | Self::Queue
             | Self::PlayNext
             | Self::Play
@@ -222,6 +330,7 @@ impl fmt::Display for Command {
             | Self::Add
             | Self::AddCurrent
             | Self::Delete
+            | Self::Visual
             | Self::Back
             | Self::Help
             | Self::ReloadConfig
@@ -235,7 +344,29 @@ impl fmt::Display for Command {
     }
 }
 
+/// The most times a command is repeated by a count prefix.
+const MAX_COUNT: u32 = 999;
+
 impl Command {
+    /// Apply a count prefix, as in `5j`: movements go `count` times as far and other commands are
+    /// repeated `count` times.
+    pub fn with_count(self, count: u32) -> Vec<Self> {
+        let count = count.clamp(1, MAX_COUNT);
+        match self {
+            Self::Move(mode, MoveAmount::Integer(amount)) => {
+                vec![Self::Move(mode, MoveAmount::Integer(amount * count as i32))]
+            }
+            Self::Move(mode, MoveAmount::Float(amount)) => {
+                vec![Self::Move(mode, MoveAmount::Float(amount * count as f32))]
+            }
+            Self::Shift(mode, amount) => {
+                vec![Self::Shift(mode, Some(amount.unwrap_or(1) * count as i32))]
+            }
+            Self::Move(_, MoveAmount::Extreme) => vec![self],
+            command => vec![command; count as usize],
+        }
+    }
+
     pub fn basename(&self) -> &str {
         match self {
             Self::Quit => "quit",
@@ -251,15 +382,30 @@ impl Command {
             Self::Save => "save",
             Self::SaveCurrent => "save current",
             Self::SaveQueue => "save queue",
+            Self::ListQueues => "queues",
+            Self::StoreQueue(_) => "queues save",
+            Self::LoadQueue(_) => "queues load",
+            Self::AppendQueue(_) => "queues append",
+            Self::DeleteQueue(_) => "queues delete",
             Self::Add => "add",
             Self::AddCurrent => "add current",
             Self::Delete => "delete",
             Self::Focus(_) => "focus",
+            Self::Pane(_) => "pane",
+            Self::Mini(_) => "mini",
             Self::Seek(_) => "seek",
             Self::VolumeUp(_) => "volup",
             Self::VolumeDown(_) => "voldown",
             Self::Repeat(_) => "repeat",
             Self::Shuffle(_) => "shuffle",
+            Self::ShuffleStrategy(_) => "shuffle",
+            Self::Autoplay(_) => "autoplay",
+            Self::Sleep(_) => "sleep",
+            Self::StopAfterCurrent(_) => "stopaftercurrent",
+            Self::Stats(_) => "stats",
+            Self::ClearAutoplay => "clear autoplay",
+            Self::Undo => "undo",
+            Self::Redo => "redo",
             #[cfg(feature = "share_clipboard")]
             Self::Share(_) => "share",
             Self::Back => "back",
@@ -267,6 +413,8 @@ impl Command {
             Self::Goto(_) => "goto",
             Self::Move(_, _) => "move",
             Self::Shift(_, _) => "shift",
+            Self::Mark(_) => "mark",
+            Self::Visual => "visual",
             Self::Search(_) => "search",
             Self::Jump(JumpMode::Previous) => "jumpprevious",
             Self::Jump(JumpMode::Next) => "jumpnext",
@@ -282,10 +430,32 @@ impl Command {
             Self::Redraw => "redraw",
             Self::Execute(_) => "exec",
             Self::Reconnect => "reconnect",
+            #[cfg(feature = "scripting")]
+            Self::Script(name, _) => name,
         }
     }
 }
 
+/// The names of the built-in commands, sorted, for completion on the command line.
+pub fn command_names() -> Vec<String> {
+    // The name of `jump` depends on its mode, so every mode is included.
+    let commands = Command::iter().chain(JumpMode::iter().map(Command::Jump));
+
+    // Commands like "save queue" share their name with a command that has an argument.
+    let mut names: Vec<String> = commands
+        .filter_map(|command| {
+            command
+                .basename()
+                .split_whitespace()
+                .next()
+                .map(String::from)
+        })
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
 fn register_aliases(map: &mut HashMap<&str, &str>, cmd: &'static str, names: Vec<&'static str>) {
     for a in names {
         map.insert(a, cmd);
@@ -317,6 +487,76 @@ fn handle_aliases(input: &str) -> &str {
     }
 }
 
+/// The aliases from the `[aliases]` configuration table, mapping a name to the commands it runs.
+static USER_ALIASES: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+
+/// Replace the user defined aliases. Aliases that would shadow a built-in command are ignored.
+pub fn set_user_aliases(aliases: HashMap<String, String>) {
+    USER_ALIASES.write().unwrap().clear();
+
+    let aliases = aliases
+        .into_iter()
+        .filter(|(name, _)| {
+            let builtin = !matches!(parse(name), Err(CommandParseError::NoSuchCommand { .. }));
+            if builtin {
+                warn!("alias \"{name}\" is ignored, as there is a built-in command with that name");
+            }
+            !builtin
+        })
+        .collect();
+    *USER_ALIASES.write().unwrap() = aliases;
+}
+
+/// The user defined aliases, sorted by name.
+pub fn user_aliases() -> BTreeMap<String, String> {
+    USER_ALIASES.read().unwrap().clone()
+}
+
+/// Substitute the positional arguments of an alias: `$1` to `$9` are replaced by the respective
+/// argument and `$*` by all of them. If `template` doesn't contain placeholders, the arguments
+/// are appended to it instead.
+fn substitute_arguments(
+    alias: &str,
+    template: &str,
+    args: &[&str],
+) -> Result<String, CommandParseError> {
+    let mut result = String::new();
+    let mut substituted = false;
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let placeholder = match (c, chars.peek()) {
+            ('$', Some('*')) => Some(None),
+            ('$', Some(digit @ '1'..='9')) => digit.to_digit(10).map(|d| Some(d as usize)),
+            _ => None,
+        };
+        match placeholder {
+            Some(None) => result.push_str(&args.join(" ")),
+            Some(Some(index)) => match args.get(index - 1) {
+                Some(arg) => result.push_str(arg),
+                None => {
+                    return Err(CommandParseError::InsufficientArgs {
+                        cmd: alias.into(),
+                        hint: Some(format!("at least {index} arguments")),
+                    })
+                }
+            },
+            None => {
+                result.push(c);
+                continue;
+            }
+        }
+        chars.next();
+        substituted = true;
+    }
+
+    if !substituted && !args.is_empty() {
+        result.push(' ');
+        result.push_str(&args.join(" "));
+    }
+    Ok(result)
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum CommandParseError {
     NoSuchCommand {
@@ -335,6 +575,9 @@ pub enum CommandParseError {
         arg: String,
         err: String,
     },
+    RecursiveAlias {
+        alias: String,
+    },
 }
 
 impl fmt::Display for CommandParseError {
@@ -361,12 +604,21 @@ impl fmt::Display for CommandParseError {
                 }
             }
             Self::ArgParseError { arg, err } => format!("Error with argument \"{arg}\": {err}"),
+            Self::RecursiveAlias { alias } => format!("Alias \"{alias}\" refers to itself"),
         };
         write!(f, "{formatted}")
     }
 }
 
 pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
+    parse_expanding(input, &mut Vec::new())
+}
+
+/// Parse `input`, where `expanding` are the aliases that are being expanded at the moment.
+fn parse_expanding(
+    input: &str,
+    expanding: &mut Vec<String>,
+) -> Result<Vec<Command>, CommandParseError> {
     let mut command_inputs = vec!["".to_string()];
     let mut command_idx = 0;
     enum ParseState {
@@ -397,6 +649,20 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
         let components: Vec<_> = command_input.split_whitespace().collect();
 
         if let Some((command, args)) = components.split_first() {
+            let user_alias = USER_ALIASES.read().unwrap().get(*command).cloned();
+            if let Some(template) = user_alias {
+                if expanding.iter().any(|alias| alias == command) {
+                    return Err(CommandParseError::RecursiveAlias {
+                        alias: command.to_string(),
+                    });
+                }
+                let expansion = substitute_arguments(command, &template, args)?;
+                expanding.push(command.to_string());
+                commands.append(&mut parse_expanding(&expansion, expanding)?);
+                expanding.pop();
+                continue;
+            }
+
             let command = handle_aliases(command);
             use CommandParseError as E;
             let command = match command {
@@ -405,8 +671,44 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                 "stop" => Command::Stop,
                 "previous" => Command::Previous,
                 "next" => Command::Next,
-                "clear" => Command::Clear,
+                "clear" => match args.first().cloned() {
+                    Some("autoplay") => Ok(Command::ClearAutoplay),
+                    Some(arg) => Err(E::BadEnumArg {
+                        arg: arg.into(),
+                        accept: vec!["autoplay".into()],
+                        optional: true,
+                    }),
+                    None => Ok(Command::Clear),
+                }?,
                 "queue" => Command::Queue,
+                "queues" => match args.split_first() {
+                    None => Ok(Command::ListQueues),
+                    Some((&action, name)) => {
+                        let name = name.join(" ");
+                        match action {
+                            "save" | "load" | "append" | "delete" if name.is_empty() => {
+                                Err(E::InsufficientArgs {
+                                    cmd: format!("{command} {action}"),
+                                    hint: Some("a name".into()),
+                                })
+                            }
+                            "save" => Ok(Command::StoreQueue(name)),
+                            "load" => Ok(Command::LoadQueue(name)),
+                            "append" => Ok(Command::AppendQueue(name)),
+                            "delete" => Ok(Command::DeleteQueue(name)),
+                            _ => Err(E::BadEnumArg {
+                                arg: action.into(),
+                                accept: vec![
+                                    "save".into(),
+                                    "load".into(),
+                                    "append".into(),
+                                    "delete".into(),
+                                ],
+                                optional: true,
+                            }),
+                        }
+                    }
+                }?,
                 "playnext" => Command::PlayNext,
                 "play" => Command::Play,
                 "update" => Command::UpdateLibrary,
@@ -433,11 +735,54 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                 "focus" => {
                     let &target = args.first().ok_or(E::InsufficientArgs {
                         cmd: command.into(),
-                        hint: Some("queue|search|library".into()),
+                        hint: Some("queue|search|library|lyrics".into()),
                     })?;
                     // TODO: this really should be strongly typed
                     Command::Focus(target.into())
                 }
+                "pane" => {
+                    let &pane_mode_raw = args.first().ok_or(E::InsufficientArgs {
+                        cmd: command.into(),
+                        hint: Some("next|previous".into()),
+                    })?;
+                    let pane_mode = match pane_mode_raw {
+                        "next" => Ok(PaneMode::Next),
+                        "previous" => Ok(PaneMode::Previous),
+                        _ => Err(E::BadEnumArg {
+                            arg: pane_mode_raw.into(),
+                            accept: vec!["next".into(), "previous".into()],
+                            optional: false,
+                        }),
+                    }?;
+                    Command::Pane(pane_mode)
+                }
+                "mini" => {
+                    let switch = match args.first().cloned() {
+                        Some("on") => Ok(Some(true)),
+                        Some("off") => Ok(Some(false)),
+                        Some(arg) => Err(E::BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["on".into(), "off".into()],
+                            optional: true,
+                        }),
+                        None => Ok(None),
+                    }?;
+                    Command::Mini(switch)
+                }
+                "mark" => {
+                    let mode = match args.first().cloned() {
+                        Some("all") => Ok(MarkMode::All),
+                        Some("none") => Ok(MarkMode::None),
+                        Some(arg) => Err(E::BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["all".into(), "none".into()],
+                            optional: true,
+                        }),
+                        None => Ok(MarkMode::Toggle),
+                    }?;
+                    Command::Mark(mode)
+                }
+                "visual" => Command::Visual,
                 "seek" => {
                     if args.is_empty() {
                         return Err(E::InsufficientArgs {
@@ -532,7 +877,85 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                     }?;
                     Command::Repeat(mode)
                 }
-                "shuffle" => {
+                "shuffle" => match args.first().cloned() {
+                    Some("on") => Command::Shuffle(Some(true)),
+                    Some("off") => Command::Shuffle(Some(false)),
+                    Some("random") => Command::ShuffleStrategy(ShuffleStrategy::Random),
+                    Some("spread") => Command::ShuffleStrategy(ShuffleStrategy::Spread),
+                    Some("album") => Command::ShuffleStrategy(ShuffleStrategy::Album),
+                    Some("weighted") => Command::ShuffleStrategy(ShuffleStrategy::Weighted),
+                    Some(arg) => {
+                        return Err(E::BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec![
+                                "on".into(),
+                                "off".into(),
+                                "random".into(),
+                                "spread".into(),
+                                "album".into(),
+                                "weighted".into(),
+                            ],
+                            optional: true,
+                        })
+                    }
+                    None => Command::Shuffle(None),
+                },
+                "undo" => Command::Undo,
+                "redo" => Command::Redo,
+                "autoplay" => {
+                    let switch = match args.first().cloned() {
+                        Some("on") => Ok(Some(true)),
+                        Some("off") => Ok(Some(false)),
+                        Some(arg) => Err(E::BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["on".into(), "off".into()],
+                            optional: true,
+                        }),
+                        None => Ok(None),
+                    }?;
+                    Command::Autoplay(switch)
+                }
+                "sleep" => {
+                    let mode = match args {
+                        [] => Err(E::InsufficientArgs {
+                            cmd: command.into(),
+                            hint: Some("a duration|end-of-track|after <COUNT> tracks|off".into()),
+                        }),
+                        ["off"] => Ok(SleepMode::Off),
+                        ["end-of-track"] => Ok(SleepMode::Tracks(1)),
+                        ["after", count] | ["after", count, "track" | "tracks"] => count
+                            .parse::<usize>()
+                            .map(SleepMode::Tracks)
+                            .map_err(|err| E::ArgParseError {
+                                arg: count.to_string(),
+                                err: err.to_string(),
+                            }),
+                        _ => {
+                            let duration = args.join(" ");
+                            parse_duration::parse(&duration)
+                                .map(SleepMode::Duration)
+                                .map_err(|err| E::ArgParseError {
+                                    arg: duration,
+                                    err: err.to_string(),
+                                })
+                        }
+                    }?;
+                    Command::Sleep(mode)
+                }
+                "stats" => {
+                    let period = match args {
+                        [] => Ok(None),
+                        _ => {
+                            let period = args.join(" ");
+                            period
+                                .parse()
+                                .map(Some)
+                                .map_err(|err| E::ArgParseError { arg: period, err })
+                        }
+                    }?;
+                    Command::Stats(period)
+                }
+                "stopaftercurrent" => {
                     let switch = match args.first().cloned() {
                         Some("on") => Ok(Some(true)),
                         Some("off") => Ok(Some(false)),
@@ -543,7 +966,7 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                         }),
                         None => Ok(None),
                     }?;
-                    Command::Shuffle(switch)
+                    Command::StopAfterCurrent(switch)
                 }
                 #[cfg(feature = "share_clipboard")]
                 "share" => {
@@ -726,28 +1149,43 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                     }?
                 }
                 "sort" => {
-                    let &key_raw = args.first().ok_or(E::InsufficientArgs {
+                    let &keys_raw = args.first().ok_or(E::InsufficientArgs {
                         cmd: command.into(),
                         hint: Some("a sort key".into()),
                     })?;
-                    let key = match key_raw {
-                        "title" => Ok(SortKey::Title),
-                        "duration" => Ok(SortKey::Duration),
-                        "album" => Ok(SortKey::Album),
-                        "added" => Ok(SortKey::Added),
-                        "artist" => Ok(SortKey::Artist),
-                        _ => Err(E::BadEnumArg {
-                            arg: key_raw.into(),
-                            accept: vec![
-                                "title".into(),
-                                "duration".into(),
-                                "album".into(),
-                                "added".into(),
-                                "artist".into(),
-                            ],
-                            optional: false,
-                        }),
-                    }?;
+                    let keys = keys_raw
+                        .split(',')
+                        .map(|key_raw| match key_raw {
+                            "title" => Ok(SortKey::Title),
+                            "duration" => Ok(SortKey::Duration),
+                            "album" => Ok(SortKey::Album),
+                            "added" => Ok(SortKey::Added),
+                            "artist" => Ok(SortKey::Artist),
+                            "releasedate" => Ok(SortKey::ReleaseDate),
+                            "tracknumber" => Ok(SortKey::TrackNumber),
+                            "discnumber" => Ok(SortKey::DiscNumber),
+                            "popularity" => Ok(SortKey::Popularity),
+                            "playcount" => Ok(SortKey::PlayCount),
+                            "random" => Ok(SortKey::Random),
+                            _ => Err(E::BadEnumArg {
+                                arg: key_raw.into(),
+                                accept: vec![
+                                    "title".into(),
+                                    "duration".into(),
+                                    "album".into(),
+                                    "added".into(),
+                                    "artist".into(),
+                                    "releasedate".into(),
+                                    "tracknumber".into(),
+                                    "discnumber".into(),
+                                    "popularity".into(),
+                                    "playcount".into(),
+                                    "random".into(),
+                                ],
+                                optional: false,
+                            }),
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
                     let direction = match args.get(1).copied() {
                         Some("a" | "asc" | "ascending") => Ok(SortDirection::Ascending),
                         Some("d" | "desc" | "descending") => Ok(SortDirection::Descending),
@@ -765,7 +1203,7 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                         }),
                         None => Ok(SortDirection::Ascending),
                     }?;
-                    Command::Sort(key, direction)
+                    Command::Sort(keys, direction)
                 }
                 "logout" => Command::Logout,
                 "similar" => {
@@ -787,6 +1225,10 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                 "redraw" => Command::Redraw,
                 "exec" => Command::Execute(args.join(" ")),
                 "reconnect" => Command::Reconnect,
+                #[cfg(feature = "scripting")]
+                name if crate::scripting::is_command(name) => {
+                    Command::Script(name.into(), args.iter().map(|a| a.to_string()).collect())
+                }
                 _ => {
                     return Err(E::NoSuchCommand {
                         cmd: command.into(),
@@ -798,3 +1240,42 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
     }
     Ok(commands)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_alias_arguments() {
+        let args = ["a", "b"];
+
+        assert_eq!(
+            substitute_arguments("x", "search $2; jump $1", &args).unwrap(),
+            "search b; jump a"
+        );
+        assert_eq!(
+            substitute_arguments("x", "exec echo $*", &args).unwrap(),
+            "exec echo a b"
+        );
+        assert_eq!(
+            substitute_arguments("x", "search", &args).unwrap(),
+            "search a b"
+        );
+        assert_eq!(
+            substitute_arguments("x", "exec echo $HOME", &[]).unwrap(),
+            "exec echo $HOME"
+        );
+        assert!(substitute_arguments("x", "volup $3", &args).is_err());
+    }
+
+    #[test]
+    fn applies_counts() {
+        let moved = Command::Move(MoveMode::Down, MoveAmount::Integer(2)).with_count(5);
+        assert!(matches!(
+            moved[..],
+            [Command::Move(MoveMode::Down, MoveAmount::Integer(10))]
+        ));
+        assert_eq!(Command::Next.with_count(3).len(), 3);
+        assert_eq!(Command::Next.with_count(0).len(), 1);
+    }
+}
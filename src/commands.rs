@@ -2,24 +2,34 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::application::UserData;
 use crate::command::{
-    parse, Command, GotoMode, JumpMode, MoveAmount, MoveMode, SeekDirection, ShiftMode, TargetMode,
+    parse, set_user_aliases, user_aliases, Command, GotoMode, JumpMode, MarkMode, MoveAmount,
+    MoveMode, PaneMode, SeekDirection, ShiftMode, TargetMode,
 };
-use crate::config::{user_configuration_directory, Config};
+use crate::config::{user_configuration_directory, Config, Keybinding};
 use crate::events::EventManager;
 use crate::ext_traits::CursiveExt;
+#[cfg(feature = "scripting")]
+use crate::hooks::HookEvent;
 use crate::library::Library;
 use crate::queue::{Queue, RepeatSetting};
+#[cfg(feature = "scripting")]
+use crate::scripting::Scripts;
+use crate::sleep::SleepMode;
 use crate::spotify::{Spotify, VOLUME_PERCENT};
+use crate::stats::StatsPeriod;
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
 use crate::ui::contextmenu::{
     AddToPlaylistMenu, ContextMenu, SelectArtistActionMenu, SelectArtistMenu,
 };
 use crate::ui::help::HelpView;
+use crate::ui::keysequence::{KeySequence, Keymap};
 use crate::ui::layout::Layout;
 use crate::ui::modal::Modal;
+use crate::ui::queue::QueueView;
 use crate::ui::search_results::SearchResultsView;
+use crate::ui::stats::StatsView;
+use crate::utils::format_duration;
 use cursive::event::{Event, Key};
 use cursive::traits::View;
 use cursive::views::Dialog;
@@ -27,6 +37,7 @@ use cursive::Cursive;
 use log::{debug, error, info};
 use ncspot::CONFIGURATION_FILE_NAME;
 use std::cell::RefCell;
+use strum_macros::{Display, EnumString};
 
 pub enum CommandResult {
     Consumed(Option<String>),
@@ -35,14 +46,53 @@ pub enum CommandResult {
     Ignored,
 }
 
+/// A kind of view that can have its own keybindings, configured in `[keybindings.<context>]`.
+/// They take precedence over the global keybindings while such a view is focused.
+#[derive(Display, EnumString, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[strum(serialize_all = "snake_case")]
+pub enum BindingContext {
+    Queue,
+    Library,
+    Search,
+    Playlist,
+    Album,
+    ContextMenu,
+}
+
+/// The names of the popup menus that use the [BindingContext::ContextMenu] keybindings.
+const MENU_NAMES: [&str; 4] = [
+    "contextmenu",
+    "addtrackmenu",
+    "selectartist",
+    "selectartistaction",
+];
+
+/// The context that keys are currently looked up in, if any.
+fn binding_context(s: &mut Cursive) -> Option<BindingContext> {
+    let screen = s.screen_mut();
+    if MENU_NAMES
+        .iter()
+        .any(|name| screen.find_layer_from_name(name).is_some())
+    {
+        return Some(BindingContext::ContextMenu);
+    }
+    s.call_on_name("main", |v: &mut Layout| v.binding_context())
+        .flatten()
+}
+
 pub struct CommandManager {
     aliases: HashMap<String, String>,
     bindings: RefCell<HashMap<String, Vec<Command>>>,
+    context_bindings: RefCell<HashMap<BindingContext, HashMap<String, Vec<Command>>>>,
+    /// The keys that callbacks were registered for by [CommandManager::register_keybindings].
+    registered_keys: RefCell<Vec<Event>>,
     spotify: Spotify,
     queue: Arc<Queue>,
     library: Arc<Library>,
     config: Arc<Config>,
     events: EventManager,
+    #[cfg(feature = "scripting")]
+    scripts: Scripts,
 }
 
 impl CommandManager {
@@ -53,15 +103,24 @@ impl CommandManager {
         config: Arc<Config>,
         events: EventManager,
     ) -> Self {
+        // Scripts register their commands first, so they can be used in keybindings.
+        #[cfg(feature = "scripting")]
+        let scripts = Scripts::new(queue.clone(), library.clone(), spotify.clone());
+        set_user_aliases(config.values().aliases.clone().unwrap_or_default());
         let bindings = RefCell::new(Self::get_bindings(&config));
+        let context_bindings = RefCell::new(Self::get_context_bindings(&config));
         Self {
             aliases: HashMap::new(),
             bindings,
+            context_bindings,
+            registered_keys: RefCell::new(Vec::new()),
             spotify,
             queue,
             library,
             config,
             events,
+            #[cfg(feature = "scripting")]
+            scripts,
         }
     }
 
@@ -72,26 +131,55 @@ impl CommandManager {
         } else {
             HashMap::new()
         };
-        let custom_bindings: Option<HashMap<String, String>> = config.keybindings.clone();
 
-        for (key, commands) in custom_bindings.unwrap_or_default() {
-            match parse(&commands) {
-                Ok(cmds) => {
-                    info!("Custom keybinding: {} -> {:?}", key, cmds);
-                    kb.insert(key, cmds);
-                }
-                Err(err) => {
-                    error!(
-                        "Invalid command(s) for key {}-\"{}\": {}",
-                        key, commands, err
-                    );
-                }
+        for (key, value) in config.keybindings.clone().unwrap_or_default() {
+            if let Keybinding::Commands(commands) = value {
+                Self::parse_binding(&mut kb, key, &commands);
             }
         }
 
         kb
     }
 
+    /// The keybindings of the `[keybindings.<context>]` sections.
+    pub fn get_context_bindings(
+        config: &Config,
+    ) -> HashMap<BindingContext, HashMap<String, Vec<Command>>> {
+        let mut contexts = HashMap::new();
+
+        for (name, value) in config.values().keybindings.clone().unwrap_or_default() {
+            let Keybinding::Context(bindings) = value else {
+                continue;
+            };
+            let Ok(context) = name.parse::<BindingContext>() else {
+                error!("Unknown keybinding context: \"{}\"", name);
+                continue;
+            };
+            let kb = contexts.entry(context).or_insert_with(HashMap::new);
+            for (key, commands) in bindings {
+                Self::parse_binding(kb, key, &commands);
+            }
+        }
+
+        contexts
+    }
+
+    /// Parse the custom keybinding `key` -> `commands` into `kb`.
+    fn parse_binding(kb: &mut HashMap<String, Vec<Command>>, key: String, commands: &str) {
+        match parse(commands) {
+            Ok(cmds) => {
+                info!("Custom keybinding: {} -> {:?}", key, cmds);
+                kb.insert(key, cmds);
+            }
+            Err(err) => {
+                error!(
+                    "Invalid command(s) for key {}-\"{}\": {}",
+                    key, commands, err
+                );
+            }
+        }
+    }
+
     pub fn register_aliases<S: Into<String>>(&mut self, name: S, aliases: Vec<S>) {
         let name = name.into();
         for a in aliases {
@@ -124,6 +212,7 @@ impl CommandManager {
                     s.queuestate.random_order = self.queue.get_random_order();
                     s.queuestate.current_track = self.queue.get_current_index();
                     s.queuestate.track_progress = self.spotify.get_current_progress();
+                    s.queuestate.auto_added_items = self.queue.get_auto_added();
                 });
                 self.config.save_state();
                 s.quit();
@@ -132,6 +221,8 @@ impl CommandManager {
             Command::Redraw => {
                 info!("Redrawing screen");
                 s.clear();
+                #[cfg(feature = "cover")]
+                s.call_on_name("cover", |v: &mut crate::ui::cover::CoverView| v.reset());
                 Ok(None)
             }
             Command::Stop => {
@@ -161,6 +252,43 @@ impl CommandManager {
                 s.add_layer(Modal::new(confirmation));
                 Ok(None)
             }
+            Command::Undo => self
+                .library
+                .history
+                .undo(&self.queue, &self.library)
+                .map(Some),
+            Command::Redo => self
+                .library
+                .history
+                .redo(&self.queue, &self.library)
+                .map(Some),
+            Command::ListQueues => {
+                if self.queue.saved_queues().is_empty() {
+                    return Ok(Some("No saved queues".to_string()));
+                }
+                s.add_layer(QueueView::saved_queues_dialog(self.queue.clone()));
+                Ok(None)
+            }
+            Command::StoreQueue(name) => {
+                self.queue.save_as(name);
+                Ok(Some(format!("Saved queue \"{name}\"")))
+            }
+            Command::LoadQueue(name) => self
+                .queue
+                .load_saved(name)
+                .map(|count| Some(format!("Loaded {count} tracks from \"{name}\""))),
+            Command::AppendQueue(name) => self
+                .queue
+                .append_saved(name)
+                .map(|count| Some(format!("Added {count} tracks from \"{name}\""))),
+            Command::DeleteQueue(name) => self
+                .queue
+                .delete_saved(name)
+                .map(|_| Some(format!("Deleted saved queue \"{name}\""))),
+            Command::ClearAutoplay => {
+                self.queue.clear_auto_added();
+                Ok(None)
+            }
             Command::UpdateLibrary => {
                 self.library.update_library();
                 Ok(None)
@@ -174,6 +302,31 @@ impl CommandManager {
                 self.queue.set_shuffle(mode);
                 Ok(None)
             }
+            Command::Autoplay(mode) => {
+                let mode = mode.unwrap_or_else(|| !self.queue.get_autoplay());
+                self.queue.set_autoplay(mode);
+                Ok(None)
+            }
+            Command::Sleep(mode) => {
+                self.queue.set_sleep_timer(mode);
+                Ok(Some(match mode {
+                    SleepMode::Off => "Sleep timer cancelled".to_string(),
+                    SleepMode::Duration(duration) => {
+                        format!("Sleeping in {}", format_duration(duration))
+                    }
+                    SleepMode::Tracks(1) => "Sleeping at the end of this track".to_string(),
+                    SleepMode::Tracks(count) => format!("Sleeping after {count} tracks"),
+                }))
+            }
+            Command::StopAfterCurrent(mode) => {
+                let mode = mode.unwrap_or_else(|| !self.queue.get_stop_after_current());
+                self.queue.set_stop_after_current(mode);
+                Ok(None)
+            }
+            Command::ShuffleStrategy(strategy) => {
+                self.queue.set_shuffle_strategy(*strategy);
+                Ok(None)
+            }
             Command::Repeat(mode) => {
                 let mode = mode.unwrap_or_else(|| match self.queue.get_repeat() {
                     RepeatSetting::None => RepeatSetting::RepeatPlaylist,
@@ -209,7 +362,17 @@ impl CommandManager {
                 Ok(None)
             }
             Command::Help => {
-                let view = Box::new(HelpView::new(self.bindings.borrow().clone()));
+                let view = Box::new(HelpView::new(
+                    self.bindings.borrow().clone(),
+                    self.context_bindings.borrow().clone(),
+                    user_aliases(),
+                ));
+                s.call_on_name("main", move |v: &mut Layout| v.push_view(view));
+                Ok(None)
+            }
+            Command::Stats(period) => {
+                let period = period.clone().unwrap_or(StatsPeriod::All);
+                let view = Box::new(StatsView::new(self.library.clone(), period));
                 s.call_on_name("main", move |v: &mut Layout| v.push_view(view));
                 Ok(None)
             }
@@ -230,9 +393,18 @@ impl CommandManager {
                 let theme = self.config.build_theme();
                 s.set_theme(theme);
 
-                // update bindings
+                // update the smart playlists
+                self.library.refresh_playlists();
+
+                // update the split layout
+                s.call_on_name("main", |v: &mut Layout| v.reload_split());
+
+                // update aliases and bindings, which may use them
+                set_user_aliases(self.config.values().aliases.clone().unwrap_or_default());
                 self.unregister_keybindings(s);
                 self.bindings.replace(Self::get_bindings(&self.config));
+                self.context_bindings
+                    .replace(Self::get_context_bindings(&self.config));
                 self.register_keybindings(s);
                 Ok(None)
             }
@@ -283,13 +455,19 @@ impl CommandManager {
                 self.spotify.shutdown();
                 Ok(None)
             }
+            #[cfg(feature = "scripting")]
+            Command::Script(name, args) => {
+                let result = self.scripts.run_command(name, args);
+                self.run_script_commands(s);
+                result
+            }
             Command::AddCurrent => {
                 if let Some(track) = self.queue.get_current() {
                     if let Some(track) = track.track() {
                         let dialog = ContextMenu::add_track_dialog(
                             self.library.clone(),
                             self.queue.get_spotify(),
-                            track,
+                            vec![track],
                         );
                         s.add_layer(dialog);
                     }
@@ -302,6 +480,8 @@ impl CommandManager {
                 }
                 Ok(None)
             }
+            // Views without marked items have nothing to unmark.
+            Command::Mark(MarkMode::None) => Ok(None),
 
             Command::Queue
             | Command::PlayNext
@@ -311,11 +491,15 @@ impl CommandManager {
             | Command::Add
             | Command::Delete
             | Command::Focus(_)
+            | Command::Pane(_)
+            | Command::Mini(_)
             | Command::Back
             | Command::Open(_)
             | Command::Goto(_)
             | Command::Move(_, _)
             | Command::Shift(_, _)
+            | Command::Mark(_)
+            | Command::Visual
             | Command::Jump(_)
             | Command::Insert(_)
             | Command::ShowRecommendations(_)
@@ -372,41 +556,118 @@ impl CommandManager {
         s.on_event(Event::Refresh);
     }
 
-    pub fn register_keybinding<E: Into<cursive::event::Event>>(
-        &self,
-        cursive: &mut Cursive,
-        event: E,
-        commands: Vec<Command>,
-    ) {
-        cursive.add_global_callback(event, move |s| {
-            if let Some(data) = s.user_data::<UserData>().cloned() {
-                for command in commands.clone().into_iter() {
-                    data.cmd.handle(s, command);
+    /// Let scripts react to `event`.
+    #[cfg(feature = "scripting")]
+    pub fn handle_event(&self, s: &mut Cursive, event: HookEvent) {
+        self.scripts.handle_event(event, self.queue.get_current());
+        self.run_script_commands(s);
+    }
+
+    /// Run the commands that scripts asked for.
+    #[cfg(feature = "scripting")]
+    fn run_script_commands(&self, s: &mut Cursive) {
+        for input in self.scripts.take_pending() {
+            let commands = match parse(&input) {
+                Ok(commands) => commands,
+                Err(e) => {
+                    error!("invalid command \"{input}\" from script: {e}");
+                    continue;
+                }
+            };
+            for cmd in commands {
+                if let Err(e) = self.handle_callbacks(s, &cmd) {
+                    error!("command \"{cmd}\" from script failed: {e}");
                 }
             }
-        });
+        }
     }
 
     pub fn unregister_keybindings(&self, cursive: &mut Cursive) {
-        let kb = self.bindings.borrow();
+        for key in self.registered_keys.take() {
+            cursive.clear_global_callbacks(key);
+        }
+    }
 
-        for (k, _v) in kb.iter() {
-            if let Some(binding) = Self::parse_keybinding(k) {
-                cursive.clear_global_callbacks(binding);
+    /// Parse the keybinding `kb`, which can be a sequence of keys separated by spaces.
+    /// `<leader>` is replaced by `leader`.
+    fn parse_key_sequence(kb: &str, commands: &[Command], leader: &str) -> Option<KeySequence> {
+        let keys: Vec<String> = kb
+            .split_whitespace()
+            .map(|key| match key {
+                "<leader>" => leader.to_string(),
+                key => key.to_string(),
+            })
+            .collect();
+        let events = keys
+            .iter()
+            .map(|key| Self::parse_keybinding(key))
+            .collect::<Option<Vec<_>>>()
+            .filter(|events| !events.is_empty())?;
+        Some(KeySequence {
+            keys,
+            events,
+            commands: commands.to_vec(),
+        })
+    }
+
+    /// Parse the keybindings `kb` into key sequences.
+    fn parse_key_sequences(kb: &HashMap<String, Vec<Command>>, leader: &str) -> Vec<KeySequence> {
+        let mut sequences = Vec::new();
+        for (k, v) in kb.iter() {
+            match Self::parse_key_sequence(k, v, leader) {
+                Some(sequence) => sequences.push(sequence),
+                None => error!("Could not parse keybinding: \"{}\"", k),
             }
         }
+        sequences
     }
 
     pub fn register_keybindings(&self, cursive: &mut Cursive) {
-        let kb = self.bindings.borrow();
+        let config = self.config.values();
+        let leader = config.leader_key.clone().unwrap_or_else(|| "\\".into());
+        let timeout = Duration::from_millis(config.key_sequence_timeout.unwrap_or(1000));
+
+        let global = Self::parse_key_sequences(&self.bindings.borrow(), &leader);
+        let mut contexts = HashMap::new();
+        for (context, kb) in self.context_bindings.borrow().iter() {
+            let mut sequences = Self::parse_key_sequences(kb, &leader);
+            // Global keybindings still apply, unless the context binds the same keys.
+            let inherited: Vec<KeySequence> = global
+                .iter()
+                .filter(|g| !sequences.iter().any(|s| s.events == g.events))
+                .cloned()
+                .collect();
+            sequences.extend(inherited);
+            contexts.insert(*context, Arc::new(sequences));
+        }
+        let keymap = Arc::new(Keymap::new(Arc::new(global), contexts, timeout));
 
-        for (k, v) in kb.iter() {
-            if let Some(binding) = Self::parse_keybinding(k) {
-                self.register_keybinding(cursive, binding, v.clone());
-            } else {
-                error!("Could not parse keybinding: \"{}\"", k);
+        let mut registered = Vec::new();
+        for sequence in keymap.all_sequences() {
+            let first = &sequence.events[0];
+            if !registered.contains(first) {
+                registered.push(first.clone());
+            }
+        }
+
+        // Digits can start a count prefix, as in "5j".
+        for digit in '1'..='9' {
+            let event = Event::Char(digit);
+            if !registered.contains(&event) {
+                registered.push(event);
             }
         }
+
+        for event in registered.iter().cloned() {
+            let keymap = keymap.clone();
+            let key = event.clone();
+            cursive.add_global_callback(event, move |s| {
+                let context = binding_context(s);
+                keymap.press(s, context, key.clone());
+            });
+        }
+
+        self.registered_keys.replace(registered);
     }
 
     fn default_keybindings() -> HashMap<String, Vec<Command>> {
@@ -414,6 +675,7 @@ impl CommandManager {
 
         kb.insert("q".into(), vec![Command::Quit]);
         kb.insert("Ctrl+l".into(), vec![Command::Redraw]);
+        kb.insert("Ctrl+w".into(), vec![Command::Pane(PaneMode::Next)]);
         kb.insert("Shift+p".into(), vec![Command::TogglePlay]);
         kb.insert("Shift+u".into(), vec![Command::UpdateLibrary]);
         kb.insert("Shift+s".into(), vec![Command::Stop]);
@@ -440,6 +702,8 @@ impl CommandManager {
         kb.insert("s".into(), vec![Command::Save]);
         kb.insert("Ctrl+s".into(), vec![Command::SaveQueue]);
         kb.insert("d".into(), vec![Command::Delete]);
+        kb.insert("u".into(), vec![Command::Undo]);
+        kb.insert("Ctrl+r".into(), vec![Command::Redo]);
         kb.insert(
             "f".into(),
             vec![Command::Seek(SeekDirection::Relative(1000))],
@@ -473,8 +737,11 @@ impl CommandManager {
         kb.insert("F1".into(), vec![Command::Focus("queue".into())]);
         kb.insert("F2".into(), vec![Command::Focus("search".into())]);
         kb.insert("F3".into(), vec![Command::Focus("library".into())]);
+        kb.insert("F4".into(), vec![Command::Focus("history".into())]);
+        kb.insert("F5".into(), vec![Command::Focus("lyrics".into())]);
         #[cfg(feature = "cover")]
         kb.insert("F8".into(), vec![Command::Focus("cover".into())]);
+        kb.insert("F9".into(), vec![Command::Mini(None)]);
         kb.insert("?".into(), vec![Command::Help]);
         kb.insert("Backspace".into(), vec![Command::Back]);
 
@@ -528,6 +795,14 @@ impl CommandManager {
             "End".into(),
             vec![Command::Move(MoveMode::Down, MoveAmount::Extreme)],
         );
+        kb.insert(
+            "g g".into(),
+            vec![Command::Move(MoveMode::Up, MoveAmount::Extreme)],
+        );
+        kb.insert(
+            "Shift+g".into(),
+            vec![Command::Move(MoveMode::Down, MoveAmount::Extreme)],
+        );
         kb.insert(
             "k".into(),
             vec![Command::Move(MoveMode::Up, Default::default())],
@@ -562,6 +837,16 @@ impl CommandManager {
             vec![Command::Move(MoveMode::Right, Default::default())],
         );
 
+        kb.insert("v".into(), vec![Command::Visual]);
+        kb.insert(
+            "Shift+v".into(),
+            vec![
+                Command::Mark(MarkMode::Toggle),
+                Command::Move(MoveMode::Down, Default::default()),
+            ],
+        );
+        kb.insert("Esc".into(), vec![Command::Mark(MarkMode::None)]);
+
         kb.insert("Shift+Up".into(), vec![Command::Shift(ShiftMode::Up, None)]);
         kb.insert(
             "Shift+Down".into(),
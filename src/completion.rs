@@ -0,0 +1,239 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::command::{command_names, parse, user_aliases, CommandParseError};
+use crate::library::Library;
+use crate::queue::Queue;
+use crate::utils::expand_home;
+
+/// Stands in for the argument that is completed while the command line is parsed, so the parser
+/// reports the values that it accepts in its place.
+const PLACEHOLDER: &str = "\u{1}";
+
+/// The actions of the `queues` command that take the name of a saved queue.
+const QUEUE_ACTIONS: [&str; 4] = ["save", "load", "append", "delete"];
+
+/// Completes commands that are typed on the command line.
+pub struct Completer {
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+}
+
+impl Completer {
+    pub fn new(queue: Arc<Queue>, library: Arc<Library>) -> Self {
+        Self { queue, library }
+    }
+
+    /// The completions of the last word of `input`, the command line without its `:` prefix.
+    /// Every completion is the whole command line with the last word completed.
+    pub fn complete(&self, input: &str) -> Vec<String> {
+        let queues: Vec<String> = self
+            .queue
+            .saved_queues()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        let playlists: Vec<String> = self
+            .library
+            .playlists
+            .read()
+            .unwrap()
+            .iter()
+            .map(|playlist| playlist.name.clone())
+            .collect();
+        complete(input, &queues, &playlists)
+    }
+}
+
+/// The names that can be completed as the first word of a command.
+fn names() -> Vec<String> {
+    let mut names = command_names();
+    names.extend(user_aliases().into_keys());
+    #[cfg(feature = "scripting")]
+    names.extend(crate::scripting::command_names());
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Complete `input` like [Completer::complete], with `queues` being the names of the saved queues
+/// and `playlists` the names of the playlists in the library.
+fn complete(input: &str, queues: &[String], playlists: &[String]) -> Vec<String> {
+    // Only the last of several commands separated by ";" is completed.
+    let segment = input.rsplit(';').next().unwrap_or(input);
+    let words: Vec<&str> = segment.split_whitespace().collect();
+    let (word, previous) = match words.split_last() {
+        Some((last, previous)) if !segment.ends_with(char::is_whitespace) => (*last, previous),
+        _ => ("", &words[..]),
+    };
+
+    let (typed, candidates) = match previous {
+        [] => (word, names()),
+        ["exec", ..] => (word, complete_path(word)),
+        ["queues", action, ..] if QUEUE_ACTIONS.contains(action) => (
+            name_after(segment, &["queues", *action]).unwrap_or(word),
+            queues.to_vec(),
+        ),
+        ["newplaylist", ..] => (
+            name_after(segment, &["newplaylist"]).unwrap_or(word),
+            playlists.to_vec(),
+        ),
+        [command, args @ ..] => (word, complete_argument(command, args, word)),
+    };
+
+    let prefix = &input[..input.len() - typed.len()];
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(typed))
+        .map(|candidate| format!("{prefix}{candidate}"))
+        .collect()
+}
+
+/// The rest of `segment` after the leading `words`. Names of saved queues and playlists can contain
+/// spaces, so the whole name is completed.
+fn name_after<'a>(segment: &'a str, words: &[&str]) -> Option<&'a str> {
+    words
+        .iter()
+        .try_fold(segment, |rest, word| rest.trim_start().strip_prefix(word))
+        .map(str::trim_start)
+}
+
+/// The values that `command` accepts for the argument `word` after `args`, found by parsing the
+/// command with a placeholder in place of the argument.
+fn complete_argument(command: &str, args: &[&str], word: &str) -> Vec<String> {
+    // Lists like the keys of `sort` are separated by commas, only the last item is completed.
+    let before = match word.rfind(',') {
+        Some(index) => &word[..=index],
+        None => "",
+    };
+
+    let mut probe = vec![command];
+    probe.extend(args);
+    let placeholder = format!("{before}{PLACEHOLDER}");
+    probe.push(&placeholder);
+
+    let accept = match parse(&probe.join(" ")) {
+        Err(CommandParseError::BadEnumArg { arg, accept, .. }) if arg == PLACEHOLDER => accept,
+        // Arguments like the screen of `focus` aren't validated, but listed in the hint.
+        _ => match parse(&probe[..probe.len() - 1].join(" ")) {
+            Err(CommandParseError::InsufficientArgs {
+                hint: Some(hint), ..
+            }) if before.is_empty() => hint
+                .split('|')
+                .filter(|value| !value.contains(char::is_whitespace))
+                .map(String::from)
+                .collect(),
+            _ => Vec::new(),
+        },
+    };
+
+    accept
+        .into_iter()
+        .map(|value| format!("{before}{value}"))
+        .collect()
+}
+
+/// The files and directories that start with the path `word`. Directories end with a `/`.
+fn complete_path(word: &str) -> Vec<String> {
+    let (directory, name) = match word.rfind('/') {
+        Some(index) => word.split_at(index + 1),
+        None => ("", word),
+    };
+    let path = match directory {
+        "" => Some(PathBuf::from(".")),
+        directory => expand_home(directory),
+    };
+    let Some(entries) = path.and_then(|path| fs::read_dir(path).ok()) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            // Hidden files are only completed if their name was started.
+            if !file_name.starts_with(name) || (file_name.starts_with('.') && name.is_empty()) {
+                return None;
+            }
+            let suffix = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{directory}{file_name}{suffix}"))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// The longest prefix that all `completions` have in common.
+pub fn common_prefix(completions: &[String]) -> String {
+    let Some((first, rest)) = completions.split_first() else {
+        return String::new();
+    };
+    let mut length = first.len();
+    for completion in rest {
+        length = first
+            .char_indices()
+            .zip(completion.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map(|((index, c), _)| index + c.len_utf8())
+            .unwrap_or(0)
+            .min(length);
+    }
+    first[..length].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completes_commands_and_arguments() {
+        assert_eq!(complete("volu", &[], &[]), vec!["volup"]);
+        assert_eq!(
+            complete("jum", &[], &[]),
+            vec!["jump", "jumpnext", "jumpprevious"]
+        );
+        assert_eq!(
+            complete("next; sto", &[], &[]),
+            vec!["next; stop", "next; stopaftercurrent"]
+        );
+        assert_eq!(
+            complete("goto a", &[], &[]),
+            vec!["goto album", "goto artist"]
+        );
+        assert_eq!(complete("focus q", &[], &[]), vec!["focus queue"]);
+        assert_eq!(
+            complete("sort title,al", &[], &[]),
+            vec!["sort title,album"]
+        );
+        assert_eq!(
+            complete("sort title desc", &[], &[]),
+            vec!["sort title desc", "sort title descending"]
+        );
+        assert!(complete("volup ", &[], &[]).is_empty());
+
+        let queues = vec!["road trip".to_string(), "rainy day".to_string()];
+        assert_eq!(
+            complete("queues load ro", &queues, &[]),
+            vec!["queues load road trip"]
+        );
+
+        let playlists = vec!["Summer Hits".to_string(), "Sunday Morning".to_string()];
+        assert_eq!(
+            complete("newplaylist Sun", &[], &playlists),
+            vec!["newplaylist Sunday Morning"]
+        );
+        assert_eq!(
+            complete("newplaylist ", &[], &playlists),
+            vec!["newplaylist Summer Hits", "newplaylist Sunday Morning"]
+        );
+    }
+
+    #[test]
+    fn finds_common_prefix() {
+        let completions = vec!["stop".to_string(), "stopaftercurrent".to_string()];
+        assert_eq!(common_prefix(&completions), "stop");
+        assert_eq!(common_prefix(&[]), "");
+    }
+}
@@ -1,18 +1,26 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 use std::path::PathBuf;
-use std::sync::{RwLock, RwLockReadGuard};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
 use std::{fs, process};
 
+use chrono::{DateTime, Utc};
 use cursive::theme::Theme;
 use log::{debug, error};
 use ncspot::{CONFIGURATION_FILE_NAME, USER_STATE_FILE_NAME};
 use platform_dirs::AppDirs;
+use rand::Rng;
 
 use crate::command::{SortDirection, SortKey};
+use crate::hooks::HooksConfig;
 use crate::model::playable::Playable;
 use crate::queue;
+use crate::scrobbler::ScrobblingConfig;
 use crate::serialization::{Serializer, CBOR, TOML};
+use crate::sleep::SleepAction;
+use crate::smart_playlist::SmartPlaylistRule;
+use crate::template::Template;
 
 pub const CACHE_VERSION: u16 = 1;
 pub const DEFAULT_COMMAND_KEY: char = ':';
@@ -42,6 +50,300 @@ pub enum LibraryTab {
     Browse,
 }
 
+/// How the panes of a split layout are arranged.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitOrientation {
+    /// Side by side.
+    #[default]
+    Horizontal,
+    /// Above each other.
+    Vertical,
+}
+
+/// How album covers are drawn by the cover screen.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CoverProtocol {
+    /// Detect what the terminal supports.
+    #[default]
+    Auto,
+    Kitty,
+    Sixel,
+    Iterm2,
+    /// Unicode half blocks in the colors of the cover, which work in every terminal.
+    Halfblocks,
+    Ueberzug,
+}
+
+/// The screens that are shown at the same time, configured in `[split]`.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct SplitConfig {
+    pub panes: Option<Vec<String>>,
+    pub orientation: Option<SplitOrientation>,
+}
+
+/// The segments of the status line, configured in `[statusbar]`.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct StatusbarConfig {
+    pub left: Option<Vec<StatusbarSegment>>,
+    pub center: Option<Vec<StatusbarSegment>>,
+    pub right: Option<Vec<StatusbarSegment>>,
+}
+
+/// The placeholders of statusbar segments, besides the ones of track formats which are filled in
+/// with the playing item.
+const STATUSBAR_PLACEHOLDERS: [&str; 15] = [
+    "playback",
+    "track",
+    "updating",
+    "sync",
+    "repeat",
+    "shuffle",
+    "stop_after_current",
+    "sleep",
+    "elapsed",
+    "remaining",
+    "position",
+    "volume",
+    "bitrate_setting",
+    "backend_device",
+    "duration",
+];
+
+/// All placeholders that can be used in statusbar segments.
+pub fn statusbar_placeholders() -> Vec<&'static str> {
+    STATUSBAR_PLACEHOLDERS
+        .iter()
+        .chain(Playable::FIELDS.iter())
+        .copied()
+        .collect()
+}
+
+/// Parsed templates by their format. Invalid formats are kept as None, so that they're only
+/// parsed once as well.
+type Templates = HashMap<String, Option<Arc<Template>>>;
+
+/// A segment of the status line: a format with placeholders, and optionally the colors it's
+/// drawn in.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum StatusbarSegment {
+    Format(String),
+    Styled {
+        format: String,
+        fg: Option<String>,
+        bg: Option<String>,
+    },
+}
+
+impl StatusbarSegment {
+    pub fn format(&self) -> &str {
+        match self {
+            Self::Format(format) | Self::Styled { format, .. } => format,
+        }
+    }
+}
+
+/// The columns of track lists, configured in `[columns]`. Lists without columns show the
+/// `[track_format]`.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct ColumnsConfig {
+    /// Whether a header with the names of the columns is shown above the list.
+    pub header: Option<bool>,
+    /// The columns of the lists that don't have their own.
+    pub default: Option<Vec<Column>>,
+    pub queue: Option<Vec<Column>>,
+    pub library: Option<Vec<Column>>,
+    pub playlist: Option<Vec<Column>>,
+    pub album: Option<Vec<Column>>,
+    pub artist: Option<Vec<Column>>,
+    pub search: Option<Vec<Column>>,
+    pub show: Option<Vec<Column>>,
+}
+
+impl ColumnsConfig {
+    /// The columns of the track lists in `view`, which is one of the names of the fields.
+    pub fn get(&self, view: &str) -> Option<&Vec<Column>> {
+        let columns = match view {
+            "queue" => &self.queue,
+            "library" => &self.library,
+            "playlist" => &self.playlist,
+            "album" => &self.album,
+            "artist" => &self.artist,
+            "search" => &self.search,
+            "show" => &self.show,
+            _ => &None,
+        };
+        columns.as_ref().or(self.default.as_ref())
+    }
+}
+
+/// What a column of a track list shows.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnField {
+    /// The position in the list.
+    Index,
+    Title,
+    Artists,
+    Album,
+    Duration,
+    Added,
+    TrackNumber,
+    Saved,
+    Popularity,
+    /// An indicator if the item was added to the queue by autoplay.
+    Auto,
+}
+
+impl ColumnField {
+    /// The name of the column in the header.
+    pub fn header(&self) -> &'static str {
+        match self {
+            Self::Index => "#",
+            Self::Title => "Title",
+            Self::Artists => "Artists",
+            Self::Album => "Album",
+            Self::Duration => "Duration",
+            Self::Added => "Added",
+            Self::TrackNumber => "Track",
+            Self::Saved => "Saved",
+            Self::Popularity => "Popularity",
+            Self::Auto => "Auto",
+        }
+    }
+
+    /// The key that the list is sorted on when the header of the column is clicked.
+    pub fn sort_key(&self) -> Option<SortKey> {
+        match self {
+            Self::Title => Some(SortKey::Title),
+            Self::Artists => Some(SortKey::Artist),
+            Self::Album => Some(SortKey::Album),
+            Self::Duration => Some(SortKey::Duration),
+            Self::Added => Some(SortKey::Added),
+            Self::TrackNumber => Some(SortKey::TrackNumber),
+            Self::Popularity => Some(SortKey::Popularity),
+            Self::Index | Self::Saved | Self::Auto => None,
+        }
+    }
+
+    /// The width of the column if none is configured. Columns without a width share the space
+    /// that is left.
+    fn default_width(&self) -> Option<usize> {
+        match self {
+            Self::Index => Some(4),
+            Self::Duration => Some(8),
+            Self::Added => Some(10),
+            Self::TrackNumber => Some(5),
+            Self::Saved => Some(5),
+            Self::Popularity => Some(10),
+            Self::Auto => Some(5),
+            Self::Title | Self::Artists | Self::Album => None,
+        }
+    }
+
+    fn default_align(&self) -> ColumnAlign {
+        match self {
+            Self::Index | Self::Duration | Self::TrackNumber | Self::Popularity => {
+                ColumnAlign::Right
+            }
+            _ => ColumnAlign::Left,
+        }
+    }
+}
+
+/// Where the values in a column are aligned when they are narrower than the column.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/// How values that are wider than their column are shortened.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnTruncate {
+    /// Cut off the end and put an ellipsis there.
+    #[default]
+    End,
+    /// Cut off the start and put an ellipsis there.
+    Start,
+    /// Cut off the end without an ellipsis.
+    Clip,
+}
+
+/// A column of a track list: the field it shows, and optionally its width, alignment and how
+/// values that don't fit are shortened.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum Column {
+    Field(ColumnField),
+    Styled {
+        field: ColumnField,
+        width: Option<usize>,
+        align: Option<ColumnAlign>,
+        truncate: Option<ColumnTruncate>,
+    },
+}
+
+impl Column {
+    pub fn field(&self) -> ColumnField {
+        match self {
+            Self::Field(field) | Self::Styled { field, .. } => *field,
+        }
+    }
+
+    /// The width of the column, or None if it shares the space that is left with other columns.
+    pub fn width(&self) -> Option<usize> {
+        match self {
+            Self::Styled {
+                width: Some(width), ..
+            } => Some(*width),
+            _ => self.field().default_width(),
+        }
+    }
+
+    pub fn align(&self) -> ColumnAlign {
+        match self {
+            Self::Styled {
+                align: Some(align), ..
+            } => *align,
+            _ => self.field().default_align(),
+        }
+    }
+
+    pub fn truncate(&self) -> ColumnTruncate {
+        match self {
+            Self::Styled {
+                truncate: Some(truncate),
+                ..
+            } => *truncate,
+            _ => ColumnTruncate::default(),
+        }
+    }
+}
+
+/// The compact mini-player, configured in `[mini]`.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct MiniConfig {
+    /// The amount of upcoming queue entries that are shown.
+    pub queue_entries: Option<usize>,
+    /// Whether a line with the keys for the most common commands is shown.
+    pub key_hints: Option<bool>,
+}
+
+/// An entry of the `[keybindings]` section: the commands for a key, or the keybindings of a
+/// context like `[keybindings.queue]`.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum Keybinding {
+    Commands(String),
+    Context(HashMap<String, String>),
+}
+
 /// The format used to represent tracks in a list.
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct TrackFormat {
@@ -82,8 +384,14 @@ impl NotificationFormat {
 pub struct ConfigValues {
     pub command_key: Option<char>,
     pub initial_screen: Option<String>,
+    pub split: Option<SplitConfig>,
+    pub mini: Option<MiniConfig>,
+    pub lyrics_directory: Option<String>,
     pub default_keybindings: Option<bool>,
-    pub keybindings: Option<HashMap<String, String>>,
+    pub keybindings: Option<HashMap<String, Keybinding>>,
+    pub leader_key: Option<String>,
+    pub key_sequence_timeout: Option<u64>,
+    pub aliases: Option<HashMap<String, String>>,
     pub theme: Option<ConfigTheme>,
     pub use_nerdfont: Option<bool>,
     pub flip_status_indicators: Option<bool>,
@@ -97,15 +405,25 @@ pub struct ConfigValues {
     pub bitrate: Option<u32>,
     pub gapless: Option<bool>,
     pub shuffle: Option<bool>,
+    pub shuffle_strategy: Option<queue::ShuffleStrategy>,
+    pub autoplay: Option<bool>,
+    pub sleep_action: Option<SleepAction>,
+    pub sleep_fade: Option<bool>,
     pub repeat: Option<queue::RepeatSetting>,
     pub cover_max_scale: Option<f32>,
+    pub cover_protocol: Option<CoverProtocol>,
     pub playback_state: Option<PlaybackState>,
     pub track_format: Option<TrackFormat>,
     pub notification_format: Option<NotificationFormat>,
     pub statusbar_format: Option<String>,
+    pub statusbar: Option<StatusbarConfig>,
+    pub columns: Option<ColumnsConfig>,
     pub library_tabs: Option<Vec<LibraryTab>>,
     pub hide_display_names: Option<bool>,
     pub ap_port: Option<u16>,
+    pub smart_playlists: Option<Vec<SmartPlaylistRule>>,
+    pub scrobbling: Option<ScrobblingConfig>,
+    pub hooks: Option<HooksConfig>,
 }
 
 /// The ncspot theme.
@@ -137,6 +455,50 @@ pub struct ConfigTheme {
 pub struct SortingOrder {
     pub key: SortKey,
     pub direction: SortDirection,
+    /// Keys that are used to order items that are equal according to `key`.
+    #[serde(default)]
+    pub then: Vec<SortKey>,
+}
+
+impl SortingOrder {
+    /// Create an ordering from a list of keys in order of priority. Returns None if `keys` is
+    /// empty.
+    pub fn new(keys: &[SortKey], direction: SortDirection) -> Option<Self> {
+        let (key, then) = keys.split_first()?;
+        Some(Self {
+            key: key.clone(),
+            direction,
+            then: then.to_vec(),
+        })
+    }
+
+    /// All the keys of this ordering in order of priority.
+    pub fn keys(&self) -> impl Iterator<Item = &SortKey> {
+        std::iter::once(&self.key).chain(self.then.iter())
+    }
+
+    /// Sort `items` according to this ordering. `compare` is used to compare two items on a
+    /// single key; it doesn't need to handle [SortKey::Random] or the sort direction.
+    pub fn sort<T>(&self, items: &mut Vec<T>, compare: impl Fn(&T, &T, &SortKey) -> Ordering) {
+        let mut rng = rand::thread_rng();
+        let mut keyed: Vec<(u64, T)> = items.drain(..).map(|item| (rng.gen(), item)).collect();
+
+        keyed.sort_by(|(a_random, a), (b_random, b)| {
+            let ordering = self.keys().fold(Ordering::Equal, |ordering, key| {
+                ordering.then_with(|| match key {
+                    SortKey::Random => a_random.cmp(b_random),
+                    _ => compare(a, b, key),
+                })
+            });
+
+            match self.direction {
+                SortDirection::Ascending => ordering,
+                SortDirection::Descending => ordering.reverse(),
+            }
+        });
+
+        items.extend(keyed.into_iter().map(|(_, item)| item));
+    }
 }
 
 /// The runtime state of the music queue.
@@ -151,6 +513,12 @@ pub struct QueueState {
     pub track_progress: std::time::Duration,
     // 播放列表
     pub queue: Vec<Playable>,
+    /// The strategy used to generate `random_order`.
+    #[serde(default)]
+    pub shuffle_strategy: queue::ShuffleStrategy,
+    /// Which items in `queue` were added by autoplay.
+    #[serde(default)]
+    pub auto_added_items: queue::AutoAdded,
 }
 
 /// Runtime state that should be persisted accross sessions.
@@ -160,12 +528,24 @@ pub struct UserState {
     pub volume: u16,
     // 随机播放
     pub shuffle: bool,
+    /// Add recommendations to the queue when it runs out.
+    #[serde(default)]
+    pub autoplay: bool,
     // 循环
     pub repeat: queue::RepeatSetting,
     // 播放列表状态
     pub queuestate: QueueState,
+    /// Queues that were saved under a name, see [crate::queue::Queue::save_as].
+    #[serde(default)]
+    pub saved_queues: BTreeMap<String, QueueState>,
     // 歌单列表
     pub playlist_orders: HashMap<String, SortingOrder>,
+    /// How often each track was played, by track id.
+    #[serde(default)]
+    pub play_counts: HashMap<String, u32>,
+    /// When each track was last played, by track id.
+    #[serde(default)]
+    pub last_played: HashMap<String, DateTime<Utc>>,
     // 缓存版本
     pub cache_version: u16,
     // 播放状态
@@ -177,9 +557,13 @@ impl Default for UserState {
         Self {
             volume: u16::MAX,
             shuffle: false,
+            autoplay: false,
             repeat: queue::RepeatSetting::None,
             queuestate: QueueState::default(),
+            saved_queues: BTreeMap::new(),
             playlist_orders: HashMap::new(),
+            play_counts: HashMap::new(),
+            last_played: HashMap::new(),
             cache_version: 0,
             playback_state: PlaybackState::Default,
         }
@@ -198,6 +582,8 @@ pub struct Config {
     values: RwLock<ConfigValues>,
     /// Runtime state which can't be edited by the user, read/write.
     state: RwLock<UserState>,
+    /// The formats that were parsed, see [Config::template].
+    templates: RwLock<Templates>,
 }
 
 impl Config {
@@ -207,7 +593,7 @@ impl Config {
         // 没有传入配置文件名时则使用默认
         let filename = filename.unwrap_or(CONFIGURATION_FILE_NAME.to_owned());
         // 加载配置数据
-        let values = load(&filename).unwrap_or_else(|e| {
+        let (values, templates) = load(&filename).unwrap_or_else(|e| {
             // 加载不成功则结束程序
             eprint!(
                 "There is an error in your configuration file at {}:\n\n{e}",
@@ -232,6 +618,14 @@ impl Config {
             userstate.shuffle = shuffle;
         }
 
+        if let Some(autoplay) = values.autoplay {
+            userstate.autoplay = autoplay;
+        }
+
+        if let Some(strategy) = values.shuffle_strategy {
+            userstate.queuestate.shuffle_strategy = strategy;
+        }
+
         if let Some(repeat) = values.repeat {
             userstate.repeat = repeat;
         }
@@ -244,6 +638,7 @@ impl Config {
             filename,
             values: RwLock::new(values),
             state: RwLock::new(userstate),
+            templates: RwLock::new(templates),
         }
     }
 
@@ -295,19 +690,75 @@ impl Config {
     /// This only updates the values stored in memory but doesn't perform any additional actions
     /// like updating active keybindings.
     pub fn reload(&self) -> Result<(), Box<dyn Error>> {
-        let cfg = load(&self.filename)?;
+        let (cfg, templates) = load(&self.filename)?;
         *self.values.write().unwrap() = cfg;
+        *self.templates.write().unwrap() = templates;
         Ok(())
     }
+
+    /// The template of `format` with the placeholders `fields`, or None if it's invalid. The
+    /// configured formats are parsed when the configuration is loaded, and other formats the first
+    /// time they're used.
+    pub fn template(&self, format: &str, fields: &[&str]) -> Option<Arc<Template>> {
+        if let Some(template) = self.templates.read().unwrap().get(format) {
+            return template.clone();
+        }
+        let template = Template::parse(format, fields).ok().map(Arc::new);
+        self.templates
+            .write()
+            .unwrap()
+            .insert(format.to_string(), template.clone());
+        template
+    }
 }
 
 /// Parse the configuration file with name `filename` at the configuration base path.
 /// 解析配置文件
-fn load(filename: &str) -> Result<ConfigValues, String> {
+fn load(filename: &str) -> Result<(ConfigValues, Templates), String> {
     // 获取完整的配置路径
     let path = config_path(filename);
     // 解析配置文件
-    TOML.load_or_generate_default(path, || Ok(ConfigValues::default()), false)
+    let values: ConfigValues =
+        TOML.load_or_generate_default(path, || Ok(ConfigValues::default()), false)?;
+    let templates = parse_formats(&values)?;
+    Ok((values, templates))
+}
+
+/// Parse the track, notification and statusbar formats in `values`.
+fn parse_formats(values: &ConfigValues) -> Result<Templates, String> {
+    let mut templates = Templates::new();
+    let track_format = values.track_format.clone().unwrap_or_default();
+    let notification_format = values.notification_format.clone().unwrap_or_default();
+    let track_formats = [
+        ("track_format.left", track_format.left),
+        ("track_format.center", track_format.center),
+        ("track_format.right", track_format.right),
+        ("notification_format.title", notification_format.title),
+        ("notification_format.body", notification_format.body),
+        ("statusbar_format", values.statusbar_format.clone()),
+    ];
+    for (key, format) in track_formats {
+        if let Some(format) = format {
+            let template = Template::parse(&format, &Playable::FIELDS)
+                .map_err(|e| format!("invalid value for {key}: {e}"))?;
+            templates.insert(format, Some(Arc::new(template)));
+        }
+    }
+
+    let statusbar = values.statusbar.clone().unwrap_or_default();
+    let segments = [
+        ("statusbar.left", statusbar.left),
+        ("statusbar.center", statusbar.center),
+        ("statusbar.right", statusbar.right),
+    ];
+    for (key, segments) in segments {
+        for segment in segments.unwrap_or_default() {
+            let template = Template::parse(segment.format(), &statusbar_placeholders())
+                .map_err(|e| format!("invalid value for {key}: {e}"))?;
+            templates.insert(segment.format().to_string(), Some(Arc::new(template)));
+        }
+    }
+    Ok(templates)
 }
 
 /// Returns the plaform app directories for ncspot if they could be determined,
@@ -392,3 +843,56 @@ pub fn set_configuration_base_path(base_path: Option<PathBuf>) {
         *BASE_PATH.write().unwrap() = Some(basepath);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(keys: &[SortKey], direction: SortDirection) -> SortingOrder {
+        SortingOrder::new(keys, direction).unwrap()
+    }
+
+    /// Compare pairs of `(letter, number)` on title and track number respectively.
+    fn compare(a: &(char, u32), b: &(char, u32), key: &SortKey) -> Ordering {
+        match key {
+            SortKey::Title => a.0.cmp(&b.0),
+            SortKey::TrackNumber => a.1.cmp(&b.1),
+            _ => Ordering::Equal,
+        }
+    }
+
+    #[test]
+    fn sorts_on_secondary_keys() {
+        let mut items = vec![('b', 1), ('a', 2), ('b', 0), ('a', 1)];
+        order(
+            &[SortKey::Title, SortKey::TrackNumber],
+            SortDirection::Ascending,
+        )
+        .sort(&mut items, compare);
+        assert_eq!(items, vec![('a', 1), ('a', 2), ('b', 0), ('b', 1)]);
+    }
+
+    #[test]
+    fn sorts_descending() {
+        let mut items = vec![('b', 1), ('a', 2), ('b', 0), ('a', 1)];
+        order(
+            &[SortKey::Title, SortKey::TrackNumber],
+            SortDirection::Descending,
+        )
+        .sort(&mut items, compare);
+        assert_eq!(items, vec![('b', 1), ('b', 0), ('a', 2), ('a', 1)]);
+    }
+
+    #[test]
+    fn random_key_keeps_items() {
+        let mut items: Vec<(char, u32)> = (0..20).map(|n| ('a', n)).collect();
+        order(&[SortKey::Random], SortDirection::Ascending).sort(&mut items, compare);
+        items.sort();
+        assert_eq!(items, (0..20).map(|n| ('a', n)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn empty_keys_have_no_ordering() {
+        assert!(SortingOrder::new(&[], SortDirection::Ascending).is_none());
+    }
+}
@@ -8,6 +8,8 @@ use crate::spotify::PlayerEvent;
 pub enum Event {
     Player(PlayerEvent),
     Queue(QueueEvent),
+    /// The saved items of the library changed.
+    LibraryChanged,
     SessionDied,
     IpcInput(String),
 }
@@ -0,0 +1,298 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{debug, error, warn};
+use serde_json::json;
+use strum_macros::{Display, EnumString};
+
+use crate::config::Config;
+use crate::model::playable::Playable;
+use crate::spotify::{PlayerEvent, VOLUME_PERCENT};
+
+/// How long a hook may run before it is killed, unless configured otherwise.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a running hook is checked for completion.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Volume changes are only reported once the volume didn't change for this long, so holding down
+/// a volume key doesn't start a hook for every step.
+const VOLUME_SETTLE_TIME: Duration = Duration::from_millis(300);
+
+/// Shell commands that are run on player and queue events, configured in `[hooks]`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct HooksConfig {
+    pub on_track_change: Option<String>,
+    pub on_play: Option<String>,
+    pub on_pause: Option<String>,
+    pub on_stop: Option<String>,
+    pub on_volume_change: Option<String>,
+    pub on_queue_end: Option<String>,
+    /// Seconds after which a running hook is killed.
+    pub timeout: Option<u64>,
+}
+
+/// The events that hooks can be configured for.
+#[derive(Display, EnumString, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[strum(serialize_all = "snake_case")]
+pub enum HookEvent {
+    TrackChange,
+    Play,
+    Pause,
+    Stop,
+    VolumeChange,
+    QueueEnd,
+}
+
+impl HooksConfig {
+    fn command(&self, event: HookEvent) -> Option<&String> {
+        match event {
+            HookEvent::TrackChange => self.on_track_change.as_ref(),
+            HookEvent::Play => self.on_play.as_ref(),
+            HookEvent::Pause => self.on_pause.as_ref(),
+            HookEvent::Stop => self.on_stop.as_ref(),
+            HookEvent::VolumeChange => self.on_volume_change.as_ref(),
+            HookEvent::QueueEnd => self.on_queue_end.as_ref(),
+        }
+    }
+}
+
+/// The environment variables that describe `playable` to a hook.
+fn playable_env(playable: &Playable) -> Vec<(&'static str, String)> {
+    let mut env = vec![
+        ("NCSPOT_URI", playable.uri()),
+        ("NCSPOT_ID", playable.id().unwrap_or_default()),
+        ("NCSPOT_DURATION_MS", playable.duration().to_string()),
+    ];
+    match playable {
+        Playable::Track(track) => env.extend([
+            ("NCSPOT_TYPE", "track".into()),
+            ("NCSPOT_TITLE", track.title.clone()),
+            ("NCSPOT_ARTISTS", track.artists.join(", ")),
+            ("NCSPOT_ALBUM", track.album.clone().unwrap_or_default()),
+            ("NCSPOT_ALBUM_ARTISTS", track.album_artists.join(", ")),
+        ]),
+        Playable::Episode(episode) => env.extend([
+            ("NCSPOT_TYPE", "episode".into()),
+            ("NCSPOT_TITLE", episode.name.clone()),
+        ]),
+    }
+    env
+}
+
+/// The JSON document that is written to the standard input of a hook.
+fn payload(event: HookEvent, playable: Option<&Playable>, volume: u16) -> serde_json::Value {
+    json!({
+        "event": event.to_string(),
+        "volume": volume,
+        "playable": playable,
+    })
+}
+
+/// Run the shell `command` and wait at most `timeout` for it to exit.
+fn run(
+    command: &str,
+    event: HookEvent,
+    env: Vec<(&'static str, String)>,
+    input: String,
+    timeout: Duration,
+) -> Result<(), String> {
+    #[cfg(unix)]
+    let mut shell = {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    #[cfg(windows)]
+    let mut shell = {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    };
+
+    let mut child = shell
+        .arg(command)
+        .env("NCSPOT_EVENT", event.to_string())
+        .envs(env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    // The hook may exit without reading its input, so a failed write isn't an error.
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(input.as_bytes());
+    }
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) if status.success() => return Ok(()),
+            Some(status) => return Err(format!("exited with {status}")),
+            None if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("killed after {}s", timeout.as_secs()));
+            }
+            None => thread::sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+/// Runs the configured hooks for player and queue events. Hooks run in the background, so a slow
+/// hook never blocks the user interface.
+pub struct Hooks {
+    cfg: Arc<Config>,
+    /// The URI of the item that was last reported as playing.
+    current: Option<String>,
+    playing: bool,
+    /// Whether playback was last reported as stopped, rather than playing or paused.
+    stopped: bool,
+    /// The volume that hooks were last told about, and a change that hasn't settled yet.
+    volume: u16,
+    volume_change: Option<(u16, Instant)>,
+    /// The events that occurred since [Hooks::take_events] was last called.
+    #[cfg(feature = "scripting")]
+    events: Vec<HookEvent>,
+}
+
+impl Hooks {
+    pub fn new(cfg: Arc<Config>) -> Self {
+        let volume = cfg.state().volume;
+        Self {
+            cfg,
+            current: None,
+            playing: false,
+            stopped: true,
+            volume,
+            volume_change: None,
+            #[cfg(feature = "scripting")]
+            events: Vec::new(),
+        }
+    }
+
+    /// Handle a player `event`. `current` is the item in the queue that the event applies to.
+    pub fn handle(&mut self, event: &PlayerEvent, current: Option<Playable>) {
+        match event {
+            PlayerEvent::Playing(_) => {
+                let uri = current.as_ref().map(Playable::uri);
+                if uri != self.current {
+                    self.current = uri;
+                    self.fire(HookEvent::TrackChange, current.as_ref());
+                }
+                self.stopped = false;
+                if !self.playing {
+                    self.playing = true;
+                    self.fire(HookEvent::Play, current.as_ref());
+                }
+            }
+            PlayerEvent::Paused(_) => {
+                self.stopped = false;
+                if self.playing {
+                    self.playing = false;
+                    self.fire(HookEvent::Pause, current.as_ref());
+                }
+            }
+            PlayerEvent::Stopped => {
+                self.playing = false;
+                self.current = None;
+                if !self.stopped {
+                    self.stopped = true;
+                    self.fire(HookEvent::Stop, current.as_ref());
+                }
+            }
+            PlayerEvent::FinishedTrack => {}
+        }
+    }
+
+    /// Report that playback stopped because the last item of the queue finished.
+    pub fn queue_ended(&mut self, last: Option<Playable>) {
+        self.fire(HookEvent::QueueEnd, last.as_ref());
+    }
+
+    /// Check whether the volume changed. This should be called regularly, as the volume can be
+    /// changed from many places.
+    pub fn update_volume(&mut self, volume: u16, current: Option<Playable>) {
+        match self.volume_change {
+            _ if volume == self.volume => self.volume_change = None,
+            Some((pending, since)) if pending == volume => {
+                if since.elapsed() >= VOLUME_SETTLE_TIME {
+                    self.volume = volume;
+                    self.volume_change = None;
+                    self.fire(HookEvent::VolumeChange, current.as_ref());
+                }
+            }
+            _ => self.volume_change = Some((volume, Instant::now())),
+        }
+    }
+
+    /// Take the events that occurred since this was last called, oldest first.
+    #[cfg(feature = "scripting")]
+    pub fn take_events(&mut self) -> Vec<HookEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Run the hook for `event` in the background, if there is one.
+    fn fire(&mut self, event: HookEvent, playable: Option<&Playable>) {
+        #[cfg(feature = "scripting")]
+        self.events.push(event);
+
+        let config = match self.cfg.values().hooks.clone() {
+            Some(config) => config,
+            None => return,
+        };
+        let command = match config.command(event) {
+            Some(command) => command.clone(),
+            None => return,
+        };
+        let timeout = config
+            .timeout
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TIMEOUT);
+
+        let volume = self.cfg.state().volume / VOLUME_PERCENT;
+        let mut env = playable.map(playable_env).unwrap_or_default();
+        env.push(("NCSPOT_VOLUME", volume.to_string()));
+        let input = payload(event, playable, volume).to_string();
+
+        debug!("running {event} hook: {command}");
+        let spawned = thread::Builder::new()
+            .name(format!("hook {event}"))
+            .spawn(move || {
+                if let Err(e) = run(&command, event, env, input, timeout) {
+                    warn!("{event} hook failed: {e}");
+                }
+            });
+        if let Err(e) = spawned {
+            error!("could not start {event} hook: {e}");
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passes_event_and_kills_slow_hooks() {
+        let input = payload(HookEvent::QueueEnd, None, 50).to_string();
+        let check = r#"test "$NCSPOT_EVENT" = queue_end && grep -q '"volume":50'"#;
+        assert_eq!(
+            run(check, HookEvent::QueueEnd, vec![], input, DEFAULT_TIMEOUT),
+            Ok(())
+        );
+
+        let slow = run(
+            "sleep 5",
+            HookEvent::Play,
+            vec![],
+            String::new(),
+            Duration::from_millis(100),
+        );
+        assert!(slow.is_err());
+    }
+}
@@ -47,5 +47,49 @@ pub fn program_arguments() -> clap::Command {
                 .help("Filename of config file in basepath")
                 .default_value(CONFIGURATION_FILE_NAME),
         )
-        .subcommands([clap::Command::new("info").about("Print platform information like paths")])
+        .arg(
+            clap::Arg::new("mini")
+                .short('m')
+                .long("mini")
+                .action(clap::ArgAction::SetTrue)
+                .help("Start in the compact mini-player"),
+        )
+        .subcommands([
+            clap::Command::new("info").about("Print platform information like paths"),
+            clap::Command::new("stats")
+                .about("Print listening statistics from the play history")
+                .arg(
+                    clap::Arg::new("period")
+                        .short('p')
+                        .long("period")
+                        .value_name("PERIOD")
+                        .help("\"all\", a year like 2024 or a duration like 30d")
+                        .default_value("all"),
+                )
+                .arg(
+                    clap::Arg::new("order")
+                        .short('o')
+                        .long("order")
+                        .value_parser(["plays", "time"])
+                        .help("Rank by play count or listening time")
+                        .default_value("plays"),
+                )
+                .arg(
+                    clap::Arg::new("limit")
+                        .short('l')
+                        .long("limit")
+                        .value_name("COUNT")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Amount of entries in every top list")
+                        .default_value("10"),
+                )
+                .arg(
+                    clap::Arg::new("format")
+                        .short('f')
+                        .long("format")
+                        .value_parser(["markdown", "json"])
+                        .help("Output format")
+                        .default_value("markdown"),
+                ),
+        ])
 }
@@ -10,16 +10,18 @@ use rspotify::model::Id;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::config::Config;
 use crate::config::{self, CACHE_VERSION};
-use crate::events::EventManager;
+use crate::config::{Config, SortingOrder};
+use crate::events::{Event, EventManager};
 use crate::model::album::Album;
 use crate::model::artist::Artist;
 use crate::model::playable::Playable;
 use crate::model::playlist::Playlist;
 use crate::model::show::Show;
 use crate::model::track::Track;
+use crate::play_history::{PlayHistory, PLAY_HISTORY_FILE_NAME};
 use crate::spotify::Spotify;
+use crate::undo::{Change, UndoHistory};
 
 /// Cached tracks database filename.
 /// 曲目缓存db
@@ -37,6 +39,16 @@ const CACHE_ARTISTS: &str = "artists.db";
 /// 播放列表缓存db
 const CACHE_PLAYLISTS: &str = "playlists.db";
 
+/// The amount of kinds of items that are fetched when the library is updated: tracks, albums,
+/// artists, playlists and shows.
+const LIBRARY_PARTS: usize = 5;
+
+/// Key of the saved tracks in the persisted list orders.
+pub const TRACKS_ORDER_ID: &str = "library:tracks";
+
+/// Key of the saved albums in the persisted list orders.
+pub const ALBUMS_ORDER_ID: &str = "library:albums";
+
 /// The user library with all their saved tracks, albums, playlists... High level interface to the
 /// Spotify API used to manage items in the user library.
 /// 用户库，包含所有已保存的曲目、专辑、播放列表... 高级接口，用于管理用户库中的曲目、专辑、播放列表等。
@@ -46,10 +58,19 @@ pub struct Library {
     pub albums: Arc<RwLock<Vec<Album>>>,
     pub artists: Arc<RwLock<Vec<Artist>>>,
     pub playlists: Arc<RwLock<Vec<Playlist>>>,
+    /// The smart playlists followed by the playlists of the library, as listed in the playlists
+    /// view. Rebuilt by [Library::refresh_playlists] when the library changes.
+    pub listed_playlists: Arc<RwLock<Vec<Playlist>>>,
     pub shows: Arc<RwLock<Vec<Show>>>,
     pub is_done: Arc<RwLock<bool>>,
+    /// The amount of kinds of items that were fetched since the update of the library started.
+    updated_parts: Arc<RwLock<usize>>,
     pub user_id: Option<String>,
     pub display_name: Option<String>,
+    /// Changes to the queue and playlists that can be undone.
+    pub history: Arc<UndoHistory>,
+    /// The items that were played.
+    pub play_history: Arc<PlayHistory>,
     ev: EventManager,
     spotify: Spotify,
     pub cfg: Arc<Config>,
@@ -67,10 +88,16 @@ impl Library {
             albums: Arc::new(RwLock::new(Vec::new())),
             artists: Arc::new(RwLock::new(Vec::new())),
             playlists: Arc::new(RwLock::new(Vec::new())),
+            listed_playlists: Arc::new(RwLock::new(Vec::new())),
             shows: Arc::new(RwLock::new(Vec::new())),
             is_done: Arc::new(RwLock::new(false)),
+            updated_parts: Arc::new(RwLock::new(0)),
             user_id,
             display_name,
+            history: Arc::new(UndoHistory::default()),
+            play_history: Arc::new(PlayHistory::load(config::config_path(
+                PLAY_HISTORY_FILE_NAME,
+            ))),
             ev,
             spotify,
             cfg,
@@ -81,6 +108,27 @@ impl Library {
         library
     }
 
+    /// Record that one kind of items was fetched, and redraw to show the progress.
+    fn finish_part(&self) {
+        *self.updated_parts.write().unwrap() += 1;
+        self.changed();
+    }
+
+    /// Notify the main event loop that the saved items changed, which also redraws the UI.
+    fn changed(&self) {
+        self.ev.send(Event::LibraryChanged);
+    }
+
+    /// The amount of kinds of items that were fetched and the amount that are fetched in total,
+    /// while the library is being updated.
+    pub fn update_progress(&self) -> Option<(usize, usize)> {
+        if *self.is_done.read().unwrap() {
+            None
+        } else {
+            Some((*self.updated_parts.read().unwrap(), LIBRARY_PARTS))
+        }
+    }
+
     /// Load cached items from the file at `cache_path` into the given `store`.
     /// 加载指定的缓存db数据
     fn load_cache<T: DeserializeOwned>(&self, cache_path: &Path, store: &mut Vec<T>) {
@@ -128,6 +176,7 @@ impl Library {
         if let Err(message) = serialize_result {
             error!("could not write cache: {message:?}");
         }
+        self.changed();
     }
 
     /// Check whether the `remote` [Playlist] is newer than its locally saved version. Returns
@@ -157,10 +206,17 @@ impl Library {
         store.len() - 1
     }
 
-    /// Delete the playlist with the given `id` if it exists.
+    /// Delete the playlist with the given `id` if it exists. The deletion can be undone.
     pub fn delete_playlist(&self, id: &str) {
+        if let Some(playlist) = self.remove_playlist(id) {
+            self.history.record(Change::PlaylistDeleted(playlist));
+        }
+    }
+
+    /// Delete the playlist with the given `id` if it exists and return it.
+    pub fn remove_playlist(&self, id: &str) -> Option<Playlist> {
         if !*self.is_done.read().unwrap() {
-            return;
+            return None;
         }
 
         let position = self
@@ -168,16 +224,103 @@ impl Library {
             .read()
             .unwrap()
             .iter()
-            .position(|i| i.id == id);
-
-        if let Some(position) = position {
-            if self.spotify.api.delete_playlist(id).is_ok() {
-                self.playlists.write().unwrap().remove(position);
-                self.save_cache(
-                    &config::cache_path(CACHE_PLAYLISTS),
-                    &self.playlists.read().unwrap(),
-                );
-            }
+            .position(|i| i.id == id)?;
+
+        if self.spotify.api.delete_playlist(id).is_ok() {
+            let playlist = self.playlists.write().unwrap().remove(position);
+            self.save_cache(
+                &config::cache_path(CACHE_PLAYLISTS),
+                &self.playlists.read().unwrap(),
+            );
+            Some(playlist)
+        } else {
+            None
+        }
+    }
+
+    /// Insert `tracks` into the playlist with `id` at `position`. Returns the new snapshot id of
+    /// the playlist.
+    pub fn insert_playlist_tracks(
+        &self,
+        id: &str,
+        position: usize,
+        tracks: &[Playable],
+    ) -> Result<String, String> {
+        let result = self
+            .spotify
+            .api
+            .append_tracks(id, tracks, Some(position as u32))
+            .map_err(|_| "Could not add tracks to playlist".to_string())?;
+
+        self.update_playlist_tracks(id, &result.snapshot_id, |stored| {
+            let position = position.min(stored.len());
+            stored.splice(position..position, tracks.iter().cloned());
+        });
+
+        Ok(result.snapshot_id)
+    }
+
+    /// Remove `tracks` from the playlist with `id`, where they are expected to start at
+    /// `position` in the playlist version `snapshot_id`. Returns the new snapshot id of the
+    /// playlist.
+    pub fn remove_playlist_tracks(
+        &self,
+        id: &str,
+        snapshot_id: &str,
+        position: usize,
+        tracks: &[Playable],
+    ) -> Result<String, String> {
+        let tracks: Vec<Playable> = tracks
+            .iter()
+            .cloned()
+            .enumerate()
+            .map(|(index, mut track)| {
+                track.set_list_index(position + index);
+                track
+            })
+            .collect();
+
+        let result = self
+            .spotify
+            .api
+            .delete_tracks(id, snapshot_id, &tracks)
+            .map_err(|_| "Could not remove tracks from playlist".to_string())?;
+
+        self.update_playlist_tracks(id, &result.snapshot_id, |stored| {
+            let end = (position + tracks.len()).min(stored.len());
+            stored.drain(position.min(end)..end);
+        });
+
+        Ok(result.snapshot_id)
+    }
+
+    /// Modify the tracks of the locally stored playlist with `id` and rewrite the cache.
+    fn update_playlist_tracks(
+        &self,
+        id: &str,
+        snapshot_id: &str,
+        update: impl FnOnce(&mut Vec<Playable>),
+    ) {
+        let updated = {
+            let mut playlists = self.playlists.write().unwrap();
+            playlists.iter_mut().find(|p| p.id == id).map(|playlist| {
+                playlist.snapshot_id = snapshot_id.to_string();
+                if let Some(tracks) = playlist.tracks.as_mut() {
+                    update(tracks);
+                    for (index, track) in tracks.iter_mut().enumerate() {
+                        track.set_list_index(index);
+                    }
+                    playlist.num_tracks = tracks.len();
+                }
+            })
+        };
+
+        if updated.is_some() {
+            self.save_cache(
+                &config::cache_path(CACHE_PLAYLISTS),
+                &self.playlists.read().unwrap(),
+            );
+            self.trigger_redraw();
         }
     }
 
@@ -207,6 +350,7 @@ impl Library {
     /// 更新本地缓存的library信息
     pub fn update_library(&self) {
         *self.is_done.write().unwrap() = false;
+        *self.updated_parts.write().unwrap() = 0;
 
         let library = self.clone();
         // 开启新线程执行
@@ -226,6 +370,7 @@ impl Library {
                         &config::cache_path(CACHE_TRACKS),
                         &library.tracks.read().unwrap(),
                     );
+                    library.finish_part();
                 })
             };
 
@@ -242,6 +387,7 @@ impl Library {
                         &config::cache_path(CACHE_ALBUMS),
                         &library.albums.read().unwrap(),
                     );
+                    library.finish_part();
                 })
             };
 
@@ -254,6 +400,7 @@ impl Library {
                         library.artists.write().unwrap().as_mut(),
                     );
                     library.fetch_artists();
+                    library.finish_part();
                 })
             };
 
@@ -270,6 +417,7 @@ impl Library {
                         &config::cache_path(CACHE_PLAYLISTS),
                         &library.playlists.read().unwrap(),
                     );
+                    library.finish_part();
                 })
             };
 
@@ -278,6 +426,7 @@ impl Library {
                 thread::spawn(move || {
                     // 加载博客数据
                     library.fetch_shows();
+                    library.finish_part();
                 })
             };
 
@@ -296,11 +445,10 @@ impl Library {
             t_playlists.join().unwrap();
             t_shows.join().unwrap();
 
-            let mut is_done = library.is_done.write().unwrap();
-            *is_done = true;
+            *library.is_done.write().unwrap() = true;
 
             // 触发重绘
-            library.ev.trigger();
+            library.changed();
         });
     }
 
@@ -497,6 +645,57 @@ impl Library {
         });
 
         *self.albums.write().unwrap() = albums;
+        self.apply_albums_order();
+    }
+
+    /// Sort the saved tracks by the order the user chose for them, if any.
+    fn apply_tracks_order(&self) {
+        let state = self.cfg.state();
+        if let Some(order) = state.playlist_orders.get(TRACKS_ORDER_ID) {
+            order.sort(&mut self.tracks.write().unwrap(), |a, b, key| {
+                a.compare(b, key, &state.play_counts)
+            });
+        }
+    }
+
+    /// Sort the saved albums by the order the user chose for them, if any.
+    fn apply_albums_order(&self) {
+        let state = self.cfg.state();
+        if let Some(order) = state.playlist_orders.get(ALBUMS_ORDER_ID) {
+            order.sort(&mut self.albums.write().unwrap(), |a, b, key| {
+                a.compare(b, key, &state.play_counts)
+            });
+        }
+    }
+
+    /// Sort the saved tracks by `order` and remember it for future library updates.
+    pub fn sort_tracks(&self, order: SortingOrder) {
+        self.cfg.with_state_mut(|state| {
+            state
+                .playlist_orders
+                .insert(TRACKS_ORDER_ID.to_string(), order.clone());
+        });
+        self.apply_tracks_order();
+        self.save_cache(
+            &config::cache_path(CACHE_TRACKS),
+            &self.tracks.read().unwrap(),
+        );
+        self.trigger_redraw();
+    }
+
+    /// Sort the saved albums by `order` and remember it for future library updates.
+    pub fn sort_albums(&self, order: SortingOrder) {
+        self.cfg.with_state_mut(|state| {
+            state
+                .playlist_orders
+                .insert(ALBUMS_ORDER_ID.to_string(), order.clone());
+        });
+        self.apply_albums_order();
+        self.save_cache(
+            &config::cache_path(CACHE_ALBUMS),
+            &self.albums.read().unwrap(),
+        );
+        self.trigger_redraw();
     }
 
     /// Fetch the tracks from the web API and save them in the local library.
@@ -529,12 +728,13 @@ impl Library {
                 // 则认为数据没有变化
                 let store = self.tracks.read().unwrap();
 
+                // The store might be sorted by the user, so only check whether the most recently
+                // saved tracks are present.
                 if page.total as usize == store.len()
-                    && !page
-                        .items
-                        .iter()
-                        .enumerate()
-                        .any(|(i, t)| t.track.id.as_ref().map(|id| id.to_string()) != store[i].id)
+                    && page.items.iter().all(|t| {
+                        let id = t.track.id.as_ref().map(|id| id.id().to_string());
+                        store.iter().any(|track| track.id == id)
+                    })
                 {
                     // 直接函数返回
                     return;
@@ -551,6 +751,7 @@ impl Library {
         }
 
         *self.tracks.write().unwrap() = tracks;
+        self.apply_tracks_order();
     }
 
     /// 填充artists数据
@@ -905,6 +1106,35 @@ impl Library {
             .unwrap_or(false)
     }
 
+    /// Evaluate the smart playlists from the configuration against the saved tracks. Rules that
+    /// can't be evaluated are skipped.
+    pub fn smart_playlists(&self) -> Vec<Playlist> {
+        let rules = self
+            .cfg
+            .values()
+            .smart_playlists
+            .clone()
+            .unwrap_or_default();
+        rules
+            .iter()
+            .filter_map(|rule| match rule.evaluate(self) {
+                Ok(playlist) => Some(playlist),
+                Err(e) => {
+                    error!("could not evaluate smart playlist \"{}\": {}", rule.name, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Rebuild [Library::listed_playlists] from the smart playlist rules and the playlists of the
+    /// library.
+    pub fn refresh_playlists(&self) {
+        let mut playlists = self.smart_playlists();
+        playlists.extend(self.playlists.read().unwrap().iter().cloned());
+        *self.listed_playlists.write().unwrap() = playlists;
+    }
+
     /// Add `playlist` to the user's library by following it as the logged in user.
     pub fn follow_playlist(&self, mut playlist: Playlist) {
         if !*self.is_done.read().unwrap() {
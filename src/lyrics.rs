@@ -0,0 +1,237 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use log::debug;
+
+use crate::config::{user_configuration_directory, Config};
+use crate::model::track::Track;
+use crate::utils::expand_home;
+
+/// The directory inside the configuration directory that lyrics are loaded from by default.
+const LYRICS_DIRECTORY: &str = "lyrics";
+
+/// A line of lyrics, with the time at which it's sung if the lyrics are synced.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LyricsLine {
+    pub time: Option<Duration>,
+    pub text: String,
+}
+
+/// The lyrics of a track.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Lyrics {
+    pub lines: Vec<LyricsLine>,
+}
+
+impl Lyrics {
+    /// Parse lyrics in the LRC format, where lines start with one or more `[mm:ss.xx]` timestamps.
+    /// Metadata tags are skipped, except for `[offset:ms]`. If no line has a timestamp, the lyrics
+    /// aren't synced.
+    pub fn from_lrc(content: &str) -> Self {
+        let mut offset = 0i64;
+        let mut synced = Vec::new();
+        let mut plain = Vec::new();
+
+        for line in content.lines() {
+            let mut rest = line.trim();
+            let mut times = Vec::new();
+            while let Some((tag, after)) =
+                rest.strip_prefix('[').and_then(|tag| tag.split_once(']'))
+            {
+                if let Some(time) = parse_timestamp(tag) {
+                    times.push(time);
+                } else if let Some(value) = tag.strip_prefix("offset:") {
+                    offset = value.trim().parse().unwrap_or(0);
+                }
+                rest = after;
+            }
+
+            let text = rest.trim().to_string();
+            if times.is_empty() {
+                // Lines that only consist of metadata tags are dropped.
+                if rest.len() != line.trim().len() && text.is_empty() {
+                    continue;
+                }
+                plain.push(LyricsLine { time: None, text });
+            } else {
+                synced.extend(times.into_iter().map(|time| LyricsLine {
+                    time: Some(time),
+                    text: text.clone(),
+                }));
+            }
+        }
+
+        if synced.is_empty() {
+            return Self::from_lines(plain);
+        }
+
+        // A positive offset shows the lines earlier.
+        for line in synced.iter_mut() {
+            line.time = line.time.map(|time| {
+                let millis = time.as_millis() as i64 - offset;
+                Duration::from_millis(millis.max(0) as u64)
+            });
+        }
+        synced.sort_by_key(|line| line.time);
+        Self { lines: synced }
+    }
+
+    /// Plain lyrics that aren't synced, one line per line of `content`.
+    pub fn from_text(content: &str) -> Self {
+        Self::from_lines(
+            content
+                .lines()
+                .map(|line| LyricsLine {
+                    time: None,
+                    text: line.trim().to_string(),
+                })
+                .collect(),
+        )
+    }
+
+    /// The lyrics made of `lines`, without empty lines at the start or end.
+    fn from_lines(mut lines: Vec<LyricsLine>) -> Self {
+        while lines.last().is_some_and(|line| line.text.is_empty()) {
+            lines.pop();
+        }
+        let start = lines
+            .iter()
+            .position(|line| !line.text.is_empty())
+            .unwrap_or(lines.len());
+        lines.drain(..start);
+        Self { lines }
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.lines.iter().any(|line| line.time.is_some())
+    }
+
+    /// The index of the line that is sung at `progress`, if the lyrics are synced.
+    pub fn current_line(&self, progress: Duration) -> Option<usize> {
+        self.lines
+            .iter()
+            .rposition(|line| line.time.is_some_and(|time| time <= progress))
+    }
+}
+
+/// Parse a timestamp like `01:23.45` or `01:23`.
+fn parse_timestamp(tag: &str) -> Option<Duration> {
+    let (minutes, seconds) = tag.split_once(':')?;
+    let minutes: u64 = minutes.trim().parse().ok()?;
+    let seconds: f64 = seconds.trim().parse().ok()?;
+    if !seconds.is_finite() || seconds < 0.0 {
+        return None;
+    }
+    Some(Duration::from_secs(minutes * 60) + Duration::from_secs_f64(seconds))
+}
+
+/// A source of lyrics.
+pub trait LyricsProvider: Send + Sync {
+    /// The lyrics of `track`, or None if this provider doesn't have them.
+    fn lyrics(&self, track: &Track) -> Option<Lyrics>;
+}
+
+/// Lyrics from `.lrc` and `.txt` files in a local directory. The files are named after the
+/// Spotify id of the track, or `<artists> - <title>`, ignoring case, with the artists separated by
+/// `, `. Only the first artist can be given as well. `.lrc` files are preferred.
+pub struct LocalLyricsProvider {
+    directory: PathBuf,
+}
+
+impl LocalLyricsProvider {
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+
+    /// The names that the lyrics file of `track` can have, without extension and in lowercase.
+    fn file_stems(track: &Track) -> Vec<String> {
+        let mut stems = Vec::new();
+        if let Some(id) = &track.id {
+            stems.push(id.to_lowercase());
+        }
+        stems.push(format!("{} - {}", track.artists.join(", "), track.title));
+        if let Some(artist) = track.artists.first() {
+            stems.push(format!("{} - {}", artist, track.title));
+        }
+        stems
+            .into_iter()
+            // File names can't contain slashes.
+            .map(|stem| stem.replace('/', "_").to_lowercase())
+            .collect()
+    }
+}
+
+impl LyricsProvider for LocalLyricsProvider {
+    fn lyrics(&self, track: &Track) -> Option<Lyrics> {
+        let stems = Self::file_stems(track);
+        let mut candidates: Vec<(usize, bool, PathBuf)> = fs::read_dir(&self.directory)
+            .ok()?
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let path = entry.path();
+                let extension = path.extension()?.to_str()?.to_lowercase();
+                let is_lrc = match extension.as_str() {
+                    "lrc" => true,
+                    "txt" => false,
+                    _ => return None,
+                };
+                let stem = path.file_stem()?.to_str()?.to_lowercase();
+                let rank = stems.iter().position(|s| *s == stem)?;
+                Some((rank, !is_lrc, path))
+            })
+            .collect();
+        candidates.sort();
+
+        let (_, is_txt, path) = candidates.into_iter().next()?;
+        debug!("loading lyrics from {path:?}");
+        let content = fs::read_to_string(path).ok()?;
+        Some(if is_txt {
+            Lyrics::from_text(&content)
+        } else {
+            Lyrics::from_lrc(&content)
+        })
+    }
+}
+
+/// The lyrics providers that are enabled in `config`, in the order in which they're asked.
+pub fn providers(config: &Config) -> Vec<Box<dyn LyricsProvider>> {
+    let directory = match config.values().lyrics_directory.as_deref() {
+        Some(directory) => expand_home(directory),
+        None => user_configuration_directory().map(|directory| directory.join(LYRICS_DIRECTORY)),
+    };
+
+    let mut providers: Vec<Box<dyn LyricsProvider>> = Vec::new();
+    if let Some(directory) = directory {
+        providers.push(Box::new(LocalLyricsProvider::new(directory)));
+    }
+    providers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_lrc() {
+        let lyrics = Lyrics::from_lrc(
+            "[ar:Someone]\n[offset:500]\n[00:12.00]First line\n[00:05.50][00:20]Chorus\n",
+        );
+        let times: Vec<Option<Duration>> = lyrics.lines.iter().map(|line| line.time).collect();
+        assert_eq!(
+            times,
+            vec![
+                Some(Duration::from_millis(5000)),
+                Some(Duration::from_millis(11500)),
+                Some(Duration::from_millis(19500)),
+            ]
+        );
+        assert_eq!(lyrics.lines[1].text, "First line");
+        assert_eq!(lyrics.current_line(Duration::from_secs(12)), Some(1));
+        assert_eq!(lyrics.current_line(Duration::from_secs(1)), None);
+
+        let plain = Lyrics::from_lrc("[ti:Song]\n\nJust text\n");
+        assert!(!plain.is_synced());
+        assert_eq!(plain.lines.len(), 1);
+    }
+}
@@ -15,22 +15,32 @@ mod authentication;
 mod cli;
 mod command;
 mod commands;
+mod completion;
 mod config;
 mod events;
 mod ext_traits;
+mod hooks;
 mod library;
+mod lyrics;
 mod model;
 mod panic;
+mod play_history;
 mod queue;
+mod scrobbler;
 mod serialization;
 mod sharing;
+mod sleep;
+mod smart_playlist;
 mod spotify;
 mod spotify_api;
 mod spotify_url;
 mod spotify_worker;
+mod stats;
+mod template;
 mod theme;
 mod traits;
 mod ui;
+mod undo;
 mod utils;
 
 #[cfg(unix)]
@@ -39,6 +49,9 @@ mod ipc;
 #[cfg(feature = "mpris")]
 mod mpris;
 
+#[cfg(feature = "scripting")]
+mod scripting;
+
 fn main() -> Result<(), String> {
     // Set a custom backtrace hook that writes the backtrace to a file instead of stdout, since
     // stdout is most likely in use by Cursive.
@@ -64,20 +77,23 @@ fn main() -> Result<(), String> {
     match matches.subcommand() {
         // 打印info信息
         Some(("info", _subcommand_matches)) => cli::info(),
+        Some(("stats", subcommand_matches)) => cli::stats(subcommand_matches),
         // 类似于panic!
         Some((_, _)) => unreachable!(),
         None => {
             // Create the application.
             // 构建应用
-            let mut application =
-                match Application::new(matches.get_one::<String>("config").cloned()) {
-                    Ok(application) => application,
-                    Err(error) => {
-                        eprintln!("{error}");
-                        error!("{error}");
-                        exit(-1);
-                    }
-                };
+            let mut application = match Application::new(
+                matches.get_one::<String>("config").cloned(),
+                matches.get_flag("mini"),
+            ) {
+                Ok(application) => application,
+                Err(error) => {
+                    eprintln!("{error}");
+                    error!("{error}");
+                    exit(-1);
+                }
+            };
 
             // Start the application event loop.
             // 运行应用
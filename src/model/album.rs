@@ -1,5 +1,7 @@
 use rand::{seq::IteratorRandom, thread_rng};
 use rspotify::model::Id;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
 use std::sync::{Arc, RwLock};
 
@@ -7,6 +9,7 @@ use chrono::{DateTime, Utc};
 use log::debug;
 use rspotify::model::album::{FullAlbum, SavedAlbum, SimplifiedAlbum};
 
+use crate::command::SortKey;
 use crate::library::Library;
 use crate::model::artist::Artist;
 use crate::model::playable::Playable;
@@ -15,6 +18,7 @@ use crate::queue::Queue;
 use crate::spotify::Spotify;
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
 use crate::ui::{album::AlbumView, listview::ListView};
+use crate::utils::without_article;
 
 #[derive(Clone, Deserialize, Serialize)]
 pub struct Album {
@@ -27,6 +31,8 @@ pub struct Album {
     pub url: Option<String>,
     pub tracks: Option<Vec<Track>>,
     pub added_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub popularity: Option<u32>,
     total_tracks: Option<usize>,
 }
 
@@ -69,6 +75,42 @@ impl Album {
             self.tracks = Some(collected_tracks);
         }
     }
+
+    /// Compare this album to `other` on a single sort `key`. Keys that only apply to tracks
+    /// compare as equal.
+    pub fn compare(
+        &self,
+        other: &Self,
+        key: &SortKey,
+        play_counts: &HashMap<String, u32>,
+    ) -> Ordering {
+        let play_count = |album: &Self| -> u32 {
+            album
+                .tracks
+                .iter()
+                .flatten()
+                .filter_map(|track| track.id.as_ref().and_then(|id| play_counts.get(id)))
+                .sum()
+        };
+
+        match key {
+            SortKey::Title | SortKey::Album => {
+                without_article(&self.title).cmp(&without_article(&other.title))
+            }
+            SortKey::Artist => self
+                .artists
+                .first()
+                .map(|artist| without_article(artist))
+                .cmp(&other.artists.first().map(|artist| without_article(artist))),
+            SortKey::ReleaseDate => self.year.cmp(&other.year),
+            SortKey::Added => self.added_at.cmp(&other.added_at),
+            SortKey::Popularity => self.popularity.cmp(&other.popularity),
+            SortKey::PlayCount => play_count(self).cmp(&play_count(other)),
+            SortKey::Duration | SortKey::TrackNumber | SortKey::DiscNumber | SortKey::Random => {
+                Ordering::Equal
+            }
+        }
+    }
 }
 
 impl From<&SimplifiedAlbum> for Album {
@@ -94,6 +136,7 @@ impl From<&SimplifiedAlbum> for Album {
             url: sa.id.as_ref().map(|id| id.url()),
             tracks: None,
             added_at: None,
+            popularity: None,
             total_tracks: None,
         }
     }
@@ -123,6 +166,7 @@ impl From<&FullAlbum> for Album {
             url: Some(fa.id.uri()),
             tracks,
             added_at: None,
+            popularity: Some(fa.popularity),
             total_tracks: Some(fa.tracks.total as usize),
         }
     }
@@ -206,9 +250,11 @@ impl ListItem for Album {
         self.load_all_tracks(queue.get_spotify());
 
         if let Some(tracks) = self.tracks.as_ref() {
-            for t in tracks.iter().rev() {
-                queue.insert_after_current(Playable::Track(t.clone()));
-            }
+            queue.record(|| {
+                for t in tracks.iter().rev() {
+                    queue.insert_after_current(Playable::Track(t.clone()));
+                }
+            });
         }
     }
 
@@ -216,9 +262,11 @@ impl ListItem for Album {
         self.load_all_tracks(queue.get_spotify());
 
         if let Some(tracks) = self.tracks.as_ref() {
-            for t in tracks {
-                queue.append(Playable::Track(t.clone()));
-            }
+            queue.record(|| {
+                for t in tracks {
+                    queue.append(Playable::Track(t.clone()));
+                }
+            });
         }
     }
 
@@ -135,9 +135,11 @@ impl ListItem for Artist {
         self.load_top_tracks(queue.get_spotify());
 
         if let Some(tracks) = self.tracks.as_ref() {
-            for t in tracks.iter().rev() {
-                queue.insert_after_current(Playable::Track(t.clone()));
-            }
+            queue.record(|| {
+                for t in tracks.iter().rev() {
+                    queue.insert_after_current(Playable::Track(t.clone()));
+                }
+            });
         }
     }
 
@@ -145,9 +147,11 @@ impl ListItem for Artist {
         self.load_top_tracks(queue.get_spotify());
 
         if let Some(tracks) = &self.tracks {
-            for t in tracks {
-                queue.append(Playable::Track(t.clone()));
-            }
+            queue.record(|| {
+                for t in tracks {
+                    queue.append(Playable::Track(t.clone()));
+                }
+            });
         }
     }
 
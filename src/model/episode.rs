@@ -1,3 +1,4 @@
+use crate::config::ColumnField;
 use crate::library::Library;
 use crate::model::playable::Playable;
 use crate::queue::Queue;
@@ -20,6 +21,9 @@ pub struct Episode {
     pub cover_url: Option<String>,
     pub added_at: Option<DateTime<Utc>>,
     pub list_index: usize,
+    /// The name of the show that the episode belongs to, if it's known.
+    #[serde(default)]
+    pub show: Option<String>,
 }
 
 impl Episode {
@@ -40,6 +44,7 @@ impl From<&SimplifiedEpisode> for Episode {
             cover_url: episode.images.first().map(|img| img.url.clone()),
             added_at: None,
             list_index: 0,
+            show: None,
         }
     }
 }
@@ -56,6 +61,7 @@ impl From<&FullEpisode> for Episode {
             cover_url: episode.images.first().map(|img| img.url.clone()),
             added_at: None,
             list_index: 0,
+            show: Some(episode.show.name.clone()),
         }
     }
 }
@@ -114,6 +120,10 @@ impl ListItem for Episode {
         true
     }
 
+    fn column(&self, field: ColumnField, library: &Library) -> Option<String> {
+        Playable::Episode(self.clone()).column(field, library)
+    }
+
     fn as_listitem(&self) -> Box<dyn ListItem> {
         Box::new(self.clone())
     }
@@ -1,6 +1,7 @@
 use chrono::{DateTime, Utc};
 use rspotify::model::PlayableItem;
 
+use crate::config::ColumnField;
 use crate::library::Library;
 use crate::model::album::Album;
 use crate::model::artist::Artist;
@@ -20,62 +21,82 @@ pub enum Playable {
 }
 
 impl Playable {
+    /// The placeholders that can be used in track formats.
+    pub const FIELDS: [&'static str; 13] = [
+        "artists",
+        "artist",
+        "title",
+        "album",
+        "album_artists",
+        "saved",
+        "duration",
+        "track_number",
+        "disc_number",
+        "added",
+        "year",
+        "show",
+        "index",
+    ];
+
+    /// Fill in the placeholders in `formatting`, see [crate::template::Template]. An
+    /// invalid format is shown as is.
     pub fn format(playable: &Self, formatting: &str, library: &Library) -> String {
-        formatting
-            .replace(
-                "%artists",
-                if let Some(artists) = playable.artists() {
-                    artists
-                        .iter()
-                        .map(|artist| artist.clone().name)
-                        .collect::<Vec<String>>()
-                        .join(", ")
-                } else {
-                    String::new()
-                }
-                .as_str(),
-            )
-            .replace(
-                "%artist",
-                if let Some(artists) = playable.artists() {
-                    artists.first().unwrap().clone().name
-                } else {
-                    String::new()
-                }
-                .as_str(),
-            )
-            .replace(
-                "%title",
-                match playable.clone() {
-                    Self::Episode(episode) => episode.name,
-                    Self::Track(track) => track.title,
-                }
-                .as_str(),
-            )
-            .replace(
-                "%album",
-                match playable.clone() {
-                    Self::Track(track) => track.album.unwrap_or_default(),
-                    _ => String::new(),
-                }
-                .as_str(),
-            )
-            .replace(
-                "%saved",
-                if library.is_saved_track(&match playable.clone() {
-                    Self::Episode(episode) => Self::Episode(episode),
-                    Self::Track(track) => Self::Track(track),
-                }) {
+        match library.cfg.template(formatting, &Self::FIELDS) {
+            Some(template) => template.render(&|name| playable.field(name, library)),
+            None => formatting.to_string(),
+        }
+    }
+
+    /// The value of the placeholder `name` of [Self::FIELDS], or an empty string if it doesn't
+    /// apply to this item.
+    pub fn field(&self, name: &str, library: &Library) -> String {
+        match (name, self) {
+            ("artists", _) => self
+                .artists()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|artist| artist.name)
+                .collect::<Vec<String>>()
+                .join(", "),
+            ("artist", _) => self
+                .artists()
+                .and_then(|artists| artists.into_iter().next())
+                .map(|artist| artist.name)
+                .unwrap_or_default(),
+            ("title", Self::Track(track)) => track.title.clone(),
+            ("title", Self::Episode(episode)) => episode.name.clone(),
+            ("album", Self::Track(track)) => track.album.clone().unwrap_or_default(),
+            ("album_artists", Self::Track(track)) => track.album_artists.join(", "),
+            ("saved", _) => {
+                if library.is_saved_track(self) {
                     if library.cfg.values().use_nerdfont.unwrap_or_default() {
-                        "\u{f012c}"
+                        "\u{f012c}".to_string()
                     } else {
-                        "✓"
+                        "✓".to_string()
                     }
                 } else {
-                    ""
-                },
-            )
-            .replace("%duration", playable.duration_str().as_str())
+                    String::new()
+                }
+            }
+            ("duration", _) => self.duration_str(),
+            ("track_number", Self::Track(track)) => track.track_number.to_string(),
+            ("disc_number", Self::Track(track)) => track.disc_number.to_string(),
+            ("added", _) => match self {
+                Self::Track(track) => track.added_at,
+                Self::Episode(episode) => episode.added_at,
+            }
+            .map(|added_at| added_at.format("%Y-%m-%d").to_string())
+            .unwrap_or_default(),
+            ("year", Self::Track(track)) => track
+                .release_date
+                .as_deref()
+                .map(|date| date.chars().take(4).collect())
+                .unwrap_or_default(),
+            ("year", Self::Episode(episode)) => episode.release_date.chars().take(4).collect(),
+            ("show", Self::Episode(episode)) => episode.show.clone().unwrap_or_default(),
+            ("index", _) => (self.list_index() + 1).to_string(),
+            _ => String::new(),
+        }
     }
 
     pub fn id(&self) -> Option<String> {
@@ -233,6 +254,31 @@ impl ListItem for Playable {
         self.as_listitem().track()
     }
 
+    fn column(&self, field: ColumnField, library: &Library) -> Option<String> {
+        let name = match field {
+            ColumnField::Index => "index",
+            ColumnField::Title => "title",
+            ColumnField::Artists => "artists",
+            ColumnField::Album => "album",
+            ColumnField::Duration => "duration",
+            ColumnField::Added => "added",
+            ColumnField::TrackNumber => "track_number",
+            ColumnField::Saved => "saved",
+            // Only the list knows which items were added by autoplay.
+            ColumnField::Auto => return Some(String::new()),
+            ColumnField::Popularity => {
+                return Some(match self {
+                    Self::Track(track) => track
+                        .popularity
+                        .map(|popularity| popularity.to_string())
+                        .unwrap_or_default(),
+                    Self::Episode(_) => String::new(),
+                })
+            }
+        };
+        Some(self.field(name, library))
+    }
+
     fn as_listitem(&self) -> Box<dyn ListItem> {
         self.as_listitem()
     }
@@ -1,6 +1,6 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::iter::Iterator;
 use std::sync::{Arc, RwLock};
-use std::{cmp::Ordering, iter::Iterator};
 
 use rand::{seq::IteratorRandom, thread_rng};
 
@@ -8,13 +8,15 @@ use log::{debug, warn};
 use rspotify::model::playlist::{FullPlaylist, SimplifiedPlaylist};
 use rspotify::model::Id;
 
+use crate::config::SortingOrder;
+use crate::library::Library;
 use crate::model::playable::Playable;
 use crate::model::track::Track;
 use crate::queue::Queue;
 use crate::spotify::Spotify;
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
 use crate::ui::{listview::ListView, playlist::PlaylistView};
-use crate::{command::SortDirection, command::SortKey, library::Library};
+use crate::undo::Change;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Playlist {
@@ -26,9 +28,28 @@ pub struct Playlist {
     pub num_tracks: usize,
     pub tracks: Option<Vec<Playable>>,
     pub collaborative: bool,
+    /// Whether this is a locally generated smart playlist that doesn't exist on Spotify.
+    #[serde(default)]
+    pub is_smart: bool,
 }
 
 impl Playlist {
+    /// Create a smart playlist from tracks that were selected by a
+    /// [SmartPlaylistRule](crate::smart_playlist::SmartPlaylistRule).
+    pub fn smart(id: String, name: String, owner_id: String, tracks: Vec<Playable>) -> Self {
+        Self {
+            id,
+            name,
+            owner_id,
+            owner_name: None,
+            snapshot_id: String::new(),
+            num_tracks: tracks.len(),
+            tracks: Some(tracks),
+            collaborative: false,
+            is_smart: true,
+        }
+    }
+
     /// 加载歌单曲目
     pub fn load_tracks(&mut self, spotify: &Spotify) {
         if self.tracks.is_some() {
@@ -60,6 +81,11 @@ impl Playlist {
     }
 
     pub fn delete_track(&mut self, index: usize, spotify: Spotify, library: &Library) -> bool {
+        if self.is_smart {
+            warn!("can't delete tracks from a smart playlist");
+            return false;
+        }
+
         let playable = self.tracks.as_ref().unwrap()[index].clone();
         debug!("deleting track: {} {:?}", index, playable);
 
@@ -70,86 +96,76 @@ impl Playlist {
 
         match spotify
             .api
-            .delete_tracks(&self.id, &self.snapshot_id, &[playable])
-            .is_ok()
+            .delete_tracks(&self.id, &self.snapshot_id, &[playable.clone()])
         {
-            false => false,
-            true => {
+            Err(_) => false,
+            Ok(result) => {
+                self.snapshot_id = result.snapshot_id;
                 if let Some(tracks) = &mut self.tracks {
                     tracks.remove(index);
                     library.playlist_update(self);
                 }
 
+                library.history.record(Change::PlaylistTracksRemoved {
+                    playlist_id: self.id.clone(),
+                    snapshot_id: self.snapshot_id.clone(),
+                    position: playable.list_index(),
+                    tracks: vec![playable],
+                });
+
                 true
             }
         }
     }
 
     pub fn append_tracks(&mut self, new_tracks: &[Playable], spotify: &Spotify, library: &Library) {
-        let mut has_modified = false;
+        if self.is_smart {
+            warn!("can't add tracks to a smart playlist");
+            return;
+        }
 
-        if spotify
-            .api
-            .append_tracks(&self.id, new_tracks, None)
-            .is_ok()
-        {
+        let position = self
+            .tracks
+            .as_ref()
+            .map(|tracks| tracks.len())
+            .unwrap_or(self.num_tracks);
+        let new_tracks = new_tracks.to_vec();
+
+        if let Ok(result) = spotify.api.append_tracks(&self.id, &new_tracks, None) {
+            self.snapshot_id = result.snapshot_id;
             if let Some(tracks) = &mut self.tracks {
-                tracks.append(&mut new_tracks.to_vec());
-                has_modified = true;
+                tracks.extend(new_tracks.iter().cloned());
+                library.playlist_update(self);
             }
-        }
 
-        if has_modified {
-            library.playlist_update(self);
+            library.history.record(Change::PlaylistTracksAdded {
+                playlist_id: self.id.clone(),
+                snapshot_id: self.snapshot_id.clone(),
+                position,
+                tracks: new_tracks,
+            });
         }
     }
 
-    pub fn sort(&mut self, key: &SortKey, direction: &SortDirection) {
-        fn compare_artists(a: &[String], b: &[String]) -> Ordering {
-            let sanitize_artists_name = |x: &[String]| -> Vec<String> {
-                x.iter()
-                    .map(|x| {
-                        x.to_lowercase()
-                            .split(' ')
-                            .skip_while(|x| x == &"the")
-                            .collect()
-                    })
-                    .collect()
-            };
-
-            let a = sanitize_artists_name(a);
-            let b = sanitize_artists_name(b);
-
-            a.cmp(&b)
-        }
-
-        fn compare_album(a: &Track, b: &Track) -> Ordering {
-            a.album
-                .as_ref()
-                .map(|x| x.to_lowercase())
-                .cmp(&b.album.as_ref().map(|x| x.to_lowercase()))
-                .then_with(|| a.disc_number.cmp(&b.disc_number))
-                .then_with(|| a.track_number.cmp(&b.track_number))
-        }
+    /// Sort the tracks of this playlist by `order`. Episodes stay at their positions.
+    pub fn sort(&mut self, order: &SortingOrder, play_counts: &HashMap<String, u32>) {
+        let Some(items) = self.tracks.as_mut() else {
+            return;
+        };
 
-        if let Some(c) = self.tracks.as_mut() {
-            c.sort_by(|a, b| match (a.track(), b.track()) {
-                (Some(a), Some(b)) => {
-                    let (a, b) = match *direction {
-                        SortDirection::Ascending => (a, b),
-                        SortDirection::Descending => (b, a),
-                    };
-                    match *key {
-                        SortKey::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
-                        SortKey::Duration => a.duration.cmp(&b.duration),
-                        SortKey::Album => compare_album(&a, &b),
-                        SortKey::Added => a.added_at.cmp(&b.added_at),
-                        SortKey::Artist => compare_artists(&a.artists, &b.artists)
-                            .then_with(|| compare_album(&a, &b)),
-                    }
-                }
-                _ => std::cmp::Ordering::Equal,
+        // Only the tracks are sorted, and put back in the positions that held tracks before.
+        let (positions, mut tracks): (Vec<usize>, Vec<Track>) = items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| match item {
+                Playable::Track(track) => Some((index, track.clone())),
+                Playable::Episode(_) => None,
             })
+            .unzip();
+        order.sort(&mut tracks, |a, b, key| a.compare(b, key, play_counts));
+
+        for (index, track) in positions.into_iter().zip(tracks) {
+            items[index] = Playable::Track(track);
         }
     }
 }
@@ -165,6 +181,7 @@ impl From<&SimplifiedPlaylist> for Playlist {
             num_tracks: list.tracks.total as usize,
             tracks: None,
             collaborative: list.collaborative,
+            is_smart: false,
         }
     }
 }
@@ -180,6 +197,7 @@ impl From<&FullPlaylist> for Playlist {
             num_tracks: list.tracks.total as usize,
             tracks: None,
             collaborative: list.collaborative,
+            is_smart: false,
         }
     }
 }
@@ -202,6 +220,10 @@ impl ListItem for Playlist {
     }
 
     fn display_left(&self, library: &Library) -> String {
+        if self.is_smart {
+            return format!("{} • smart playlist", self.name);
+        }
+
         let hide_owners = library.cfg.values().hide_display_names.unwrap_or(false);
         match (self.owner_name.as_ref(), hide_owners) {
             (Some(owner), false) => format!("{} • {}", self.name, owner),
@@ -242,9 +264,11 @@ impl ListItem for Playlist {
         self.load_tracks(&queue.get_spotify());
 
         if let Some(tracks) = self.tracks.as_ref() {
-            for track in tracks.iter().rev() {
-                queue.insert_after_current(track.clone());
-            }
+            queue.record(|| {
+                for track in tracks.iter().rev() {
+                    queue.insert_after_current(track.clone());
+                }
+            });
         }
     }
 
@@ -252,13 +276,19 @@ impl ListItem for Playlist {
         self.load_tracks(&queue.get_spotify());
 
         if let Some(tracks) = self.tracks.as_ref() {
-            for track in tracks.iter() {
-                queue.append(track.clone());
-            }
+            queue.record(|| {
+                for track in tracks.iter() {
+                    queue.append(track.clone());
+                }
+            });
         }
     }
 
     fn toggle_saved(&mut self, library: &Library) {
+        if self.is_smart {
+            return;
+        }
+
         // Don't allow users to unsave their own playlists with one keypress
         if !library.is_followed_playlist(self) {
             return;
@@ -272,10 +302,22 @@ impl ListItem for Playlist {
     }
 
     fn save(&mut self, library: &Library) {
+        // Saving a smart playlist turns a snapshot of it into a regular Spotify playlist
+        if self.is_smart {
+            if let Some(tracks) = self.tracks.as_ref() {
+                library.save_playlist(&self.name, tracks);
+            }
+            return;
+        }
+
         library.follow_playlist(self.clone());
     }
 
     fn unsave(&mut self, library: &Library) {
+        if self.is_smart {
+            return;
+        }
+
         library.delete_playlist(&self.id);
     }
 
@@ -329,6 +371,10 @@ impl ListItem for Playlist {
     }
 
     fn share_url(&self) -> Option<String> {
+        if self.is_smart {
+            return None;
+        }
+
         Some(format!(
             "https://open.spotify.com/user/{}/playlist/{}",
             self.owner_id, self.id
@@ -336,6 +382,10 @@ impl ListItem for Playlist {
     }
 
     fn is_saved(&self, library: &Library) -> Option<bool> {
+        if self.is_smart {
+            return None;
+        }
+
         // save status of personal playlists can't be toggled for safety
         if !library.is_followed_playlist(self) {
             return None;
@@ -32,7 +32,10 @@ impl Show {
             episodes_result.next();
         }
 
-        let episodes = episodes_result.items.read().unwrap().clone();
+        let mut episodes = episodes_result.items.read().unwrap().clone();
+        for episode in &mut episodes {
+            episode.show = Some(self.name.clone());
+        }
         self.episodes = Some(episodes);
     }
 }
@@ -112,18 +115,22 @@ impl ListItem for Show {
         self.load_all_episodes(queue.get_spotify());
 
         if let Some(episodes) = self.episodes.as_ref() {
-            for ep in episodes.iter().rev() {
-                queue.insert_after_current(Playable::Episode(ep.clone()));
-            }
+            queue.record(|| {
+                for ep in episodes.iter().rev() {
+                    queue.insert_after_current(Playable::Episode(ep.clone()));
+                }
+            });
         }
     }
 
     fn queue(&mut self, queue: &Queue) {
         self.load_all_episodes(queue.get_spotify());
 
-        for ep in self.episodes.as_ref().unwrap_or(&Vec::new()) {
-            queue.append(Playable::Episode(ep.clone()));
-        }
+        queue.record(|| {
+            for ep in self.episodes.as_ref().unwrap_or(&Vec::new()) {
+                queue.append(Playable::Episode(ep.clone()));
+            }
+        });
     }
 
     fn toggle_saved(&mut self, library: &Library) {
@@ -1,8 +1,11 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
 use std::sync::{Arc, RwLock};
 
-use crate::config;
-use crate::utils::ms_to_hms;
+use crate::command::SortKey;
+use crate::config::{self, ColumnField};
+use crate::utils::{ms_to_hms, without_article};
 use chrono::{DateTime, Utc};
 use rspotify::model::album::FullAlbum;
 use rspotify::model::track::{FullTrack, SavedTrack, SimplifiedTrack};
@@ -35,9 +38,39 @@ pub struct Track {
     pub list_index: usize,
     pub is_local: bool,
     pub is_playable: Option<bool>,
+    #[serde(default)]
+    pub release_date: Option<String>,
+    #[serde(default)]
+    pub popularity: Option<u32>,
 }
 
 impl Track {
+    /// A track with the given `id`, `title`, `artist` and `album` to use in tests.
+    #[cfg(test)]
+    pub fn test(id: &str, title: &str, artist: &str, album: Option<&str>) -> Self {
+        Self {
+            id: Some(id.into()),
+            uri: format!("spotify:track:{id}"),
+            title: title.into(),
+            track_number: 1,
+            disc_number: 1,
+            duration: 0,
+            artists: vec![artist.into()],
+            artist_ids: Vec::new(),
+            album: album.map(str::to_string),
+            album_id: album.map(str::to_string),
+            album_artists: Vec::new(),
+            cover_url: None,
+            url: String::new(),
+            added_at: None,
+            list_index: 0,
+            is_local: false,
+            is_playable: None,
+            release_date: None,
+            popularity: None,
+        }
+    }
+
     pub fn from_simplified_track(track: &SimplifiedTrack, album: &FullAlbum) -> Self {
         let artists = track
             .artists
@@ -73,12 +106,65 @@ impl Track {
             list_index: 0,
             is_local: track.is_local,
             is_playable: track.is_playable,
+            release_date: Some(album.release_date.clone()),
+            popularity: None,
         }
     }
 
     pub fn duration_str(&self) -> String {
         ms_to_hms(self.duration)
     }
+
+    /// Compare this track to `other` on a single sort `key`. `play_counts` maps track ids to how
+    /// often they were played.
+    pub fn compare(
+        &self,
+        other: &Self,
+        key: &SortKey,
+        play_counts: &HashMap<String, u32>,
+    ) -> Ordering {
+        fn compare_artists(a: &[String], b: &[String]) -> Ordering {
+            let a = a.iter().map(|name| without_article(name));
+            let b = b.iter().map(|name| without_article(name));
+            a.cmp(b)
+        }
+
+        fn compare_album(a: &Track, b: &Track) -> Ordering {
+            a.album
+                .as_ref()
+                .map(|x| x.to_lowercase())
+                .cmp(&b.album.as_ref().map(|x| x.to_lowercase()))
+                .then_with(|| a.disc_number.cmp(&b.disc_number))
+                .then_with(|| a.track_number.cmp(&b.track_number))
+        }
+
+        let play_count = |track: &Self| {
+            track
+                .id
+                .as_ref()
+                .and_then(|id| play_counts.get(id))
+                .copied()
+                .unwrap_or(0)
+        };
+
+        match key {
+            SortKey::Title => self.title.to_lowercase().cmp(&other.title.to_lowercase()),
+            SortKey::Duration => self.duration.cmp(&other.duration),
+            SortKey::Album => compare_album(self, other),
+            SortKey::Added => self.added_at.cmp(&other.added_at),
+            SortKey::Artist => compare_artists(&self.artists, &other.artists)
+                .then_with(|| compare_album(self, other)),
+            SortKey::ReleaseDate => self.release_date.cmp(&other.release_date),
+            SortKey::TrackNumber => self
+                .disc_number
+                .cmp(&other.disc_number)
+                .then_with(|| self.track_number.cmp(&other.track_number)),
+            SortKey::DiscNumber => self.disc_number.cmp(&other.disc_number),
+            SortKey::Popularity => self.popularity.cmp(&other.popularity),
+            SortKey::PlayCount => play_count(self).cmp(&play_count(other)),
+            SortKey::Random => Ordering::Equal,
+        }
+    }
 }
 
 impl From<&SimplifiedTrack> for Track {
@@ -112,6 +198,8 @@ impl From<&SimplifiedTrack> for Track {
             list_index: 0,
             is_local: track.is_local,
             is_playable: track.is_playable,
+            release_date: None,
+            popularity: None,
         }
     }
 }
@@ -153,6 +241,8 @@ impl From<&FullTrack> for Track {
             list_index: 0,
             is_local: track.is_local,
             is_playable: track.is_playable,
+            release_date: track.album.release_date.clone(),
+            popularity: Some(track.popularity),
         }
     }
 }
@@ -339,7 +429,73 @@ impl ListItem for Track {
         true
     }
 
+    fn column(&self, field: ColumnField, library: &Library) -> Option<String> {
+        Playable::Track(self.clone()).column(field, library)
+    }
+
     fn as_listitem(&self) -> Box<dyn ListItem> {
         Box::new(self.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: &str, artist: &str, disc_number: i32, track_number: u32) -> Track {
+        Track {
+            disc_number,
+            track_number,
+            ..Track::test(id, id, artist, None)
+        }
+    }
+
+    #[test]
+    fn compares_artists_without_article() {
+        let counts = HashMap::new();
+        let beatles = track("a", "The Beatles", 1, 1);
+        let blur = track("b", "Blur", 1, 1);
+        let cure = track("c", "the Cure", 1, 1);
+
+        assert_eq!(
+            beatles.compare(&blur, &SortKey::Artist, &counts),
+            Ordering::Less
+        );
+        assert_eq!(
+            cure.compare(&blur, &SortKey::Artist, &counts),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn compares_track_number_within_disc() {
+        let counts = HashMap::new();
+        let first = track("a", "artist", 1, 9);
+        let second = track("b", "artist", 2, 1);
+
+        assert_eq!(
+            first.compare(&second, &SortKey::TrackNumber, &counts),
+            Ordering::Less
+        );
+        assert_eq!(
+            first.compare(&second, &SortKey::DiscNumber, &counts),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn compares_play_counts() {
+        let counts = HashMap::from([("a".to_string(), 3)]);
+        let played = track("a", "artist", 1, 1);
+        let unplayed = track("b", "artist", 1, 1);
+
+        assert_eq!(
+            played.compare(&unplayed, &SortKey::PlayCount, &counts),
+            Ordering::Greater
+        );
+        assert_eq!(
+            unplayed.compare(&unplayed, &SortKey::PlayCount, &counts),
+            Ordering::Equal
+        );
+    }
+}
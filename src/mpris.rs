@@ -321,7 +321,7 @@ impl MprisPlayer {
     async fn seeked(context: &SignalEmitter<'_>, position: &i64) -> zbus::Result<()>;
 
     fn next(&self) {
-        self.queue.next(true)
+        self.queue.next(true);
     }
 
     fn previous(&self) {
@@ -0,0 +1,199 @@
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Local, Utc};
+use log::{debug, error};
+
+use crate::library::Library;
+use crate::model::album::Album;
+use crate::model::artist::Artist;
+use crate::model::playable::Playable;
+use crate::model::track::Track;
+use crate::queue::Queue;
+use crate::traits::{ListItem, ViewExt};
+use crate::utils::ms_to_hms;
+
+/// Play history log filename, stored next to the user state.
+pub const PLAY_HISTORY_FILE_NAME: &str = "history.jsonl";
+
+/// A single played item in the [PlayHistory].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub playable: Playable,
+    /// When the item stopped playing.
+    pub played_at: DateTime<Utc>,
+    /// How long the item was actually listened to.
+    pub listened: Duration,
+    /// Whether playback moved on before the item finished.
+    pub skipped: bool,
+}
+
+impl HistoryEntry {
+    pub fn new(playable: Playable, listened: Duration, skipped: bool) -> Self {
+        Self {
+            playable,
+            played_at: Utc::now(),
+            listened,
+            skipped,
+        }
+    }
+}
+
+/// The log of all the items that were played, oldest first. Every entry is appended to a file
+/// as a line of JSON, so the log survives crashes and doesn't have to be rewritten.
+pub struct PlayHistory {
+    entries: RwLock<Vec<HistoryEntry>>,
+    path: PathBuf,
+}
+
+impl PlayHistory {
+    /// Load the play history from the file at `path`. Lines that can't be parsed are skipped.
+    pub fn load(path: PathBuf) -> Self {
+        let mut entries = Vec::new();
+
+        if let Ok(file) = File::open(&path) {
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
+                match serde_json::from_str(&line) {
+                    Ok(entry) => entries.push(entry),
+                    Err(e) => error!("can't parse play history entry: {e}"),
+                }
+            }
+            debug!("loaded {} play history entries", entries.len());
+        }
+
+        Self {
+            entries: RwLock::new(entries),
+            path,
+        }
+    }
+
+    /// Append `entry` to the history.
+    pub fn record(&self, entry: HistoryEntry) {
+        let write_result = serde_json::to_string(&entry)
+            .map_err(|e| e.to_string())
+            .and_then(|line| {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)
+                    .and_then(|mut file| writeln!(file, "{line}"))
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = write_result {
+            error!("could not write play history: {e}");
+        }
+
+        self.entries.write().unwrap().push(entry);
+    }
+
+    /// The amount of entries in the history.
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    /// All entries, oldest first.
+    pub fn entries(&self) -> Vec<HistoryEntry> {
+        self.entries.read().unwrap().clone()
+    }
+
+    /// The entry at `index`.
+    pub fn get(&self, index: usize) -> Option<HistoryEntry> {
+        self.entries.read().unwrap().get(index).cloned()
+    }
+
+    /// Find the most recent entry before `index` whose item isn't one of the `excluded` URIs.
+    /// Returns the index of the entry and its item.
+    pub fn previous(&self, index: usize, excluded: &HashSet<String>) -> Option<(usize, Playable)> {
+        let entries = self.entries.read().unwrap();
+        entries[..index.min(entries.len())]
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, entry)| !excluded.contains(&entry.playable.uri()))
+            .map(|(index, entry)| (index, entry.playable.clone()))
+    }
+}
+
+impl ListItem for HistoryEntry {
+    fn is_playing(&self, queue: &Queue) -> bool {
+        self.playable.is_playing(queue)
+    }
+
+    fn display_left(&self, library: &Library) -> String {
+        self.playable.display_left(library)
+    }
+
+    fn display_center(&self, library: &Library) -> String {
+        self.playable.display_center(library)
+    }
+
+    fn display_right(&self, _library: &Library) -> String {
+        let played_at = self
+            .played_at
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M");
+        let listened = ms_to_hms(self.listened.as_millis().try_into().unwrap_or(u32::MAX));
+        let skipped = if self.skipped { "skipped " } else { "" };
+        format!("{skipped}{played_at}  {listened}")
+    }
+
+    fn play(&mut self, queue: &Queue) {
+        self.playable.play(queue)
+    }
+
+    fn play_next(&mut self, queue: &Queue) {
+        self.playable.play_next(queue)
+    }
+
+    fn queue(&mut self, queue: &Queue) {
+        self.playable.queue(queue)
+    }
+
+    fn toggle_saved(&mut self, library: &Library) {
+        self.playable.toggle_saved(library)
+    }
+
+    fn save(&mut self, library: &Library) {
+        self.playable.save(library)
+    }
+
+    fn unsave(&mut self, library: &Library) {
+        self.playable.unsave(library)
+    }
+
+    fn open(&self, queue: Arc<Queue>, library: Arc<Library>) -> Option<Box<dyn ViewExt>> {
+        self.playable.open(queue, library)
+    }
+
+    fn share_url(&self) -> Option<String> {
+        self.playable.share_url()
+    }
+
+    fn album(&self, queue: &Queue) -> Option<Album> {
+        self.playable.album(queue)
+    }
+
+    fn artists(&self) -> Option<Vec<Artist>> {
+        self.playable.artists()
+    }
+
+    fn track(&self) -> Option<Track> {
+        self.playable.track()
+    }
+
+    fn is_saved(&self, library: &Library) -> Option<bool> {
+        self.playable.as_listitem().is_saved(library)
+    }
+
+    fn is_playable(&self) -> bool {
+        self.playable.as_listitem().is_playable()
+    }
+
+    fn as_listitem(&self) -> Box<dyn ListItem> {
+        Box::new(self.clone())
+    }
+}
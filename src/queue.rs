@@ -1,6 +1,10 @@
 use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
+use chrono::{DateTime, Utc};
 use log::{debug, info};
 #[cfg(feature = "notify")]
 use notify_rust::Notification;
@@ -8,11 +12,17 @@ use notify_rust::Notification;
 use rand::prelude::*;
 use strum_macros::Display;
 
-use crate::config::Config;
+use crate::config::{Config, QueueState};
+use crate::events::{Event, EventManager};
 use crate::library::Library;
 use crate::model::playable::Playable;
+use crate::model::track::Track;
+use crate::play_history::HistoryEntry;
+use crate::sleep::{faded_volume, SleepAction, SleepMode, SleepTimer, FADE_DURATION};
 use crate::spotify::PlayerEvent;
 use crate::spotify::Spotify;
+use crate::undo::Change;
+use crate::utils::ms_to_hms;
 
 /// Repeat behavior for the [Queue].
 /// 循环枚举
@@ -26,12 +36,126 @@ pub enum RepeatSetting {
     RepeatTrack, // 循环单曲
 }
 
+/// The strategy that is used to generate the playback order when shuffle is enabled.
+#[derive(Display, Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ShuffleStrategy {
+    /// Uniformly random order.
+    #[default]
+    Random,
+    /// Random order that avoids playing the same artist or album back-to-back.
+    Spread,
+    /// Shuffle the albums, but keep the order of the tracks within an album.
+    Album,
+    /// Random order that favours tracks that haven't been played recently.
+    Weighted,
+}
+
+/// A copy of the contents of the [Queue] at some point in time.
+#[derive(Clone, Debug)]
+pub struct QueueSnapshot {
+    pub items: Vec<Playable>,
+    pub random_order: Option<Vec<usize>>,
+    pub auto_added: AutoAdded,
+}
+
+impl QueueSnapshot {
+    /// Whether both snapshots contain the same items in the same order, with the same items marked
+    /// as added by autoplay.
+    fn has_same_items(&self, other: &Self) -> bool {
+        self.auto_added == other.auto_added
+            && self.items.len() == other.items.len()
+            && self
+                .items
+                .iter()
+                .zip(&other.items)
+                .all(|(a, b)| a.uri() == b.uri())
+    }
+}
+
+/// Whether each item of the [Queue] was added by autoplay, by its index in the queue.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutoAdded(Vec<bool>);
+
+impl AutoAdded {
+    /// Mark the item at `index` as added by autoplay or not, shifting the items after it.
+    fn insert(&mut self, index: usize, auto_added: bool) {
+        if self.0.len() < index {
+            self.0.resize(index, false);
+        }
+        self.0.insert(index, auto_added);
+    }
+
+    /// Add `count` items at the end that are all marked as added by autoplay or not.
+    fn extend(&mut self, len: usize, count: usize, auto_added: bool) {
+        self.0.resize(len, false);
+        self.0.resize(len + count, auto_added);
+    }
+
+    fn remove(&mut self, index: usize) {
+        if index < self.0.len() {
+            self.0.remove(index);
+        }
+    }
+
+    fn shift(&mut self, from: usize, to: usize) {
+        let auto_added = self.get(from);
+        self.remove(from);
+        self.insert(to, auto_added);
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Whether the item at `index` was added by autoplay.
+    fn get(&self, index: usize) -> bool {
+        self.0.get(index).copied().unwrap_or(false)
+    }
+
+    /// The marks for a queue of `len` items.
+    fn fit(mut self, len: usize) -> Self {
+        self.0.resize(len, false);
+        self
+    }
+
+    /// The indexes of the items that were added by autoplay, except for `keep`.
+    fn indexes(&self, keep: Option<usize>) -> Vec<usize> {
+        (0..self.0.len())
+            .filter(|&index| self.get(index) && Some(index) != keep)
+            .collect()
+    }
+}
+
+/// Amount of tracks that are added to the queue at once by autoplay.
+const AUTOPLAY_BATCH_SIZE: usize = 20;
+
+/// Amount of recently played tracks that are used to seed autoplay recommendations.
+const AUTOPLAY_SEED_TRACKS: usize = 3;
+
+/// Tracks that were played within this amount of hours aren't added by autoplay.
+const AUTOPLAY_AVOID_HOURS: i64 = 24;
+
 /// Events that are specific to the [Queue].
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub enum QueueEvent {
     /// Request the player to 'preload' a track, basically making sure that
     /// transitions between tracks can be uninterrupted.
     PreloadTrackRequest,
+    /// Recommendations that were fetched in the background for autoplay.
+    AutoplayTracks(Vec<Track>),
+}
+
+/// The state of the autoplay recommendations that are fetched in the background.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum AutoplayFetch {
+    Idle,
+    /// The recommendations are being fetched. `play` is set when playback stopped at the end of
+    /// the queue in the meantime, so that the first recommendation is played when they arrive.
+    Pending {
+        play: bool,
+    },
 }
 
 /// The queue determines the playback order of [Playable] items, and is also used to control
@@ -53,12 +177,34 @@ pub struct Queue {
     cfg: Arc<Config>,
     // library实例
     library: Arc<Library>,
+    /// The running sleep timer, if any.
+    sleep_timer: RwLock<Option<SleepTimer>>,
+    /// Whether playback should end when the current item finishes.
+    stop_after_current: RwLock<bool>,
+    /// The volume from before a sleep timer started fading it out.
+    faded_from: RwLock<Option<u16>>,
+    /// The play history entry that was last stepped back to with [Queue::previous].
+    history_cursor: RwLock<Option<usize>>,
+    /// Which items in `self.queue` were added by autoplay.
+    auto_added: RwLock<AutoAdded>,
+    /// Whether autoplay recommendations are being fetched.
+    autoplay_fetch: RwLock<AutoplayFetch>,
+    /// The amount of nested [Queue::record] calls that are running.
+    recording: RwLock<usize>,
+    /// Used to hand the autoplay recommendations back to the main event loop.
+    ev: EventManager,
 }
 
 impl Queue {
-    pub fn new(spotify: Spotify, cfg: Arc<Config>, library: Arc<Library>) -> Self {
+    pub fn new(
+        spotify: Spotify,
+        cfg: Arc<Config>,
+        library: Arc<Library>,
+        ev: EventManager,
+    ) -> Self {
         // 获取播放列表状态缓存
         let queue_state = cfg.state().queuestate.clone();
+        let auto_added = queue_state.auto_added_items.fit(queue_state.queue.len());
 
         Self {
             queue: Arc::new(RwLock::new(queue_state.queue)),
@@ -67,6 +213,14 @@ impl Queue {
             random_order: RwLock::new(queue_state.random_order),
             cfg,
             library,
+            sleep_timer: RwLock::new(None),
+            stop_after_current: RwLock::new(false),
+            faded_from: RwLock::new(None),
+            history_cursor: RwLock::new(None),
+            auto_added: RwLock::new(auto_added),
+            autoplay_fetch: RwLock::new(AutoplayFetch::Idle),
+            recording: RwLock::new(0),
+            ev,
         }
     }
 
@@ -145,6 +299,10 @@ impl Queue {
     /// playing item, taking into account shuffle status.
     /// 插入歌曲到当前播放歌曲后面
     pub fn insert_after_current(&self, track: Playable) {
+        self.record(|| self.insert_item_after_current(track));
+    }
+
+    fn insert_item_after_current(&self, track: Playable) {
         if let Some(index) = self.get_current_index() {
             let mut random_order = self.random_order.write().unwrap();
             if let Some(order) = random_order.as_mut() {
@@ -165,15 +323,20 @@ impl Queue {
             // 再插入下一首歌曲到queue中
             let mut q = self.queue.write().unwrap();
             q.insert(index + 1, track);
+            self.auto_added.write().unwrap().insert(index + 1, false);
         } else {
             // 没有当前播放歌曲，则插入到队列的末尾
-            self.append(track);
+            self.append_item(track);
         }
     }
 
     /// Add `track` to the end of the queue.
     /// 将歌曲插入到队列的末尾
     pub fn append(&self, track: Playable) {
+        self.record(|| self.append_item(track));
+    }
+
+    fn append_item(&self, track: Playable) {
         let mut random_order = self.random_order.write().unwrap();
         if let Some(order) = random_order.as_mut() {
             // 饱和减法
@@ -184,6 +347,7 @@ impl Queue {
         }
 
         let mut q = self.queue.write().unwrap();
+        self.auto_added.write().unwrap().extend(q.len(), 1, false);
         q.push(track);
     }
 
@@ -191,32 +355,51 @@ impl Queue {
     /// shuffle status. Returns the amount of added items.
     /// 将歌曲插入到当前曲目之后，考虑顺序播放状态。返回添加的曲目数量
     pub fn append_next(&self, tracks: &Vec<Playable>) -> usize {
-        let mut q = self.queue.write().unwrap();
+        self.record(|| {
+            let mut q = self.queue.write().unwrap();
 
-        {
-            let mut random_order = self.random_order.write().unwrap();
-            if let Some(order) = random_order.as_mut() {
-                order.extend((q.len().saturating_sub(1))..(q.len() + tracks.len()));
+            {
+                let mut random_order = self.random_order.write().unwrap();
+                if let Some(order) = random_order.as_mut() {
+                    order.extend((q.len().saturating_sub(1))..(q.len() + tracks.len()));
+                }
             }
-        }
-
-        let first = match *self.current_track.read().unwrap() {
-            Some(index) => index + 1,
-            None => q.len(),
-        };
 
-        let mut i = first;
-        for track in tracks {
-            q.insert(i, track.clone());
-            i += 1;
-        }
+            let first = match *self.current_track.read().unwrap() {
+                Some(index) => index + 1,
+                None => q.len(),
+            };
+
+            let mut auto_added = self.auto_added.write().unwrap();
+            let mut i = first;
+            for track in tracks {
+                q.insert(i, track.clone());
+                auto_added.insert(i, false);
+                i += 1;
+            }
 
-        first
+            first
+        })
     }
 
     /// Remove the item at `index`. This doesn't take into account shuffle
     /// status, and will literally remove the item at `index` in `self.queue`.
+    /// The removal can be undone.
     pub fn remove(&self, index: usize) {
+        self.record(|| self.remove_item(index));
+    }
+
+    /// Remove the items at the sorted `indexes` like [Queue::remove], as one change that can be
+    /// undone.
+    pub fn remove_many(&self, indexes: &[usize]) {
+        self.record(|| {
+            for &index in indexes.iter().rev() {
+                self.remove_item(index);
+            }
+        });
+    }
+
+    fn remove_item(&self, index: usize) {
         {
             let mut q = self.queue.write().unwrap();
             if q.len() == 0 {
@@ -224,6 +407,7 @@ impl Queue {
                 return;
             }
             q.remove(index);
+            self.auto_added.write().unwrap().remove(index);
         }
 
         // if the queue is empty stop playback
@@ -265,12 +449,17 @@ impl Queue {
         }
     }
 
-    /// Clear all the items from the queue and stop playback.
+    /// Clear all the items from the queue and stop playback. This can be undone.
     pub fn clear(&self) {
+        self.record(|| self.clear_items());
+    }
+
+    fn clear_items(&self) {
         self.stop();
 
         let mut q = self.queue.write().unwrap();
         q.clear();
+        self.auto_added.write().unwrap().clear();
 
         let mut random_order = self.random_order.write().unwrap();
         if let Some(o) = random_order.as_mut() {
@@ -283,11 +472,44 @@ impl Queue {
         self.queue.read().unwrap().len()
     }
 
+    /// The indexes in `self.queue` of at most `count` items that are played next, in the order in
+    /// which they're played. Repeating the queue isn't taken into account.
+    pub fn upcoming_indexes(&self, count: usize) -> Vec<usize> {
+        let current = self.get_current_index();
+        match self.random_order.read().unwrap().as_ref() {
+            Some(order) => {
+                let start = current
+                    .and_then(|current| order.iter().position(|&i| i == current))
+                    .map_or(0, |position| position + 1);
+                order.iter().skip(start).take(count).copied().collect()
+            }
+            None => {
+                let start = current.map_or(0, |current| current + 1);
+                (start..self.len()).take(count).collect()
+            }
+        }
+    }
+
     /// Shift the item at `from` in `self.queue` to `to`.
     pub fn shift(&self, from: usize, to: usize) {
+        self.record(|| self.shift_item(from, to));
+    }
+
+    /// Shift the items at the first index of every pair in `shifts` to the second one, in order,
+    /// as one change that can be undone.
+    pub fn shift_many(&self, shifts: &[(usize, usize)]) {
+        self.record(|| {
+            for &(from, to) in shifts {
+                self.shift_item(from, to);
+            }
+        });
+    }
+
+    fn shift_item(&self, from: usize, to: usize) {
         let mut queue = self.queue.write().unwrap();
         let item = queue.remove(from);
         queue.insert(to, item);
+        self.auto_added.write().unwrap().shift(from, to);
 
         // if the currently playing track is affected by the shift, update its
         // index
@@ -295,10 +517,10 @@ impl Queue {
         if let Some(index) = *current {
             if index == from {
                 current.replace(to);
-            } else if index == to && from > index {
-                current.replace(to + 1);
-            } else if index == to && from < index {
-                current.replace(to - 1);
+            } else if from < index && index <= to {
+                current.replace(index - 1);
+            } else if to <= index && index < from {
+                current.replace(index + 1);
             }
         }
     }
@@ -318,6 +540,12 @@ impl Queue {
 
         if let Some(track) = &self.queue.read().unwrap().get(index) {
             self.spotify.load(track, true, 0);
+            if let Some(id) = track.id() {
+                let now = Utc::now();
+                self.cfg.with_state_mut(|state| {
+                    state.last_played.insert(id.clone(), now);
+                });
+            }
             let mut current = self.current_track.write().unwrap();
             current.replace(index);
             self.spotify.update_track();
@@ -366,7 +594,9 @@ impl Queue {
             }
             // 当前状态状态为停止，则播放下一首或播放第一首
             PlayerEvent::Stopped => match self.next_index() {
-                Some(_) => self.next(false),
+                Some(_) => {
+                    self.next(false);
+                }
                 None => self.play(0, false, false),
             },
             _ => (),
@@ -386,8 +616,23 @@ impl Queue {
     /// `manual`: If this is true, normal queue logic like repeat will not be
     /// used, and the next track will actually be played. This should be used
     /// when going to the next entry in the queue is the wanted behavior.
+    ///
+    /// Returns whether playback stopped because the end of the queue was reached.
     /// 播放下一首
-    pub fn next(&self, manual: bool) {
+    pub fn next(&self, manual: bool) -> bool {
+        if manual {
+            self.log_played(false);
+        }
+
+        if !manual && self.sleep_after_track() {
+            self.end_playback(true);
+            return false;
+        }
+
+        if manual || self.get_repeat() != RepeatSetting::RepeatTrack {
+            self.extend_with_autoplay();
+        }
+
         let q = self.queue.read().unwrap();
         let current = *self.current_track.read().unwrap();
         let repeat = self.cfg.state().repeat;
@@ -420,34 +665,423 @@ impl Queue {
         } else {
             // 否则停止播放
             self.spotify.stop();
+
+            // Continue with the recommendations that autoplay is still fetching.
+            if let AutoplayFetch::Pending { play } = &mut *self.autoplay_fetch.write().unwrap() {
+                *play = true;
+                return false;
+            }
+            return true;
         }
+        false
     }
 
     /// Play the previous item in the queue.
     /// 播放上一首
     pub fn previous(&self) {
-        let q = self.queue.read().unwrap();
+        let logged = self.library.play_history.len();
+        self.log_played(false);
+
+        let len = self.len();
         let current = *self.current_track.read().unwrap();
         let repeat = self.cfg.state().repeat;
 
         if let Some(index) = self.previous_index() {
             self.play(index, false, false);
-        } else if repeat == RepeatSetting::RepeatPlaylist && q.len() > 0 {
+        } else if repeat == RepeatSetting::RepeatPlaylist && len > 0 {
             if self.get_shuffle() {
-                let random_order = self.random_order.read().unwrap();
-                self.play(
-                    random_order.as_ref().map(|o| o[q.len() - 1]).unwrap_or(0),
-                    false,
-                    false,
-                );
+                let index = self
+                    .random_order
+                    .read()
+                    .unwrap()
+                    .as_ref()
+                    .map(|o| o[len - 1])
+                    .unwrap_or(0);
+                self.play(index, false, false);
             } else {
-                self.play(q.len() - 1, false, false);
+                self.play(len - 1, false, false);
             }
+        } else if let Some(playable) = self.previous_in_history(logged) {
+            // Step back past the start of the queue by prepending the previously played item.
+            self.record(|| self.insert_first(playable));
+            self.play(0, false, false);
         } else if let Some(index) = current {
             self.play(index, false, false);
         }
     }
 
+    /// Add the currently playing item to the play history. `finished` should be true if it
+    /// played until the end, and false if playback moved on before that. Only finished items
+    /// count towards the play counts. Unfinished items are only logged if they were actually
+    /// playing or paused, not if playback was stopped.
+    pub fn log_played(&self, finished: bool) {
+        let started = matches!(
+            self.spotify.get_current_status(),
+            PlayerEvent::Playing(_) | PlayerEvent::Paused(_)
+        );
+        let Some(playable) = self.get_current().filter(|_| finished || started) else {
+            return;
+        };
+
+        if let Some(id) = playable.id().filter(|_| finished) {
+            self.cfg.with_state_mut(|state| {
+                *state.play_counts.entry(id.clone()).or_default() += 1;
+            });
+        }
+
+        let duration = Duration::from_millis(playable.duration().into());
+        let listened = if finished {
+            duration
+        } else {
+            self.spotify.get_current_progress().min(duration)
+        };
+        self.library
+            .play_history
+            .record(HistoryEntry::new(playable, listened, !finished));
+    }
+
+    /// Find the item that was played before the start of the queue in the play history.
+    /// `logged` is the length of the history before the current item was added to it.
+    fn previous_in_history(&self, logged: usize) -> Option<Playable> {
+        let history = &self.library.play_history;
+        let current = self.get_current().map(|p| p.uri());
+        let mut cursor = self.history_cursor.write().unwrap();
+
+        // Continue from the entry that is playing if we already stepped back before.
+        let start = cursor
+            .filter(|&index| history.get(index).map(|e| e.playable.uri()) == current)
+            .unwrap_or(logged);
+        let queued = self
+            .queue
+            .read()
+            .unwrap()
+            .iter()
+            .map(Playable::uri)
+            .collect();
+        let (index, playable) = history.previous(start, &queued)?;
+
+        *cursor = Some(index);
+        Some(playable)
+    }
+
+    /// Insert `playable` at the start of the queue, also in the shuffle order.
+    fn insert_first(&self, playable: Playable) {
+        let mut q = self.queue.write().unwrap();
+        q.insert(0, playable);
+        self.auto_added.write().unwrap().insert(0, false);
+
+        if let Some(order) = self.random_order.write().unwrap().as_mut() {
+            for index in order.iter_mut() {
+                *index += 1;
+            }
+            order.insert(0, 0);
+        }
+
+        if let Some(current) = self.current_track.write().unwrap().as_mut() {
+            *current += 1;
+        }
+    }
+
+    /// Take a snapshot of the queue contents, which can be restored with [Queue::restore].
+    pub fn snapshot(&self) -> QueueSnapshot {
+        QueueSnapshot {
+            items: self.queue.read().unwrap().clone(),
+            random_order: self.random_order.read().unwrap().clone(),
+            auto_added: self.get_auto_added(),
+        }
+    }
+
+    /// Replace the queue contents with `snapshot`. Playback isn't interrupted: if the playing
+    /// item is part of the snapshot, it stays the current item.
+    pub fn restore(&self, snapshot: &QueueSnapshot) {
+        let playing = self.get_current();
+        let current = self.get_current_index();
+
+        {
+            let mut q = self.queue.write().unwrap();
+            q.clone_from(&snapshot.items);
+            *self.auto_added.write().unwrap() = snapshot.auto_added.clone().fit(q.len());
+
+            let restored = playing.and_then(|playing| {
+                current
+                    .filter(|&index| q.get(index).is_some_and(|p| p.uri() == playing.uri()))
+                    .or_else(|| q.iter().position(|p| p.uri() == playing.uri()))
+            });
+            *self.current_track.write().unwrap() = restored;
+        }
+
+        self.restore_random_order(&snapshot.random_order);
+    }
+
+    /// Reuse a previously stored shuffle `order` if it still fits the queue, or generate a new
+    /// one when shuffle is enabled.
+    fn restore_random_order(&self, order: &Option<Vec<usize>>) {
+        let order_matches = order
+            .as_ref()
+            .is_some_and(|order| order.len() == self.queue.read().unwrap().len());
+        if self.get_shuffle() && order_matches {
+            self.random_order.write().unwrap().clone_from(order);
+        } else if self.get_shuffle() {
+            self.generate_random_order();
+        } else {
+            *self.random_order.write().unwrap() = None;
+        }
+    }
+
+    /// Apply `mutation` to the queue and record it as one change that can be undone. Every
+    /// change to the items of the queue has to go through this. The changes of nested calls
+    /// become part of the outermost one.
+    pub fn record<T>(&self, mutation: impl FnOnce() -> T) -> T {
+        let outermost = {
+            let mut depth = self.recording.write().unwrap();
+            *depth += 1;
+            *depth == 1
+        };
+
+        let before = outermost.then(|| self.snapshot());
+        let result = mutation();
+        *self.recording.write().unwrap() -= 1;
+
+        if let Some(before) = before {
+            let after = self.snapshot();
+            if !before.has_same_items(&after) {
+                self.library.history.record(Change::Queue { before, after });
+            }
+        }
+        result
+    }
+
+    /// Save the queue under `name`, together with the current item and its progress. An existing
+    /// saved queue with the same name is overwritten.
+    pub fn save_as(&self, name: &str) {
+        let state = QueueState {
+            current_track: self.get_current_index(),
+            random_order: self.get_random_order(),
+            track_progress: self.spotify.get_current_progress(),
+            queue: self.queue.read().unwrap().clone(),
+            shuffle_strategy: self.get_shuffle_strategy(),
+            auto_added_items: self.get_auto_added(),
+        };
+        self.cfg.with_state_mut(|s| {
+            s.saved_queues.insert(name.to_string(), state.clone());
+        });
+    }
+
+    /// The names of the saved queues and the amount of items in each of them.
+    pub fn saved_queues(&self) -> Vec<(String, usize)> {
+        self.cfg
+            .state()
+            .saved_queues
+            .iter()
+            .map(|(name, state)| (name.clone(), state.queue.len()))
+            .collect()
+    }
+
+    fn saved_queue(&self, name: &str) -> Result<QueueState, String> {
+        self.cfg
+            .state()
+            .saved_queues
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("No saved queue named \"{name}\""))
+    }
+
+    /// Replace the queue with the saved queue `name` and continue playback where it was saved.
+    /// Returns the amount of items in the loaded queue.
+    pub fn load_saved(&self, name: &str) -> Result<usize, String> {
+        let saved = self.saved_queue(name)?;
+        let current = saved
+            .current_track
+            .filter(|&index| index < saved.queue.len());
+
+        self.record(|| {
+            self.queue.write().unwrap().clone_from(&saved.queue);
+            *self.auto_added.write().unwrap() =
+                saved.auto_added_items.clone().fit(saved.queue.len());
+            *self.current_track.write().unwrap() = current;
+            self.restore_random_order(&saved.random_order);
+        });
+
+        match current.and_then(|index| saved.queue.get(index)) {
+            Some(playable) => {
+                let playing = matches!(self.spotify.get_current_status(), PlayerEvent::Playing(_));
+                self.spotify
+                    .load(playable, playing, saved.track_progress.as_millis() as u32);
+                self.spotify.update_track();
+            }
+            None => self.spotify.stop(),
+        }
+
+        Ok(saved.queue.len())
+    }
+
+    /// Add the items of the saved queue `name` to the end of the queue. Returns the amount of
+    /// added items.
+    pub fn append_saved(&self, name: &str) -> Result<usize, String> {
+        let saved = self.saved_queue(name)?;
+
+        self.record(|| {
+            let mut q = self.queue.write().unwrap();
+            if let Some(order) = self.random_order.write().unwrap().as_mut() {
+                order.extend(q.len()..q.len() + saved.queue.len());
+            }
+            let mut auto_added = self.auto_added.write().unwrap();
+            for (index, _) in saved.queue.iter().enumerate() {
+                auto_added.insert(q.len() + index, saved.auto_added_items.get(index));
+            }
+            q.extend(saved.queue.iter().cloned());
+        });
+
+        Ok(saved.queue.len())
+    }
+
+    /// Delete the saved queue `name`.
+    pub fn delete_saved(&self, name: &str) -> Result<(), String> {
+        self.saved_queue(name)?;
+        self.cfg.with_state_mut(|s| {
+            s.saved_queues.remove(name);
+        });
+        Ok(())
+    }
+
+    /// Start a sleep timer that ends playback at the point given by `mode`, replacing a running
+    /// timer.
+    pub fn set_sleep_timer(&self, mode: &SleepMode) {
+        *self.sleep_timer.write().unwrap() = SleepTimer::new(mode);
+        self.restore_volume();
+    }
+
+    /// Whether playback ends when the current item finishes.
+    pub fn get_stop_after_current(&self) -> bool {
+        *self.stop_after_current.read().unwrap()
+    }
+
+    /// Set whether playback ends when the current item finishes.
+    pub fn set_stop_after_current(&self, new: bool) {
+        *self.stop_after_current.write().unwrap() = new;
+        if !new {
+            self.restore_volume();
+        }
+    }
+
+    /// A short description of when the sleep timer ends playback, for the statusbar.
+    pub fn sleep_status(&self) -> Option<String> {
+        match (*self.sleep_timer.read().unwrap())? {
+            SleepTimer::Tracks(count) if count > 1 => Some(format!("{count} tracks")),
+            _ => self
+                .sleep_remaining()
+                .map(|remaining| ms_to_hms(remaining.as_millis().try_into().unwrap_or(u32::MAX))),
+        }
+    }
+
+    /// The time that is left until the sleep timer or the stop after the current item ends
+    /// playback, if known.
+    fn sleep_remaining(&self) -> Option<Duration> {
+        let current_remaining = || {
+            let current = self.get_current()?;
+            let duration = Duration::from_millis(current.duration().into());
+            Some(duration.saturating_sub(self.spotify.get_current_progress()))
+        };
+
+        match *self.sleep_timer.read().unwrap() {
+            Some(SleepTimer::Deadline(deadline)) => {
+                return Some(deadline.saturating_duration_since(Instant::now()))
+            }
+            Some(SleepTimer::Tracks(1)) => return current_remaining(),
+            _ => {}
+        }
+
+        if self.get_stop_after_current() {
+            current_remaining()
+        } else {
+            None
+        }
+    }
+
+    /// Check whether the sleep timer ran out, and fade out the volume during its final part if
+    /// enabled. Should be called regularly during playback.
+    pub fn update_sleep_timer(&self) {
+        let timer = *self.sleep_timer.read().unwrap();
+        if let Some(SleepTimer::Deadline(deadline)) = timer {
+            if Instant::now() >= deadline {
+                *self.sleep_timer.write().unwrap() = None;
+                if matches!(self.spotify.get_current_status(), PlayerEvent::Playing(_)) {
+                    self.end_playback(false);
+                } else {
+                    self.restore_volume();
+                }
+                return;
+            }
+        }
+
+        if !self.cfg.values().sleep_fade.unwrap_or(false)
+            || !matches!(self.spotify.get_current_status(), PlayerEvent::Playing(_))
+        {
+            return;
+        }
+
+        if let Some(remaining) = self.sleep_remaining().filter(|r| *r < FADE_DURATION) {
+            let volume = *self
+                .faded_from
+                .write()
+                .unwrap()
+                .get_or_insert_with(|| self.spotify.volume());
+            let faded = faded_volume(volume, remaining);
+            if faded != self.spotify.volume() {
+                self.spotify.set_volume(faded, false);
+            }
+        }
+    }
+
+    /// Called when the current item finished playing. Returns true if the sleep timer or the stop
+    /// after the current item should end playback now.
+    fn sleep_after_track(&self) -> bool {
+        if std::mem::take(&mut *self.stop_after_current.write().unwrap()) {
+            return true;
+        }
+
+        let mut timer = self.sleep_timer.write().unwrap();
+        match *timer {
+            Some(SleepTimer::Tracks(count)) if count <= 1 => {
+                *timer = None;
+                true
+            }
+            Some(SleepTimer::Tracks(count)) => {
+                *timer = Some(SleepTimer::Tracks(count - 1));
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// End playback because of the sleep timer, using the configured [SleepAction]. If
+    /// `track_finished` is true, the next item is loaded without playing it when pausing.
+    fn end_playback(&self, track_finished: bool) {
+        let action = self.cfg.values().sleep_action.unwrap_or_default();
+        match action {
+            SleepAction::Pause if track_finished => match self.next_index() {
+                Some(index) => {
+                    if let Some(track) = self.queue.read().unwrap().get(index) {
+                        self.spotify.load(track, false, 0);
+                    }
+                    self.current_track.write().unwrap().replace(index);
+                    self.spotify.update_track();
+                }
+                None => self.spotify.stop(),
+            },
+            SleepAction::Pause => self.spotify.pause(),
+            SleepAction::Stop => self.spotify.stop(),
+        }
+        self.restore_volume();
+    }
+
+    /// Undo the volume fade of the sleep timer.
+    fn restore_volume(&self) {
+        if let Some(volume) = self.faded_from.write().unwrap().take() {
+            self.spotify.set_volume(volume, true);
+        }
+    }
+
     /// Get the current repeat behavior.
     pub fn get_repeat(&self) -> RepeatSetting {
         self.cfg.state().repeat
@@ -472,22 +1106,34 @@ impl Queue {
     /// 生成随机播放顺序
     fn generate_random_order(&self) {
         let q = self.queue.read().unwrap();
-        let mut order: Vec<usize> = Vec::with_capacity(q.len());
-        let mut random: Vec<usize> = (0..q.len()).collect();
-
-        if let Some(current) = *self.current_track.read().unwrap() {
-            order.push(current);
-            random.remove(current);
-        }
-
-        let mut rng = rand::thread_rng();
-        random.shuffle(&mut rng);
-        order.extend(random);
+        let current = *self.current_track.read().unwrap();
+        let order = {
+            let state = self.cfg.state();
+            shuffled_order(
+                &q,
+                current,
+                state.queuestate.shuffle_strategy,
+                &state.last_played,
+                &mut rand::thread_rng(),
+            )
+        };
 
         let mut random_order = self.random_order.write().unwrap();
         *random_order = Some(order);
     }
 
+    /// Get the current shuffle strategy.
+    pub fn get_shuffle_strategy(&self) -> ShuffleStrategy {
+        self.cfg.state().queuestate.shuffle_strategy
+    }
+
+    /// Set the current shuffle strategy and enable shuffle.
+    pub fn set_shuffle_strategy(&self, strategy: ShuffleStrategy) {
+        self.cfg
+            .with_state_mut(|s| s.queuestate.shuffle_strategy = strategy);
+        self.set_shuffle(true);
+    }
+
     /// Set the current shuffle behavior.
     pub fn set_shuffle(&self, new: bool) {
         self.cfg.with_state_mut(|s| s.shuffle = new);
@@ -499,16 +1145,176 @@ impl Queue {
         }
     }
 
+    /// Get whether recommendations are added automatically when the queue runs out.
+    pub fn get_autoplay(&self) -> bool {
+        self.cfg.state().autoplay
+    }
+
+    /// Set whether recommendations are added automatically when the queue runs out.
+    pub fn set_autoplay(&self, new: bool) {
+        self.cfg.with_state_mut(|s| s.autoplay = new);
+    }
+
+    /// Start fetching recommendations in the background if autoplay is enabled and the currently
+    /// playing item is the last one that would be played. They're added to the queue when they
+    /// arrive as a [QueueEvent::AutoplayTracks].
+    fn extend_with_autoplay(&self) {
+        if !self.get_autoplay()
+            || self.get_repeat() == RepeatSetting::RepeatPlaylist
+            || self.get_current_index().is_none()
+            || self.next_index().is_some()
+        {
+            return;
+        }
+
+        let (seed_tracks, seed_artists) = self.autoplay_seeds();
+        if seed_tracks.is_empty() && seed_artists.is_empty() {
+            return;
+        }
+
+        {
+            let mut fetch = self.autoplay_fetch.write().unwrap();
+            if *fetch != AutoplayFetch::Idle {
+                return;
+            }
+            *fetch = AutoplayFetch::Pending { play: false };
+        }
+
+        let api = self.spotify.api.clone();
+        let ev = self.ev.clone();
+        thread::spawn(move || {
+            let recommendations = api.recommendations(
+                (!seed_artists.is_empty())
+                    .then(|| seed_artists.iter().map(String::as_str).collect()),
+                None,
+                (!seed_tracks.is_empty()).then(|| seed_tracks.iter().map(String::as_str).collect()),
+            );
+            let tracks = recommendations
+                .map(|r| r.tracks.iter().map(Track::from).collect())
+                .unwrap_or_default();
+            ev.send(Event::Queue(QueueEvent::AutoplayTracks(tracks)));
+        });
+    }
+
+    /// The track and artist ids to seed autoplay recommendations with, taken from the last few
+    /// played items.
+    fn autoplay_seeds(&self) -> (Vec<String>, Vec<String>) {
+        let q = self.queue.read().unwrap();
+        let played: Vec<usize> = match (
+            self.random_order.read().unwrap().as_ref(),
+            self.get_current_index(),
+        ) {
+            (Some(order), Some(current)) => {
+                let position = order.iter().position(|&i| i == current).unwrap_or(0);
+                order[..=position].to_vec()
+            }
+            (None, Some(current)) => (0..=current).collect(),
+            (_, None) => Vec::new(),
+        };
+
+        let recent: Vec<&Track> = played
+            .iter()
+            .rev()
+            .filter_map(|&index| match q.get(index) {
+                Some(Playable::Track(track)) => Some(track),
+                _ => None,
+            })
+            .take(AUTOPLAY_SEED_TRACKS)
+            .collect();
+
+        autoplay_seeds(&recent)
+    }
+
+    /// Add the `tracks` that were fetched by [Queue::extend_with_autoplay] to the end of the queue,
+    /// leaving out items that are already in the queue or were played recently.
+    fn add_autoplay_tracks(&self, tracks: Vec<Track>) {
+        let fetch = std::mem::replace(
+            &mut *self.autoplay_fetch.write().unwrap(),
+            AutoplayFetch::Idle,
+        );
+        if !self.get_autoplay() || self.next_index().is_some() {
+            return;
+        }
+
+        let tracks = {
+            let queued: HashSet<String> = self
+                .queue
+                .read()
+                .unwrap()
+                .iter()
+                .filter_map(Playable::id)
+                .collect();
+            autoplay_candidates(tracks, &queued, &self.cfg.state().last_played, Utc::now())
+        };
+        debug!("autoplay adding {} tracks", tracks.len());
+        if tracks.is_empty() {
+            return;
+        }
+
+        self.record(|| {
+            let mut q = self.queue.write().unwrap();
+            if let Some(order) = self.random_order.write().unwrap().as_mut() {
+                order.extend(q.len()..q.len() + tracks.len());
+            }
+            self.auto_added
+                .write()
+                .unwrap()
+                .extend(q.len(), tracks.len(), true);
+            q.extend(tracks.into_iter().map(Playable::Track));
+        });
+
+        if let Some(index) = self.next_index() {
+            if fetch == (AutoplayFetch::Pending { play: true }) {
+                self.play(index, false, false);
+            } else {
+                let track = self.queue.read().unwrap()[index].clone();
+                self.spotify.preload(&track);
+            }
+        }
+    }
+
+    /// Whether the item at `index` in `self.queue` was added by autoplay.
+    pub fn is_auto_added(&self, index: usize) -> bool {
+        self.auto_added.read().unwrap().get(index)
+    }
+
+    /// Which items in the queue were added by autoplay.
+    pub fn get_auto_added(&self) -> AutoAdded {
+        let len = self.queue.read().unwrap().len();
+        self.auto_added.read().unwrap().clone().fit(len)
+    }
+
+    /// Remove all the items that were added by autoplay, except for the currently playing one.
+    pub fn clear_auto_added(&self) {
+        let auto_added = self
+            .auto_added
+            .read()
+            .unwrap()
+            .indexes(self.get_current_index());
+
+        if auto_added.is_empty() {
+            return;
+        }
+
+        self.record(|| {
+            for index in auto_added.into_iter().rev() {
+                self.remove_item(index);
+            }
+        });
+    }
+
     /// Handle events that are specific to the queue.
     pub fn handle_event(&self, event: QueueEvent) {
         match event {
             QueueEvent::PreloadTrackRequest => {
+                self.extend_with_autoplay();
                 if let Some(next_index) = self.next_index() {
                     let track = self.queue.read().unwrap()[next_index].clone();
                     debug!("Preloading track {} as requested by librespot", track);
                     self.spotify.preload(&track);
                 }
             }
+            QueueEvent::AutoplayTracks(tracks) => self.add_autoplay_tracks(tracks),
         }
     }
 
@@ -518,6 +1324,154 @@ impl Queue {
     }
 }
 
+/// The track and artist ids that are used to seed autoplay recommendations from the `recent`
+/// tracks, most recent first. Spotify allows at most 5 seeds in total.
+fn autoplay_seeds(recent: &[&Track]) -> (Vec<String>, Vec<String>) {
+    let seed_tracks: Vec<String> = recent.iter().filter_map(|t| t.id.clone()).collect();
+
+    let mut seen = HashSet::new();
+    let mut seed_artists: Vec<String> = recent
+        .iter()
+        .filter_map(|t| t.artist_ids.first().cloned())
+        .filter(|id| seen.insert(id.clone()))
+        .collect();
+    seed_artists.truncate(5 - seed_tracks.len().min(5));
+
+    (seed_tracks, seed_artists)
+}
+
+/// Select at most [AUTOPLAY_BATCH_SIZE] of the recommended `tracks` that are playable, not
+/// `queued` already and weren't played within [AUTOPLAY_AVOID_HOURS] before `now`.
+fn autoplay_candidates(
+    tracks: Vec<Track>,
+    queued: &HashSet<String>,
+    last_played: &HashMap<String, DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Vec<Track> {
+    let played_recently = |id: &String| {
+        last_played
+            .get(id)
+            .is_some_and(|played| now - *played < chrono::Duration::hours(AUTOPLAY_AVOID_HOURS))
+    };
+
+    tracks
+        .into_iter()
+        .filter(|track| track.is_playable != Some(false))
+        .filter(|track| {
+            track
+                .id
+                .as_ref()
+                .is_some_and(|id| !queued.contains(id) && !played_recently(id))
+        })
+        .take(AUTOPLAY_BATCH_SIZE)
+        .collect()
+}
+
+/// Generate a playback order for `tracks` with `strategy`, as indices into `tracks`. The
+/// `current` item is placed so that it is played first.
+fn shuffled_order(
+    tracks: &[Playable],
+    current: Option<usize>,
+    strategy: ShuffleStrategy,
+    last_played: &HashMap<String, DateTime<Utc>>,
+    rng: &mut impl Rng,
+) -> Vec<usize> {
+    let mut remaining: Vec<usize> = (0..tracks.len()).filter(|&i| Some(i) != current).collect();
+
+    match strategy {
+        ShuffleStrategy::Random => remaining.shuffle(rng),
+        ShuffleStrategy::Spread => {
+            remaining.shuffle(rng);
+
+            // Greedily pick the next item that doesn't share the artist or album with the
+            // previous one, falling back to the next item if there is none.
+            let key = |index: usize| match &tracks[index] {
+                Playable::Track(track) => (track.artists.first().cloned(), track.album.clone()),
+                Playable::Episode(_) => (None, None),
+            };
+            let mut previous = current.map(key);
+            let mut spread = Vec::with_capacity(remaining.len());
+            while !remaining.is_empty() {
+                let position = remaining
+                    .iter()
+                    .position(|&index| {
+                        let (artist, album) = key(index);
+                        previous.as_ref().map_or(true, |(prev_artist, prev_album)| {
+                            (artist.is_none() || artist != *prev_artist)
+                                && (album.is_none() || album != *prev_album)
+                        })
+                    })
+                    .unwrap_or(0);
+                let index = remaining.remove(position);
+                previous = Some(key(index));
+                spread.push(index);
+            }
+            remaining = spread;
+        }
+        ShuffleStrategy::Album => {
+            let album = |index: usize| match &tracks[index] {
+                Playable::Track(track) => track.album_id.clone().or(track.album.clone()),
+                Playable::Episode(_) => None,
+            };
+
+            // Group by album in queue order, items without album form their own group
+            let mut groups: Vec<(Option<String>, Vec<usize>)> = Vec::new();
+            for index in 0..tracks.len() {
+                let key = album(index);
+                match groups
+                    .iter_mut()
+                    .find(|(group, _)| key.is_some() && *group == key)
+                {
+                    Some((_, items)) => items.push(index),
+                    None => groups.push((key, vec![index])),
+                }
+            }
+            groups.shuffle(rng);
+
+            // The album of the current item is played first, starting at the current item. Its
+            // items before the current one are played at the end of the album.
+            if let Some(current) = current {
+                if let Some(position) = groups
+                    .iter()
+                    .position(|(_, items)| items.contains(&current))
+                {
+                    let (key, mut items) = groups.remove(position);
+                    let start = items
+                        .iter()
+                        .position(|&index| index == current)
+                        .unwrap_or(0);
+                    items.rotate_left(start);
+                    groups.insert(0, (key, items));
+                }
+            }
+
+            return groups.into_iter().flat_map(|(_, items)| items).collect();
+        }
+        ShuffleStrategy::Weighted => {
+            // Weighted random sampling: the weight grows with the time since the last playback,
+            // capped at 30 days.
+            const MAX_AGE_HOURS: f64 = 24.0 * 30.0;
+            let now = Utc::now();
+            let weight = |index: usize| {
+                let age = tracks[index]
+                    .id()
+                    .and_then(|id| last_played.get(&id).copied())
+                    .map(|played| (now - played).num_hours() as f64)
+                    .unwrap_or(MAX_AGE_HOURS);
+                age.clamp(0.0, MAX_AGE_HOURS) + 1.0
+            };
+            let mut keyed: Vec<(f64, usize)> = remaining
+                .iter()
+                .map(|&index| (rng.gen::<f64>().powf(1.0 / weight(index)), index))
+                .collect();
+            keyed.sort_by(|(a, _), (b, _)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+            remaining = keyed.into_iter().map(|(_, index)| index).collect();
+        }
+    }
+
+    current.into_iter().chain(remaining).collect()
+}
+
 /// Send a notification using the desktops default notification method.
 ///
 /// `summary_txt`: A short title for the notification.
@@ -556,3 +1510,182 @@ pub fn send_notification(summary_txt: &str, body_txt: &str, cover_url: Option<St
         Err(e) => log::error!("Failed to send notification cover: {}", e),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: usize, artist: &str, album: &str) -> Playable {
+        let id = id.to_string();
+        Playable::Track(Track::test(&id, &id, artist, Some(album)))
+    }
+
+    fn recommendation(id: &str, artist_id: &str) -> Track {
+        match track(0, "artist", "album") {
+            Playable::Track(track) => Track {
+                id: Some(id.into()),
+                artist_ids: vec![artist_id.into()],
+                ..track
+            },
+            Playable::Episode(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn autoplay_seeds_deduplicate_artists() {
+        let a = recommendation("1", "x");
+        let b = recommendation("2", "y");
+        let c = recommendation("3", "x");
+        let (tracks, artists) = autoplay_seeds(&[&a, &b, &c]);
+
+        assert_eq!(tracks, ["1", "2", "3"]);
+        assert_eq!(artists, ["x", "y"]);
+    }
+
+    #[test]
+    fn autoplay_seeds_are_limited() {
+        let recent: Vec<Track> = (0..6)
+            .map(|i| recommendation(&i.to_string(), &i.to_string()))
+            .collect();
+        let (tracks, artists) = autoplay_seeds(&recent.iter().collect::<Vec<_>>());
+
+        assert_eq!(tracks.len(), 6);
+        assert!(artists.is_empty());
+    }
+
+    #[test]
+    fn autoplay_candidates_skip_queued_and_recent_tracks() {
+        let now = Utc::now();
+        let queued = HashSet::from(["queued".to_string()]);
+        let last_played = HashMap::from([
+            ("recent".to_string(), now - chrono::Duration::hours(1)),
+            ("old".to_string(), now - chrono::Duration::days(3)),
+        ]);
+        let unplayable = Track {
+            is_playable: Some(false),
+            ..recommendation("unplayable", "a")
+        };
+        let tracks = vec![
+            recommendation("queued", "a"),
+            recommendation("recent", "a"),
+            recommendation("old", "a"),
+            unplayable,
+            recommendation("new", "a"),
+        ];
+
+        let ids: Vec<Option<String>> = autoplay_candidates(tracks, &queued, &last_played, now)
+            .into_iter()
+            .map(|track| track.id)
+            .collect();
+        assert_eq!(ids, [Some("old".into()), Some("new".into())]);
+    }
+
+    #[test]
+    fn autoplay_candidates_are_limited() {
+        let tracks = (0..AUTOPLAY_BATCH_SIZE * 2)
+            .map(|i| recommendation(&i.to_string(), "a"))
+            .collect();
+        let candidates = autoplay_candidates(tracks, &HashSet::new(), &HashMap::new(), Utc::now());
+        assert_eq!(candidates.len(), AUTOPLAY_BATCH_SIZE);
+    }
+
+    fn order(
+        tracks: &[Playable],
+        current: Option<usize>,
+        strategy: ShuffleStrategy,
+        last_played: &HashMap<String, DateTime<Utc>>,
+    ) -> Vec<usize> {
+        let order = shuffled_order(
+            tracks,
+            current,
+            strategy,
+            last_played,
+            &mut StdRng::seed_from_u64(7),
+        );
+
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..tracks.len()).collect::<Vec<_>>());
+        order
+    }
+
+    #[test]
+    fn random_plays_current_first() {
+        let tracks: Vec<Playable> = (0..10).map(|i| track(i, "artist", "album")).collect();
+        let order = order(&tracks, Some(4), ShuffleStrategy::Random, &HashMap::new());
+        assert_eq!(order[0], 4);
+    }
+
+    #[test]
+    fn spread_separates_artists() {
+        let tracks: Vec<Playable> = (0..8)
+            .map(|i| track(i, ["a", "b"][i / 4], &i.to_string()))
+            .collect();
+        let order = order(&tracks, Some(0), ShuffleStrategy::Spread, &HashMap::new());
+
+        assert_eq!(order[0], 0);
+        for pair in order.windows(2) {
+            assert_ne!(pair[0] / 4, pair[1] / 4, "{order:?}");
+        }
+    }
+
+    #[test]
+    fn album_starts_at_current_item() {
+        let tracks: Vec<Playable> = (0..9)
+            .map(|i| track(i, "artist", ["x", "y", "z"][i / 3]))
+            .collect();
+        let order = order(&tracks, Some(4), ShuffleStrategy::Album, &HashMap::new());
+
+        assert_eq!(order[..3], [4, 5, 3]);
+        for album in order.chunks(3) {
+            assert!(album.iter().all(|&i| i / 3 == album[0] / 3), "{order:?}");
+        }
+    }
+
+    #[test]
+    fn album_keeps_album_order_without_current_item() {
+        let tracks: Vec<Playable> = (0..6)
+            .map(|i| track(i, "artist", ["x", "y"][i / 3]))
+            .collect();
+        let order = order(&tracks, None, ShuffleStrategy::Album, &HashMap::new());
+
+        assert!(order == [0, 1, 2, 3, 4, 5] || order == [3, 4, 5, 0, 1, 2]);
+    }
+
+    #[test]
+    fn weighted_plays_recent_items_last() {
+        let tracks: Vec<Playable> = (0..20).map(|i| track(i, "artist", "album")).collect();
+        let last_played = HashMap::from([("1".to_string(), Utc::now())]);
+        let order = order(&tracks, Some(0), ShuffleStrategy::Weighted, &last_played);
+
+        assert_eq!(order[0], 0);
+        assert!(
+            order.iter().position(|&i| i == 1).unwrap() > 10,
+            "{order:?}"
+        );
+    }
+
+    #[test]
+    fn auto_added_marks_follow_their_items() {
+        let mut auto_added = AutoAdded::default();
+        auto_added.extend(0, 2, false);
+        auto_added.extend(2, 2, true);
+        auto_added.shift(3, 0);
+
+        assert_eq!(auto_added.indexes(None), [0, 3]);
+        assert_eq!(auto_added.indexes(Some(0)), [3]);
+    }
+
+    #[test]
+    fn clearing_autoplay_keeps_items_added_again_by_hand() {
+        let mut auto_added = AutoAdded::default();
+        auto_added.extend(0, 1, false);
+        auto_added.extend(1, 1, true);
+
+        auto_added.remove(1);
+        auto_added.extend(1, 1, false);
+
+        assert!(!auto_added.get(1));
+        assert!(auto_added.indexes(None).is_empty());
+    }
+}
@@ -0,0 +1,390 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+
+use log::{debug, error, info, warn};
+use rhai::{Array, CallFnOptions, Dynamic, Engine, FuncArgs, Scope, AST};
+
+use crate::command::{parse, CommandParseError};
+use crate::config::user_configuration_directory;
+use crate::hooks::HookEvent;
+use crate::library::Library;
+use crate::model::playable::Playable;
+use crate::queue::Queue;
+use crate::spotify::{PlayerEvent, Spotify, VOLUME_PERCENT};
+
+/// The directory inside the configuration directory that scripts are loaded from.
+const SCRIPTS_DIRECTORY: &str = "scripts";
+
+/// The names of all commands that were registered by scripts. This is global so that the command
+/// parser can recognize them.
+static COMMANDS: RwLock<BTreeSet<String>> = RwLock::new(BTreeSet::new());
+
+/// Whether `name` is a command that was registered by a script.
+pub fn is_command(name: &str) -> bool {
+    COMMANDS.read().unwrap().contains(name)
+}
+
+/// The names of all commands that were registered by scripts, sorted.
+pub fn command_names() -> Vec<String> {
+    COMMANDS.read().unwrap().iter().cloned().collect()
+}
+
+/// A script function that is called for a command or an event.
+#[derive(Clone, Debug)]
+struct Handler {
+    /// Index of the script in [Scripts::scripts].
+    script: usize,
+    function: String,
+}
+
+/// Registrations that scripts make while they are loaded.
+#[derive(Default)]
+struct Registrations {
+    commands: Vec<(String, String)>,
+    handlers: Vec<(String, String)>,
+}
+
+/// Wrap `items` in a script array.
+fn to_array(items: impl IntoIterator<Item = Playable>) -> Array {
+    items.into_iter().map(Dynamic::from).collect()
+}
+
+/// Register the `Playable` type and its properties with `engine`.
+fn register_playable(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<Playable>("Playable")
+        .register_get("uri", |p: &mut Playable| p.uri())
+        .register_get("id", |p: &mut Playable| p.id().unwrap_or_default())
+        .register_get("duration_ms", |p: &mut Playable| p.duration() as i64)
+        .register_get("kind", |p: &mut Playable| match p {
+            Playable::Track(_) => "track".to_string(),
+            Playable::Episode(_) => "episode".to_string(),
+        })
+        .register_get("title", |p: &mut Playable| match p {
+            Playable::Track(track) => track.title.clone(),
+            Playable::Episode(episode) => episode.name.clone(),
+        })
+        .register_get("artists", |p: &mut Playable| -> Array {
+            match p {
+                Playable::Track(track) => {
+                    track.artists.iter().cloned().map(Dynamic::from).collect()
+                }
+                Playable::Episode(_) => Array::new(),
+            }
+        })
+        .register_get("album", |p: &mut Playable| match p {
+            Playable::Track(track) => track.album.clone().unwrap_or_default(),
+            Playable::Episode(_) => String::new(),
+        })
+        .register_fn("to_string", |p: &mut Playable| p.to_string());
+}
+
+/// Custom commands and event handlers written in [Rhai](https://rhai.rs). Every `.rhai` file in
+/// the `scripts` configuration directory is loaded on startup. At the top level, scripts register
+/// their functions with `register_command(name, function)` and `on(event, function)`.
+pub struct Scripts {
+    engine: Engine,
+    scripts: Vec<AST>,
+    commands: HashMap<String, Handler>,
+    handlers: HashMap<HookEvent, Vec<Handler>>,
+    /// What the script that is being loaded registers.
+    registrations: Rc<RefCell<Registrations>>,
+    /// Commands that scripts asked to run with `run(command)`.
+    pending: Rc<RefCell<Vec<String>>>,
+}
+
+impl Scripts {
+    pub fn new(queue: Arc<Queue>, library: Arc<Library>, spotify: Spotify) -> Self {
+        let mut result = Self::bare();
+        result.register_player(queue, library, spotify);
+        if let Some(directory) = user_configuration_directory() {
+            result.load_directory(&directory.join(SCRIPTS_DIRECTORY));
+        }
+        result
+    }
+
+    /// Scripts with only the functions to register commands and handlers and to run commands.
+    fn bare() -> Self {
+        let mut engine = Engine::new();
+        let registrations = Rc::new(RefCell::new(Registrations::default()));
+        let pending = Rc::new(RefCell::new(Vec::new()));
+
+        // Scripts must never write to the terminal, as it's in use by the user interface.
+        engine.on_print(|text| info!("script: {text}"));
+        engine.on_debug(|text, source, position| {
+            debug!("script {}:{position}: {text}", source.unwrap_or_default())
+        });
+
+        register_playable(&mut engine);
+
+        {
+            let registrations = registrations.clone();
+            engine.register_fn("register_command", move |name: &str, function: &str| {
+                let mut registrations = registrations.borrow_mut();
+                registrations.commands.push((name.into(), function.into()));
+            });
+        }
+        {
+            let registrations = registrations.clone();
+            engine.register_fn("on", move |event: &str, function: &str| {
+                let mut registrations = registrations.borrow_mut();
+                registrations.handlers.push((event.into(), function.into()));
+            });
+        }
+        {
+            let pending = pending.clone();
+            engine.register_fn("run", move |command: &str| {
+                pending.borrow_mut().push(command.to_string());
+            });
+        }
+
+        Self {
+            engine,
+            scripts: Vec::new(),
+            commands: HashMap::new(),
+            handlers: HashMap::new(),
+            registrations,
+            pending,
+        }
+    }
+
+    /// Register the functions that give scripts access to the queue, the library and playback.
+    fn register_player(&mut self, queue: Arc<Queue>, library: Arc<Library>, spotify: Spotify) {
+        let engine = &mut self.engine;
+
+        {
+            let queue = queue.clone();
+            engine.register_fn("current", move || {
+                queue
+                    .get_current()
+                    .map(Dynamic::from)
+                    .unwrap_or(Dynamic::UNIT)
+            });
+        }
+        {
+            let queue = queue.clone();
+            engine.register_fn("queue", move || {
+                to_array(queue.queue.read().unwrap().iter().cloned())
+            });
+        }
+        {
+            let queue = queue.clone();
+            engine.register_fn("enqueue", move |playable: Playable| queue.append(playable));
+        }
+        {
+            let queue = queue.clone();
+            engine.register_fn("play_next", move |playable: Playable| {
+                queue.insert_after_current(playable)
+            });
+        }
+
+        {
+            let library = library.clone();
+            engine.register_fn("saved_tracks", move || {
+                let tracks = library.tracks.read().unwrap();
+                to_array(tracks.iter().map(|track| Playable::Track(track.clone())))
+            });
+        }
+        {
+            let library = library.clone();
+            engine.register_fn("is_saved", move |playable: Playable| {
+                library.is_saved_track(&playable)
+            });
+        }
+
+        {
+            let spotify = spotify.clone();
+            engine.register_fn("artist_top_tracks", move |playable: Playable| {
+                let artist_id = match &playable {
+                    Playable::Track(track) => track.artist_ids.first().cloned(),
+                    Playable::Episode(_) => None,
+                };
+                let tracks = artist_id
+                    .and_then(|id| spotify.api.artist_top_tracks(&id).ok())
+                    .unwrap_or_default();
+                to_array(tracks.into_iter().map(Playable::Track))
+            });
+        }
+        {
+            let spotify = spotify.clone();
+            engine.register_fn("state", move || match spotify.get_current_status() {
+                PlayerEvent::Playing(_) => "playing",
+                PlayerEvent::Paused(_) => "paused",
+                PlayerEvent::Stopped | PlayerEvent::FinishedTrack => "stopped",
+            });
+        }
+        {
+            let spotify = spotify.clone();
+            engine.register_fn("position_ms", move || {
+                spotify.get_current_progress().as_millis() as i64
+            });
+        }
+        engine.register_fn("volume", move || (spotify.volume() / VOLUME_PERCENT) as i64);
+    }
+
+    /// Load all scripts in `directory`, in alphabetical order.
+    fn load_directory(&mut self, directory: &Path) {
+        let mut paths: Vec<_> = match fs::read_dir(directory) {
+            Ok(entries) => entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|e| e == "rhai"))
+                .collect(),
+            Err(_) => return,
+        };
+        paths.sort();
+
+        for path in paths {
+            match self.load(&path) {
+                Ok(()) => info!("loaded script {}", path.display()),
+                Err(e) => error!("could not load script {}: {e}", path.display()),
+            }
+        }
+    }
+
+    /// Compile and run the script at `path`.
+    fn load(&mut self, path: &Path) -> Result<(), String> {
+        let ast = self
+            .engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| e.to_string())?;
+        self.add(ast)
+    }
+
+    /// Run the compiled script `ast`, and keep the commands and handlers it registers.
+    fn add(&mut self, ast: AST) -> Result<(), String> {
+        self.engine.run_ast(&ast).map_err(|e| e.to_string())?;
+
+        let script = self.scripts.len();
+        self.scripts.push(ast);
+
+        let Registrations { commands, handlers } = self.registrations.take();
+        for (name, function) in commands {
+            if !matches!(parse(&name), Err(CommandParseError::NoSuchCommand { .. })) {
+                warn!("script command \"{name}\" conflicts with a built-in command");
+                continue;
+            }
+            COMMANDS.write().unwrap().insert(name.clone());
+            self.commands.insert(name, Handler { script, function });
+        }
+        for (event, function) in handlers {
+            match event.parse::<HookEvent>() {
+                Ok(event) => self
+                    .handlers
+                    .entry(event)
+                    .or_default()
+                    .push(Handler { script, function }),
+                Err(_) => warn!("script registered a handler for unknown event \"{event}\""),
+            }
+        }
+        Ok(())
+    }
+
+    fn call(&self, handler: &Handler, args: impl FuncArgs) -> Result<Dynamic, String> {
+        let options = CallFnOptions::new().eval_ast(false);
+        self.engine
+            .call_fn_with_options(
+                options,
+                &mut Scope::new(),
+                &self.scripts[handler.script],
+                &handler.function,
+                args,
+            )
+            .map_err(|e| format!("{}: {e}", handler.function))
+    }
+
+    /// Run the script command `name`. Returns the text that the command returned, if any.
+    pub fn run_command(&self, name: &str, args: &[String]) -> Result<Option<String>, String> {
+        let handler = self
+            .commands
+            .get(name)
+            .ok_or_else(|| format!("No such command \"{name}\""))?;
+        let args: Array = args.iter().cloned().map(Dynamic::from).collect();
+        let result = self.call(handler, (args,))?;
+        Ok(result.into_string().ok().filter(|s| !s.is_empty()))
+    }
+
+    /// Call the handlers that scripts registered for `event`.
+    pub fn handle_event(&self, event: HookEvent, current: Option<Playable>) {
+        let current = current.map(Dynamic::from).unwrap_or(Dynamic::UNIT);
+        for handler in self.handlers.get(&event).into_iter().flatten() {
+            if let Err(e) = self.call(handler, (current.clone(),)) {
+                error!("script handler for {event} failed: {e}");
+            }
+        }
+    }
+
+    /// Take the commands that scripts asked to run.
+    pub fn take_pending(&self) -> Vec<String> {
+        self.pending.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scripts(source: &str) -> Scripts {
+        let mut scripts = Scripts::bare();
+        let ast = scripts.engine.compile(source).unwrap();
+        scripts.add(ast).unwrap();
+        scripts
+    }
+
+    #[test]
+    fn runs_commands_issued_by_script_commands() {
+        let scripts = scripts(
+            r#"
+            fn greet(args) {
+                run("play");
+                run("volume " + args[0]);
+                "hello " + args[0]
+            }
+            register_command("test_greet", "greet");
+            "#,
+        );
+
+        let result = scripts.run_command("test_greet", &["50".into()]);
+        assert_eq!(result, Ok(Some("hello 50".into())));
+        assert_eq!(scripts.take_pending(), ["play", "volume 50"]);
+        assert!(scripts.take_pending().is_empty());
+        assert!(is_command("test_greet"));
+    }
+
+    #[test]
+    fn runs_commands_issued_by_event_handlers() {
+        let scripts = scripts(
+            r#"
+            fn changed(current) {
+                if current == () {
+                    run("next");
+                }
+            }
+            on("track_change", "changed");
+            on("no_such_event", "changed");
+            "#,
+        );
+
+        scripts.handle_event(HookEvent::Play, None);
+        assert!(scripts.take_pending().is_empty());
+        scripts.handle_event(HookEvent::TrackChange, None);
+        assert_eq!(scripts.take_pending(), ["next"]);
+    }
+
+    #[test]
+    fn ignores_commands_that_shadow_built_in_ones() {
+        let scripts = scripts(
+            r#"
+            fn stop() { run("play"); }
+            register_command("stop", "stop");
+            "#,
+        );
+
+        assert!(scripts.run_command("stop", &[]).is_err());
+        assert!(scripts.take_pending().is_empty());
+    }
+}
@@ -0,0 +1,548 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError, Sender};
+use log::{debug, error, info, warn};
+use reqwest::blocking::{Client, Response};
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::model::playable::Playable;
+use crate::model::track::Track;
+use crate::spotify::PlayerEvent;
+
+/// Submissions that failed because the service wasn't reachable are stored in this file until
+/// they can be retried.
+const PENDING_SCROBBLES_FILE: &str = "scrobbles.json";
+
+/// How long to wait before retrying to submit pending listens.
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The maximum amount of listens that are submitted in a single request.
+const MAX_BATCH_SIZE: usize = 50;
+
+/// Tracks that are shorter than this are never scrobbled.
+const MIN_TRACK_DURATION: Duration = Duration::from_secs(30);
+
+/// A track is scrobbled once it was listened to for this long, even if that's less than half of it.
+const MAX_REQUIRED_LISTEN: Duration = Duration::from_secs(240);
+
+const LISTENBRAINZ_URL: &str = "https://api.listenbrainz.org";
+const LASTFM_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+
+/// The API that the scrobbling endpoint implements.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrobbleService {
+    #[default]
+    ListenBrainz,
+    LastFm,
+}
+
+/// The `[scrobbling]` section of the configuration file.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ScrobblingConfig {
+    pub service: Option<ScrobbleService>,
+    /// The base URL of the API, to use a compatible service instead of the official one.
+    pub url: Option<String>,
+    /// The ListenBrainz user token or the Last.fm session key.
+    pub token: Option<String>,
+    /// The Last.fm API key.
+    pub api_key: Option<String>,
+    /// The Last.fm API secret, used to sign requests.
+    pub api_secret: Option<String>,
+}
+
+/// A single listen, or the currently playing track when `listened_at` is None.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Listen {
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    pub duration_ms: u32,
+    pub url: Option<String>,
+    /// When the track started playing, as a unix timestamp.
+    pub listened_at: Option<i64>,
+}
+
+impl Listen {
+    fn new(track: &Track, listened_at: Option<i64>) -> Self {
+        Self {
+            artist: track.artists.join(", "),
+            title: track.title.clone(),
+            album: track.album.clone(),
+            duration_ms: track.duration,
+            url: (!track.url.is_empty()).then(|| track.url.clone()),
+            listened_at,
+        }
+    }
+}
+
+/// Whether a track of `duration` that was listened to for `listened` should be scrobbled: it
+/// has to be longer than 30 seconds, and must have played for at least half its duration or for
+/// 4 minutes.
+pub fn should_scrobble(duration: Duration, listened: Duration) -> bool {
+    duration > MIN_TRACK_DURATION && (listened >= duration / 2 || listened >= MAX_REQUIRED_LISTEN)
+}
+
+/// Why a submission failed.
+#[derive(Debug)]
+enum SubmitError {
+    /// The service couldn't be reached or had a temporary problem; try again later.
+    Retry(String),
+    /// The service refused the submission; it won't be accepted on a retry either.
+    Rejected(String),
+}
+
+fn check_response(response: reqwest::Result<Response>) -> Result<(), SubmitError> {
+    let response = response.map_err(|e| SubmitError::Retry(e.to_string()))?;
+    let status = response.status();
+    if status.is_success() {
+        Ok(())
+    } else if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+        let body = response.text().unwrap_or_default();
+        Err(SubmitError::Rejected(format!("{status}: {body}")))
+    } else {
+        Err(SubmitError::Retry(status.to_string()))
+    }
+}
+
+/// A scrobbling API.
+trait ScrobbleClient: Send {
+    fn now_playing(&self, listen: &Listen) -> Result<(), SubmitError>;
+    fn submit(&self, listens: &[Listen]) -> Result<(), SubmitError>;
+}
+
+/// Client for the ListenBrainz API, which is also implemented by several self-hosted services.
+struct ListenBrainz {
+    client: Client,
+    url: String,
+    token: String,
+}
+
+impl ListenBrainz {
+    fn payload(listen: &Listen) -> serde_json::Value {
+        let mut payload = json!({
+            "track_metadata": {
+                "artist_name": listen.artist,
+                "track_name": listen.title,
+                "additional_info": {
+                    "duration_ms": listen.duration_ms,
+                    "media_player": "ncspot",
+                    "submission_client": "ncspot",
+                    "submission_client_version": env!("VERSION"),
+                },
+            },
+        });
+        if let Some(album) = &listen.album {
+            payload["track_metadata"]["release_name"] = json!(album);
+        }
+        if let Some(url) = &listen.url {
+            payload["track_metadata"]["additional_info"]["origin_url"] = json!(url);
+        }
+        if let Some(listened_at) = listen.listened_at {
+            payload["listened_at"] = json!(listened_at);
+        }
+        payload
+    }
+
+    fn post(&self, listen_type: &str, listens: &[Listen]) -> Result<(), SubmitError> {
+        let body = json!({
+            "listen_type": listen_type,
+            "payload": listens.iter().map(Self::payload).collect::<Vec<_>>(),
+        });
+        check_response(
+            self.client
+                .post(format!(
+                    "{}/1/submit-listens",
+                    self.url.trim_end_matches('/')
+                ))
+                .header("Authorization", format!("Token {}", self.token))
+                .json(&body)
+                .send(),
+        )
+    }
+}
+
+impl ScrobbleClient for ListenBrainz {
+    fn now_playing(&self, listen: &Listen) -> Result<(), SubmitError> {
+        self.post("playing_now", std::slice::from_ref(listen))
+    }
+
+    fn submit(&self, listens: &[Listen]) -> Result<(), SubmitError> {
+        match listens {
+            [listen] => self.post("single", std::slice::from_ref(listen)),
+            _ => self.post("import", listens),
+        }
+    }
+}
+
+/// Client for the Last.fm scrobbling API, which is also implemented by compatible services.
+struct LastFm {
+    client: Client,
+    url: String,
+    api_key: String,
+    api_secret: String,
+    session_key: String,
+}
+
+impl LastFm {
+    /// Sign and send a request for `method` with the given parameters.
+    fn call(&self, method: &str, mut params: BTreeMap<String, String>) -> Result<(), SubmitError> {
+        params.insert("method".into(), method.into());
+        params.insert("api_key".into(), self.api_key.clone());
+        params.insert("sk".into(), self.session_key.clone());
+
+        let mut signature = String::new();
+        for (key, value) in &params {
+            signature.push_str(key);
+            signature.push_str(value);
+        }
+        signature.push_str(&self.api_secret);
+        params.insert(
+            "api_sig".into(),
+            format!("{:x}", md5::compute(signature.as_bytes())),
+        );
+        params.insert("format".into(), "json".into());
+
+        check_response(self.client.post(&self.url).form(&params).send())
+    }
+
+    fn params(listen: &Listen, suffix: &str) -> BTreeMap<String, String> {
+        let mut params = BTreeMap::new();
+        params.insert(format!("artist{suffix}"), listen.artist.clone());
+        params.insert(format!("track{suffix}"), listen.title.clone());
+        params.insert(
+            format!("duration{suffix}"),
+            (listen.duration_ms / 1000).to_string(),
+        );
+        if let Some(album) = &listen.album {
+            params.insert(format!("album{suffix}"), album.clone());
+        }
+        if let Some(listened_at) = listen.listened_at {
+            params.insert(format!("timestamp{suffix}"), listened_at.to_string());
+        }
+        params
+    }
+}
+
+impl ScrobbleClient for LastFm {
+    fn now_playing(&self, listen: &Listen) -> Result<(), SubmitError> {
+        self.call("track.updateNowPlaying", Self::params(listen, ""))
+    }
+
+    fn submit(&self, listens: &[Listen]) -> Result<(), SubmitError> {
+        let params = listens
+            .iter()
+            .enumerate()
+            .flat_map(|(index, listen)| Self::params(listen, &format!("[{index}]")))
+            .collect();
+        self.call("track.scrobble", params)
+    }
+}
+
+/// Create the client for the configured service. Returns an error if required settings are
+/// missing.
+fn create_client(config: &ScrobblingConfig) -> Result<Box<dyn ScrobbleClient>, String> {
+    let client = Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let token = config.token.clone().ok_or("missing token")?;
+
+    Ok(match config.service.unwrap_or_default() {
+        ScrobbleService::ListenBrainz => Box::new(ListenBrainz {
+            client,
+            url: config.url.clone().unwrap_or(LISTENBRAINZ_URL.into()),
+            token,
+        }),
+        ScrobbleService::LastFm => Box::new(LastFm {
+            client,
+            url: config.url.clone().unwrap_or(LASTFM_URL.into()),
+            api_key: config.api_key.clone().ok_or("missing api_key")?,
+            api_secret: config.api_secret.clone().ok_or("missing api_secret")?,
+            session_key: token,
+        }),
+    })
+}
+
+enum Message {
+    NowPlaying(Listen),
+    Listen(Listen),
+}
+
+/// Submits listens on a background thread. Listens that can't be submitted are kept in a file
+/// and retried regularly.
+struct Worker {
+    client: Box<dyn ScrobbleClient>,
+    pending: Vec<Listen>,
+    pending_path: PathBuf,
+}
+
+impl Worker {
+    fn run(mut self, rx: Receiver<Message>) {
+        self.flush();
+        loop {
+            match rx.recv_timeout(RETRY_INTERVAL) {
+                Ok(Message::NowPlaying(listen)) => {
+                    if let Err(e) = self.client.now_playing(&listen) {
+                        debug!("could not submit now playing: {e:?}");
+                    }
+                }
+                Ok(Message::Listen(listen)) => {
+                    self.pending.push(listen);
+                    self.flush();
+                }
+                Err(RecvTimeoutError::Timeout) => self.flush(),
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    }
+
+    /// Submit all pending listens, keeping the ones that should be retried later.
+    fn flush(&mut self) {
+        while !self.pending.is_empty() {
+            let count = self.pending.len().min(MAX_BATCH_SIZE);
+            match self.client.submit(&self.pending[..count]) {
+                Ok(()) => {
+                    info!("scrobbled {count} listens");
+                    self.pending.drain(..count);
+                }
+                Err(SubmitError::Rejected(e)) => {
+                    error!("scrobbles were rejected, dropping {count} listens: {e}");
+                    self.pending.drain(..count);
+                }
+                Err(SubmitError::Retry(e)) => {
+                    warn!("could not scrobble, will retry later: {e}");
+                    break;
+                }
+            }
+        }
+        self.save_pending();
+    }
+
+    fn save_pending(&self) {
+        let result = if self.pending.is_empty() {
+            fs::remove_file(&self.pending_path).or_else(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => Ok(()),
+                _ => Err(e.to_string()),
+            })
+        } else {
+            serde_json::to_string(&self.pending)
+                .map_err(|e| e.to_string())
+                .and_then(|json| fs::write(&self.pending_path, json).map_err(|e| e.to_string()))
+        };
+        if let Err(e) = result {
+            error!("could not save pending scrobbles: {e}");
+        }
+    }
+}
+
+/// The track that is being listened to.
+struct Playing {
+    track: Track,
+    started_at: i64,
+    /// The time the track was playing, excluding the current stretch.
+    listened: Duration,
+    /// When playback last started or resumed, if it's playing.
+    resumed_at: Option<Instant>,
+}
+
+impl Playing {
+    fn listened(&self) -> Duration {
+        self.listened + self.resumed_at.map(|t| t.elapsed()).unwrap_or_default()
+    }
+}
+
+/// Scrobbles the played tracks according to the [PlayerEvent]s.
+pub struct Scrobbler {
+    tx: Sender<Message>,
+    playing: Option<Playing>,
+}
+
+impl Scrobbler {
+    /// Start a scrobbler for the given configuration.
+    pub fn new(config: &ScrobblingConfig) -> Result<Self, String> {
+        let client = create_client(config)?;
+        let pending_path = crate::config::cache_path(PENDING_SCROBBLES_FILE);
+        let pending = fs::read_to_string(&pending_path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+
+        let (tx, rx) = unbounded();
+        let worker = Worker {
+            client,
+            pending,
+            pending_path,
+        };
+        thread::spawn(move || worker.run(rx));
+
+        Ok(Self { tx, playing: None })
+    }
+
+    /// Handle a player `event`. `current` is the item in the queue that the event applies to.
+    pub fn handle(&mut self, event: &PlayerEvent, current: Option<Playable>) {
+        match event {
+            PlayerEvent::Playing(_) => {
+                let track = match current {
+                    Some(Playable::Track(track)) => track,
+                    _ => return self.finish(),
+                };
+
+                match self.playing.as_mut() {
+                    Some(playing) if playing.track.uri == track.uri => {
+                        playing.resumed_at.get_or_insert_with(Instant::now);
+                    }
+                    _ => {
+                        self.finish();
+                        self.send(Message::NowPlaying(Listen::new(&track, None)));
+                        self.playing = Some(Playing {
+                            track,
+                            started_at: Utc::now().timestamp(),
+                            listened: Duration::ZERO,
+                            resumed_at: Some(Instant::now()),
+                        });
+                    }
+                }
+            }
+            PlayerEvent::Paused(_) => {
+                if let Some(playing) = self.playing.as_mut() {
+                    playing.listened = playing.listened();
+                    playing.resumed_at = None;
+                }
+            }
+            PlayerEvent::Stopped | PlayerEvent::FinishedTrack => self.finish(),
+        }
+    }
+
+    /// Stop tracking the current track and scrobble it if it was listened to long enough.
+    fn finish(&mut self) {
+        if let Some(playing) = self.playing.take() {
+            let duration = Duration::from_millis(playing.track.duration.into());
+            if should_scrobble(duration, playing.listened()) {
+                let listen = Listen::new(&playing.track, Some(playing.started_at));
+                self.send(Message::Listen(listen));
+            }
+        }
+    }
+
+    fn send(&self, message: Message) {
+        if self.tx.send(message).is_err() {
+            error!("scrobbler thread is not running");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn follows_the_scrobble_rules() {
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+
+        assert!(should_scrobble(minutes(3), Duration::from_secs(90)));
+        assert!(!should_scrobble(minutes(3), Duration::from_secs(89)));
+        assert!(should_scrobble(minutes(20), minutes(4)));
+        assert!(!should_scrobble(minutes(20), minutes(3)));
+        assert!(!should_scrobble(
+            Duration::from_secs(20),
+            Duration::from_secs(20)
+        ));
+    }
+
+    /// Accept a single HTTP request on a local port, answer with `status` and return the request.
+    fn stand_in_server(status: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 4096];
+            loop {
+                let read = stream.read(&mut buffer).unwrap();
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length = head
+                        .lines()
+                        .find_map(|l| {
+                            l.to_lowercase()
+                                .strip_prefix("content-length: ")
+                                .map(str::to_string)
+                        })
+                        .and_then(|l| l.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break;
+                    }
+                }
+            }
+            write!(stream, "HTTP/1.1 {status}\r\nContent-Length: 0\r\n\r\n").unwrap();
+            String::from_utf8_lossy(&request).into_owned()
+        });
+        (url, handle)
+    }
+
+    fn listen() -> Listen {
+        Listen {
+            artist: "The Artist".into(),
+            title: "The Title".into(),
+            album: Some("The Album".into()),
+            duration_ms: 180_000,
+            url: None,
+            listened_at: Some(1_700_000_000),
+        }
+    }
+
+    #[test]
+    fn submits_listens_to_listenbrainz() {
+        let (url, server) = stand_in_server("200 OK");
+        let client = create_client(&ScrobblingConfig {
+            url: Some(url),
+            token: Some("secret".into()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert!(client.submit(&[listen()]).is_ok());
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /1/submit-listens"));
+        assert!(request.contains("Token secret"));
+        assert!(request.contains(r#""listen_type":"single""#));
+        assert!(request.contains(r#""listened_at":1700000000"#));
+    }
+
+    #[test]
+    fn signs_lastfm_requests() {
+        let (url, server) = stand_in_server("503 Service Unavailable");
+        let client = create_client(&ScrobblingConfig {
+            service: Some(ScrobbleService::LastFm),
+            url: Some(url),
+            token: Some("session".into()),
+            api_key: Some("key".into()),
+            api_secret: Some("secret".into()),
+        })
+        .unwrap();
+
+        assert!(matches!(
+            client.submit(&[listen()]),
+            Err(SubmitError::Retry(_))
+        ));
+        let request = server.join().unwrap();
+        assert!(request.contains("method=track.scrobble"));
+        assert!(request.contains("timestamp%5B0%5D=1700000000"));
+        assert!(request.contains("api_sig="));
+    }
+}
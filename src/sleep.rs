@@ -0,0 +1,101 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use strum_macros::Display;
+
+/// The volume is faded out during this final part of a sleep timer, if enabled.
+pub const FADE_DURATION: Duration = Duration::from_secs(60);
+
+/// What happens to playback when a sleep timer ends.
+#[derive(Display, Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum SleepAction {
+    /// Pause playback, so it can be resumed at the same position.
+    #[default]
+    Pause,
+    /// Stop playback.
+    Stop,
+}
+
+/// The point at which a sleep timer ends playback, as given to the `sleep` command.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum SleepMode {
+    /// Cancel the running sleep timer.
+    #[default]
+    Off,
+    /// End playback after this amount of time.
+    Duration(Duration),
+    /// End playback after this amount of tracks finished playing, including the current one.
+    Tracks(usize),
+}
+
+impl fmt::Display for SleepMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Off => write!(f, "off"),
+            Self::Duration(duration) if duration.as_secs() % 60 == 0 => {
+                write!(f, "{}m", duration.as_secs() / 60)
+            }
+            Self::Duration(duration) => write!(f, "{}s", duration.as_secs()),
+            Self::Tracks(1) => write!(f, "end-of-track"),
+            Self::Tracks(count) => write!(f, "after {count} tracks"),
+        }
+    }
+}
+
+/// A running sleep timer.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SleepTimer {
+    /// Playback ends at this point in time.
+    Deadline(Instant),
+    /// Playback ends after this amount of tracks finished playing, including the current one.
+    Tracks(usize),
+}
+
+impl SleepTimer {
+    /// Start a new sleep timer for `mode`. Returns None if `mode` cancels the timer.
+    pub fn new(mode: &SleepMode) -> Option<Self> {
+        match mode {
+            SleepMode::Off | SleepMode::Tracks(0) => None,
+            SleepMode::Duration(duration) => Some(Self::Deadline(Instant::now() + *duration)),
+            SleepMode::Tracks(count) => Some(Self::Tracks(*count)),
+        }
+    }
+}
+
+/// The volume to use when `remaining` time is left of a sleep timer, fading out linearly from
+/// `volume` over the [FADE_DURATION].
+pub fn faded_volume(volume: u16, remaining: Duration) -> u16 {
+    if remaining >= FADE_DURATION {
+        volume
+    } else {
+        (volume as f64 * remaining.as_secs_f64() / FADE_DURATION.as_secs_f64()) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fades_volume_over_the_final_minute() {
+        assert_eq!(faded_volume(1000, Duration::from_secs(120)), 1000);
+        assert_eq!(faded_volume(1000, Duration::from_secs(30)), 500);
+        assert_eq!(faded_volume(1000, Duration::ZERO), 0);
+    }
+
+    #[test]
+    fn formats_modes_as_command_arguments() {
+        assert_eq!(
+            SleepMode::Duration(Duration::from_secs(1800)).to_string(),
+            "30m"
+        );
+        assert_eq!(
+            SleepMode::Duration(Duration::from_secs(90)).to_string(),
+            "90s"
+        );
+        assert_eq!(SleepMode::Tracks(1).to_string(), "end-of-track");
+        assert_eq!(SleepMode::Tracks(3).to_string(), "after 3 tracks");
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::config::SortingOrder;
+use crate::library::Library;
+use crate::model::playable::Playable;
+use crate::model::playlist::Playlist;
+use crate::model::track::Track;
+
+/// A playlist that is defined locally by a set of rules in the configuration file. It is
+/// evaluated against the saved tracks of the [Library] whenever the library changes.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SmartPlaylistRule {
+    pub name: String,
+    /// Only include tracks that were saved within this duration, i.e. `"30d"`.
+    pub added_within: Option<String>,
+    /// Only include tracks by at least one artist that the user follows.
+    pub followed_artists: Option<bool>,
+    /// Only include tracks by at least one of these artists (case insensitive).
+    pub artists: Option<Vec<String>>,
+    /// Only include tracks that are at least this long, i.e. `"3m"`.
+    pub min_duration: Option<String>,
+    /// Only include tracks that are at most this long, i.e. `"10m"`.
+    pub max_duration: Option<String>,
+    /// The order of the resulting tracks. Defaults to the order of the saved tracks.
+    pub sort: Option<SortingOrder>,
+    /// The maximum amount of tracks in the playlist.
+    pub limit: Option<usize>,
+}
+
+/// The compiled form of a [SmartPlaylistRule] that can be matched against tracks.
+#[derive(Debug, Default)]
+struct TrackFilter {
+    added_within: Option<chrono::Duration>,
+    followed_artists: bool,
+    artists: Vec<String>,
+    min_duration: Option<u32>,
+    max_duration: Option<u32>,
+}
+
+impl TrackFilter {
+    fn matches(&self, track: &Track, followed: &HashSet<&str>, now: DateTime<Utc>) -> bool {
+        if let Some(within) = self.added_within {
+            match track.added_at {
+                Some(added_at) if now - added_at <= within => {}
+                _ => return false,
+            }
+        }
+
+        if self.followed_artists && !track.artist_ids.iter().any(|id| followed.contains(&**id)) {
+            return false;
+        }
+
+        if !self.artists.is_empty()
+            && !track
+                .artists
+                .iter()
+                .any(|name| self.artists.contains(&name.to_lowercase()))
+        {
+            return false;
+        }
+
+        if self.min_duration.is_some_and(|min| track.duration < min)
+            || self.max_duration.is_some_and(|max| track.duration > max)
+        {
+            return false;
+        }
+
+        true
+    }
+}
+
+/// Parse a human readable duration like `"3m"` or `"30 days"`.
+fn parse_duration(field: &str, value: &str) -> Result<Duration, String> {
+    parse_duration::parse(value).map_err(|e| format!("invalid {field} \"{value}\": {e}"))
+}
+
+impl SmartPlaylistRule {
+    /// The id of the virtual [Playlist] generated from this rule.
+    pub fn playlist_id(&self) -> String {
+        format!("smart:{}", self.name)
+    }
+
+    fn filter(&self) -> Result<TrackFilter, String> {
+        let added_within = self
+            .added_within
+            .as_deref()
+            .map(|value| parse_duration("added_within", value))
+            .transpose()?
+            .map(|within| chrono::Duration::from_std(within).unwrap_or(chrono::Duration::MAX));
+        let min_duration = self
+            .min_duration
+            .as_deref()
+            .map(|value| parse_duration("min_duration", value))
+            .transpose()?
+            .map(|min| min.as_millis().try_into().unwrap_or(u32::MAX));
+        let max_duration = self
+            .max_duration
+            .as_deref()
+            .map(|value| parse_duration("max_duration", value))
+            .transpose()?
+            .map(|max| max.as_millis().try_into().unwrap_or(u32::MAX));
+
+        Ok(TrackFilter {
+            added_within,
+            followed_artists: self.followed_artists.unwrap_or(false),
+            artists: self
+                .artists
+                .iter()
+                .flatten()
+                .map(|name| name.to_lowercase())
+                .collect(),
+            min_duration,
+            max_duration,
+        })
+    }
+
+    /// Evaluate this rule against the saved tracks in `library` and return the resulting
+    /// virtual [Playlist].
+    pub fn evaluate(&self, library: &Library) -> Result<Playlist, String> {
+        let filter = self.filter()?;
+        let now = Utc::now();
+
+        let artists = library.artists.read().unwrap();
+        let followed: HashSet<&str> = artists
+            .iter()
+            .filter(|artist| artist.is_followed)
+            .filter_map(|artist| artist.id.as_deref())
+            .collect();
+
+        let tracks: Vec<Playable> = library
+            .tracks
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|track| filter.matches(track, &followed, now))
+            .cloned()
+            .map(Playable::Track)
+            .collect();
+
+        let mut playlist = Playlist::smart(
+            self.playlist_id(),
+            self.name.clone(),
+            library.user_id.clone().unwrap_or_default(),
+            tracks,
+        );
+
+        if let Some(order) = &self.sort {
+            playlist.sort(order, &library.cfg.state().play_counts);
+        }
+
+        if let (Some(limit), Some(tracks)) = (self.limit, playlist.tracks.as_mut()) {
+            tracks.truncate(limit);
+        }
+
+        if let Some(tracks) = playlist.tracks.as_mut() {
+            for (index, track) in tracks.iter_mut().enumerate() {
+                track.set_list_index(index);
+            }
+            playlist.num_tracks = tracks.len();
+        }
+
+        Ok(playlist)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(duration: u32, artist_id: &str, added_days_ago: Option<i64>) -> Track {
+        Track {
+            duration,
+            artist_ids: vec![artist_id.into()],
+            added_at: added_days_ago.map(|days| Utc::now() - chrono::Duration::days(days)),
+            ..Track::test("id", "title", "The Artist", None)
+        }
+    }
+
+    fn rule() -> SmartPlaylistRule {
+        SmartPlaylistRule {
+            name: "test".into(),
+            added_within: None,
+            followed_artists: None,
+            artists: None,
+            min_duration: None,
+            max_duration: None,
+            sort: None,
+            limit: None,
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        let rule = SmartPlaylistRule {
+            min_duration: Some("three minutes".into()),
+            ..rule()
+        };
+        assert!(rule.filter().is_err());
+    }
+
+    #[test]
+    fn filters_on_duration() {
+        let filter = SmartPlaylistRule {
+            min_duration: Some("3m".into()),
+            max_duration: Some("5m".into()),
+            ..rule()
+        }
+        .filter()
+        .unwrap();
+        let followed = HashSet::new();
+        let now = Utc::now();
+
+        assert!(!filter.matches(&track(120_000, "a", None), &followed, now));
+        assert!(filter.matches(&track(240_000, "a", None), &followed, now));
+        assert!(!filter.matches(&track(360_000, "a", None), &followed, now));
+    }
+
+    #[test]
+    fn filters_on_added_date_and_followed_artists() {
+        let filter = SmartPlaylistRule {
+            added_within: Some("30d".into()),
+            followed_artists: Some(true),
+            ..rule()
+        }
+        .filter()
+        .unwrap();
+        let followed = HashSet::from(["followed"]);
+        let now = Utc::now();
+
+        assert!(filter.matches(&track(1, "followed", Some(2)), &followed, now));
+        assert!(!filter.matches(&track(1, "followed", Some(60)), &followed, now));
+        assert!(!filter.matches(&track(1, "followed", None), &followed, now));
+        assert!(!filter.matches(&track(1, "other", Some(2)), &followed, now));
+    }
+
+    #[test]
+    fn filters_on_artist_names() {
+        let filter = SmartPlaylistRule {
+            artists: Some(vec!["the artist".into()]),
+            ..rule()
+        }
+        .filter()
+        .unwrap();
+        let followed = HashSet::new();
+
+        assert!(filter.matches(&track(1, "a", None), &followed, Utc::now()));
+    }
+}
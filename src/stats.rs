@@ -0,0 +1,325 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::fmt::Write;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, Timelike};
+use strum_macros::{Display, EnumString};
+
+use crate::model::playable::Playable;
+use crate::play_history::HistoryEntry;
+use crate::utils::format_duration;
+
+/// Skipped items only count as a play if they were listened to for at least this long.
+const MIN_PLAY_DURATION: Duration = Duration::from_secs(30);
+
+/// The part of the play history that statistics are computed for.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatsPeriod {
+    /// The whole play history.
+    All,
+    /// A calendar year.
+    Year(i32),
+    /// The given amount of time up to now.
+    Last(Duration),
+}
+
+impl StatsPeriod {
+    fn contains(&self, played_at: &DateTime<Local>, now: &DateTime<Local>) -> bool {
+        match self {
+            Self::All => true,
+            Self::Year(year) => played_at.year() == *year,
+            Self::Last(duration) => chrono::Duration::from_std(*duration)
+                .map(|duration| *now - *played_at <= duration)
+                .unwrap_or(true),
+        }
+    }
+}
+
+impl FromStr for StatsPeriod {
+    type Err = String;
+
+    /// Parse `all`, a year like `2024` or a duration like `30d`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(Self::All),
+            _ if s.len() == 4 && s.chars().all(|c| c.is_ascii_digit()) => {
+                s.parse().map(Self::Year).map_err(|e| format!("{e}"))
+            }
+            _ => parse_duration::parse(s)
+                .map(Self::Last)
+                .map_err(|e| format!("invalid period \"{s}\": {e}")),
+        }
+    }
+}
+
+impl fmt::Display for StatsPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::All => write!(f, "all"),
+            Self::Year(year) => write!(f, "{year}"),
+            Self::Last(duration) if duration.as_secs() % 86400 == 0 => {
+                write!(f, "{}d", duration.as_secs() / 86400)
+            }
+            Self::Last(duration) => write!(f, "{}s", duration.as_secs()),
+        }
+    }
+}
+
+/// How the top lists are ranked.
+#[derive(Display, EnumString, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[strum(serialize_all = "lowercase")]
+pub enum StatsOrder {
+    /// By the amount of plays.
+    #[default]
+    Plays,
+    /// By the total listening time.
+    Time,
+}
+
+/// The output format of an exported [Report].
+#[derive(Display, EnumString, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[strum(serialize_all = "lowercase")]
+pub enum StatsFormat {
+    #[default]
+    Markdown,
+    Json,
+}
+
+/// A track, artist or album in a top list.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Ranked {
+    pub name: String,
+    pub plays: u32,
+    pub listened_secs: u64,
+}
+
+/// Listening statistics computed from the play history.
+#[derive(Clone, Debug, Serialize)]
+pub struct Report {
+    pub period: String,
+    pub plays: u32,
+    pub listened_secs: u64,
+    pub top_tracks: Vec<Ranked>,
+    pub top_artists: Vec<Ranked>,
+    pub top_albums: Vec<Ranked>,
+    /// The most consecutive days with at least one play.
+    pub longest_streak_days: u32,
+    /// The consecutive days with at least one play up to today or yesterday.
+    pub current_streak_days: u32,
+    /// The amount of plays in every hour of the day, in local time.
+    pub plays_per_hour: [u32; 24],
+}
+
+/// Add a play to the `name` entry of a top list.
+fn count(list: &mut HashMap<String, Ranked>, name: String, played: bool, listened: Duration) {
+    let ranked = list.entry(name.clone()).or_insert_with(|| Ranked {
+        name,
+        ..Default::default()
+    });
+    ranked.plays += u32::from(played);
+    ranked.listened_secs += listened.as_secs();
+}
+
+/// The top `limit` items of `list` according to `order`.
+fn top(list: HashMap<String, Ranked>, order: StatsOrder, limit: usize) -> Vec<Ranked> {
+    let mut list: Vec<Ranked> = list.into_values().filter(|r| r.plays > 0).collect();
+    list.sort_by(|a, b| {
+        let (a_key, b_key) = match order {
+            StatsOrder::Plays => (
+                (a.plays as u64, a.listened_secs),
+                (b.plays as u64, b.listened_secs),
+            ),
+            StatsOrder::Time => (
+                (a.listened_secs, a.plays as u64),
+                (b.listened_secs, b.plays as u64),
+            ),
+        };
+        b_key.cmp(&a_key).then_with(|| a.name.cmp(&b.name))
+    });
+    list.truncate(limit);
+    list
+}
+
+/// The longest and the current streak of consecutive `days`. The current streak has to include
+/// `today` or the day before.
+fn streaks(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> (u32, u32) {
+    let mut longest = 0;
+    let mut streak = 0;
+    let mut previous: Option<NaiveDate> = None;
+
+    for day in days {
+        streak = match previous {
+            Some(previous) if previous.succ_opt() == Some(*day) => streak + 1,
+            _ => 1,
+        };
+        longest = longest.max(streak);
+        previous = Some(*day);
+    }
+
+    let current = match previous {
+        Some(last) if last == today || last.succ_opt() == Some(today) => streak,
+        _ => 0,
+    };
+    (longest, current)
+}
+
+impl Report {
+    /// Compute the statistics for `period` from the play history `entries`, keeping the top
+    /// `limit` items of every list.
+    pub fn new(
+        entries: &[HistoryEntry],
+        period: &StatsPeriod,
+        order: StatsOrder,
+        limit: usize,
+        now: DateTime<Local>,
+    ) -> Self {
+        let mut plays = 0;
+        let mut listened_secs = 0;
+        let mut tracks = HashMap::new();
+        let mut artists = HashMap::new();
+        let mut albums = HashMap::new();
+        let mut days = BTreeSet::new();
+        let mut plays_per_hour = [0; 24];
+
+        for entry in entries {
+            let played_at = entry.played_at.with_timezone(&Local);
+            if !period.contains(&played_at, &now) {
+                continue;
+            }
+
+            let played = !entry.skipped || entry.listened >= MIN_PLAY_DURATION;
+            listened_secs += entry.listened.as_secs();
+            if played {
+                plays += 1;
+                days.insert(played_at.date_naive());
+                plays_per_hour[played_at.hour() as usize] += 1;
+            }
+
+            match &entry.playable {
+                Playable::Track(track) => {
+                    let name = format!("{} - {}", track.artists.join(", "), track.title);
+                    count(&mut tracks, name, played, entry.listened);
+                    for artist in &track.artists {
+                        count(&mut artists, artist.clone(), played, entry.listened);
+                    }
+                    if let Some(album) = &track.album {
+                        let name = match track.album_artists.first() {
+                            Some(artist) => format!("{artist} - {album}"),
+                            None => album.clone(),
+                        };
+                        count(&mut albums, name, played, entry.listened);
+                    }
+                }
+                Playable::Episode(episode) => {
+                    count(&mut tracks, episode.name.clone(), played, entry.listened);
+                }
+            }
+        }
+
+        let (longest_streak_days, current_streak_days) = streaks(&days, now.date_naive());
+
+        Self {
+            period: period.to_string(),
+            plays,
+            listened_secs,
+            top_tracks: top(tracks, order, limit),
+            top_artists: top(artists, order, limit),
+            top_albums: top(albums, order, limit),
+            longest_streak_days,
+            current_streak_days,
+            plays_per_hour,
+        }
+    }
+
+    /// Export the report in the given `format`.
+    pub fn export(&self, format: StatsFormat) -> String {
+        match format {
+            StatsFormat::Json => serde_json::to_string_pretty(self).unwrap_or_default(),
+            StatsFormat::Markdown => self.to_markdown(),
+        }
+    }
+
+    fn to_markdown(&self) -> String {
+        let listened = |secs: u64| format_duration(&Duration::from_secs(secs));
+        let mut out = String::new();
+
+        let _ = writeln!(out, "# Listening statistics ({})\n", self.period);
+        let _ = writeln!(out, "- Plays: {}", self.plays);
+        let _ = writeln!(out, "- Listening time: {}", listened(self.listened_secs));
+        let _ = writeln!(out, "- Longest streak: {} days", self.longest_streak_days);
+        let _ = writeln!(out, "- Current streak: {} days", self.current_streak_days);
+
+        for (title, list) in [
+            ("Top tracks", &self.top_tracks),
+            ("Top artists", &self.top_artists),
+            ("Top albums", &self.top_albums),
+        ] {
+            let _ = writeln!(out, "\n## {title}\n");
+            let _ = writeln!(out, "| # | Name | Plays | Listening time |");
+            let _ = writeln!(out, "|---|------|-------|----------------|");
+            for (index, ranked) in list.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {} | {} |",
+                    index + 1,
+                    ranked.name.replace('|', "\\|"),
+                    ranked.plays,
+                    listened(ranked.listened_secs)
+                );
+            }
+        }
+
+        let _ = writeln!(out, "\n## Plays by hour of day\n");
+        let _ = writeln!(out, "| Hour | Plays | |");
+        let _ = writeln!(out, "|------|-------|-|");
+        let max = self
+            .plays_per_hour
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        for (hour, plays) in self.plays_per_hour.iter().enumerate() {
+            let bar = "█".repeat((*plays as usize * 20).div_ceil(max as usize));
+            let _ = writeln!(out, "| {hour:02}:00 | {plays} | {bar} |");
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    #[test]
+    fn finds_streaks() {
+        let days = BTreeSet::from([date(1), date(2), date(3), date(10), date(11)]);
+
+        assert_eq!(streaks(&days, date(12)), (3, 2));
+        assert_eq!(streaks(&days, date(11)), (3, 2));
+        assert_eq!(streaks(&days, date(20)), (3, 0));
+        assert_eq!(streaks(&BTreeSet::new(), date(1)), (0, 0));
+    }
+
+    #[test]
+    fn parses_periods() {
+        assert_eq!("all".parse(), Ok(StatsPeriod::All));
+        assert_eq!("2024".parse(), Ok(StatsPeriod::Year(2024)));
+        assert_eq!(
+            "30d".parse(),
+            Ok(StatsPeriod::Last(Duration::from_secs(30 * 86400)))
+        );
+        assert!("soon".parse::<StatsPeriod>().is_err());
+        assert_eq!(
+            StatsPeriod::Last(Duration::from_secs(7 * 86400)).to_string(),
+            "7d"
+        );
+    }
+}
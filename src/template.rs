@@ -0,0 +1,261 @@
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// The characters of a template that is being parsed, with their byte offsets.
+type Chars<'a> = Peekable<CharIndices<'a>>;
+
+/// A format with placeholders like `%artists - %title{?album: (%album)}`.
+///
+/// - `%name` is replaced with the value of the field `name`. `%%` is a literal `%`.
+/// - `%20name` pads the value to 20 columns on the left, `%-20name` on the right, and `%.20name`
+///   truncates it to 20 columns. They can be combined, like `%-20.20name`.
+/// - `{?name:text}` is replaced with `text`, which can contain placeholders itself, only if the
+///   field `name` isn't empty.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Part {
+    Text(String),
+    Field { name: String, width: Width },
+    Conditional { name: String, body: Template },
+}
+
+/// How the value of a field is padded and truncated.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Width {
+    min: Option<usize>,
+    max: Option<usize>,
+    /// Whether the value is aligned to the left when it's padded.
+    left: bool,
+}
+
+impl Width {
+    fn apply(&self, value: String) -> String {
+        let mut value = match self.max {
+            Some(max) if value.width() > max => truncate(&value, max),
+            _ => value,
+        };
+        if let Some(min) = self.min {
+            let padding = " ".repeat(min.saturating_sub(value.width()));
+            if self.left {
+                value.push_str(&padding);
+            } else {
+                value.insert_str(0, &padding);
+            }
+        }
+        value
+    }
+}
+
+/// Shorten `value` to `max` columns, with an ellipsis at the end.
+pub fn truncate(value: &str, max: usize) -> String {
+    if max == 0 {
+        return String::new();
+    }
+    let mut truncated = String::new();
+    let mut width = 0;
+    for c in value.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if width + char_width > max - 1 {
+            break;
+        }
+        width += char_width;
+        truncated.push(c);
+    }
+    truncated.push('…');
+    truncated
+}
+
+impl Template {
+    /// Parse `source`, in which the placeholders in `fields` can be used.
+    pub fn parse(source: &str, fields: &[&str]) -> Result<Self, String> {
+        Self::parse_parts(source, &mut source.char_indices().peekable(), fields, false)
+    }
+
+    fn parse_parts(
+        source: &str,
+        chars: &mut Chars<'_>,
+        fields: &[&str],
+        in_conditional: bool,
+    ) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+
+        while let Some((_, c)) = chars.next() {
+            match c {
+                '%' if chars.peek().is_some_and(|(_, c)| *c == '%') => {
+                    chars.next();
+                    text.push('%');
+                }
+                '%' => {
+                    let mut width = Width::default();
+                    if chars.next_if(|(_, c)| *c == '-').is_some() {
+                        width.left = true;
+                    }
+                    width.min = parse_number(chars);
+                    if chars.next_if(|(_, c)| *c == '.').is_some() {
+                        width.max = Some(parse_number(chars).ok_or_else(|| {
+                            format!("expected a width after \".\" in \"{source}\"")
+                        })?);
+                    }
+                    let name = parse_field(source, chars, fields)?;
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Field { name, width });
+                }
+                '{' if chars.peek().is_some_and(|(_, c)| *c == '?') => {
+                    chars.next();
+                    let name = parse_field(source, chars, fields)?;
+                    if chars.next_if(|(_, c)| *c == ':').is_none() {
+                        return Err(format!(
+                            "expected \":\" after the condition \"{name}\" in \"{source}\""
+                        ));
+                    }
+                    let body = Self::parse_parts(source, chars, fields, true)?;
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Conditional { name, body });
+                }
+                '}' if in_conditional => {
+                    if !text.is_empty() {
+                        parts.push(Part::Text(text));
+                    }
+                    return Ok(Self { parts });
+                }
+                _ => text.push(c),
+            }
+        }
+
+        if in_conditional {
+            return Err(format!(
+                "a conditional isn't closed with \"}}\" in \"{source}\""
+            ));
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Fill in the placeholders with the values that `value` returns for the names of the fields.
+    pub fn render(&self, value: &dyn Fn(&str) -> String) -> String {
+        let mut output = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => output.push_str(text),
+                Part::Field { name, width } => output.push_str(&width.apply(value(name))),
+                Part::Conditional { name, body } => {
+                    if !value(name).is_empty() {
+                        output.push_str(&body.render(value));
+                    }
+                }
+            }
+        }
+        output
+    }
+
+    /// The names of the fields that are used, including the ones in conditionals.
+    pub fn fields(&self) -> Vec<&str> {
+        self.parts
+            .iter()
+            .flat_map(|part| match part {
+                Part::Text(_) => Vec::new(),
+                Part::Field { name, .. } => vec![name.as_str()],
+                Part::Conditional { name, body } => {
+                    let mut fields = vec![name.as_str()];
+                    fields.extend(body.fields());
+                    fields
+                }
+            })
+            .collect()
+    }
+}
+
+fn parse_number(chars: &mut Chars<'_>) -> Option<usize> {
+    let mut number = None;
+    while let Some((_, c)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+        number = Some(number.unwrap_or(0) * 10 + c.to_digit(10).unwrap_or(0) as usize);
+    }
+    number
+}
+
+/// Parse the longest name in `fields` that comes next.
+fn parse_field(source: &str, chars: &mut Chars<'_>, fields: &[&str]) -> Result<String, String> {
+    let start = chars
+        .peek()
+        .map(|(index, _)| *index)
+        .unwrap_or(source.len());
+    let rest = &source[start..];
+    let name = fields
+        .iter()
+        .filter(|field| rest.starts_with(**field))
+        .max_by_key(|field| field.len())
+        .ok_or_else(|| {
+            let word: String = rest
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .collect();
+            if word.is_empty() {
+                format!("expected a placeholder name in \"{source}\", use \"%%\" for \"%\"")
+            } else {
+                format!("unknown placeholder \"{word}\" in \"{source}\"")
+            }
+        })?;
+    for _ in name.chars() {
+        chars.next();
+    }
+    Ok(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELDS: [&str; 4] = ["artist", "artists", "title", "album"];
+
+    fn render(source: &str, album: &str) -> Result<String, String> {
+        let template = Template::parse(source, &FIELDS)?;
+        Ok(template.render(&|name| match name {
+            "artists" => "Foo, Bar".to_string(),
+            "artist" => "Foo".to_string(),
+            "title" => "Song".to_string(),
+            "album" => album.to_string(),
+            _ => String::new(),
+        }))
+    }
+
+    #[test]
+    fn renders_templates() {
+        assert_eq!(
+            render("%artists - %title (100%%)", "").as_deref(),
+            Ok("Foo, Bar - Song (100%)")
+        );
+        assert_eq!(
+            render("{%title} {?album:on %album}", "Album").as_deref(),
+            Ok("{Song} on Album")
+        );
+        assert_eq!(
+            render("%title{?album: - %album}", "").as_deref(),
+            Ok("Song")
+        );
+        assert_eq!(
+            render("[%-6artist][%6artist][%.4artists]", "").as_deref(),
+            Ok("[Foo   ][   Foo][Foo…]")
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        assert!(render("%titel", "").is_err());
+        assert!(render("50%", "").is_err());
+        assert!(render("{?album:%album", "").is_err());
+        assert!(render("%.title", "").is_err());
+    }
+}
@@ -5,7 +5,8 @@ use cursive::views::NamedView;
 use cursive::Cursive;
 
 use crate::command::Command;
-use crate::commands::CommandResult;
+use crate::commands::{BindingContext, CommandResult};
+use crate::config::ColumnField;
 use crate::library::Library;
 use crate::model::album::Album;
 use crate::model::artist::Artist;
@@ -59,6 +60,12 @@ pub trait ListItem: Sync + Send + 'static {
         false
     }
 
+    /// The value of `field` in the columns of a track list, or None if this [ListItem] isn't
+    /// shown in columns.
+    fn column(&self, _field: ColumnField, _library: &Library) -> Option<String> {
+        None
+    }
+
     fn as_listitem(&self) -> Box<dyn ListItem>;
 }
 
@@ -73,6 +80,11 @@ pub trait ViewExt: View {
 
     fn on_leave(&self) {}
 
+    /// The context whose keybindings apply while this view is focused.
+    fn binding_context(&self) -> Option<BindingContext> {
+        None
+    }
+
     fn on_command(&mut self, _s: &mut Cursive, _cmd: &Command) -> Result<CommandResult, String> {
         Ok(CommandResult::Ignored)
     }
@@ -91,6 +103,10 @@ impl<V: ViewExt> ViewExt for NamedView<V> {
         self.with_view(|v| v.on_leave());
     }
 
+    fn binding_context(&self) -> Option<BindingContext> {
+        self.with_view(|v| v.binding_context()).flatten()
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         self.with_view_mut(move |v| v.on_command(s, cmd)).unwrap()
     }
@@ -177,6 +193,10 @@ impl ViewExt for BoxedViewExt {
         self.boxed_view.on_leave();
     }
 
+    fn binding_context(&self) -> Option<BindingContext> {
+        self.boxed_view.binding_context()
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         self.boxed_view.on_command(s, cmd)
     }
@@ -4,7 +4,7 @@ use cursive::view::ViewWrapper;
 use cursive::Cursive;
 
 use crate::command::Command;
-use crate::commands::CommandResult;
+use crate::commands::{BindingContext, CommandResult};
 use crate::library::Library;
 use crate::model::album::Album;
 use crate::model::artist::Artist;
@@ -44,7 +44,8 @@ impl AlbumView {
                 Arc::new(RwLock::new(tracks)),
                 queue.clone(),
                 library.clone(),
-            ),
+            )
+            .with_columns("album"),
         );
         tabs.add_tab(
             "Artists",
@@ -75,6 +76,10 @@ impl ViewExt for AlbumView {
         }
     }
 
+    fn binding_context(&self) -> Option<BindingContext> {
+        Some(BindingContext::Album)
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         self.tabs.on_command(s, cmd)
     }
@@ -72,12 +72,13 @@ impl ArtistView {
                     Arc::new(RwLock::new(tracks)),
                     queue.clone(),
                     library.clone(),
-                ),
+                )
+                .with_columns("artist"),
             );
         }
         tabs.add_tab(
             "Top 10",
-            ListView::new(top_tracks, queue.clone(), library.clone()),
+            ListView::new(top_tracks, queue.clone(), library.clone()).with_columns("artist"),
         );
         tabs.add_tab("Albums", albums_view);
         tabs.add_tab("Singles", singles_view);
@@ -0,0 +1,248 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use cursive::event::{Event, EventResult, Key};
+use cursive::traits::View;
+use cursive::view::ViewWrapper;
+use cursive::views::EditView;
+use log::error;
+
+use crate::completion::{common_prefix, Completer};
+
+/// The file in the cache directory that the command line history is saved to.
+pub const CMDLINE_HISTORY_FILE_NAME: &str = "cmdline_history";
+
+/// The most entries that are kept in the command line history.
+const MAX_HISTORY_ENTRIES: usize = 1000;
+
+/// The commands and searches that were entered on the command line, oldest first. The history is
+/// saved after every entry, so it's kept across sessions.
+pub struct History {
+    entries: Vec<String>,
+    /// The index of the entry that is shown, while the history is browsed.
+    position: Option<usize>,
+    /// What was typed before the history was browsed. Only entries that start with it are shown.
+    draft: String,
+    path: PathBuf,
+}
+
+impl History {
+    pub fn load(path: PathBuf) -> Self {
+        let entries = fs::read_to_string(&path)
+            .map(|content| content.lines().map(String::from).collect())
+            .unwrap_or_default();
+        Self {
+            entries,
+            position: None,
+            draft: String::new(),
+            path,
+        }
+    }
+
+    /// Add `entry` to the end of the history and save it. Earlier occurrences are removed.
+    fn push(&mut self, entry: &str) {
+        self.position = None;
+        // A lone prefix like ":" isn't worth remembering.
+        if entry.chars().nth(1).is_none() || entry.contains('\n') {
+            return;
+        }
+
+        self.entries.retain(|e| e != entry);
+        self.entries.push(entry.to_string());
+        let excess = self.entries.len().saturating_sub(MAX_HISTORY_ENTRIES);
+        self.entries.drain(..excess);
+
+        let mut content = self.entries.join("\n");
+        content.push('\n');
+        if let Err(e) = fs::write(&self.path, content) {
+            error!("could not save the command line history: {e}");
+        }
+    }
+
+    /// The entry before the one that is shown. `current` is the content of the command line.
+    fn previous(&mut self, current: &str) -> Option<String> {
+        let end = match self.position {
+            Some(position) => position,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len()
+            }
+        };
+        let position = self.entries[..end]
+            .iter()
+            .rposition(|entry| entry.starts_with(&self.draft))?;
+        self.position = Some(position);
+        Some(self.entries[position].clone())
+    }
+
+    /// The entry after the one that is shown, or what was typed before the history was browsed.
+    fn next(&mut self) -> Option<String> {
+        let start = self.position? + 1;
+        self.position = self.entries[start..]
+            .iter()
+            .position(|entry| entry.starts_with(&self.draft))
+            .map(|offset| start + offset);
+        Some(match self.position {
+            Some(position) => self.entries[position].clone(),
+            None => self.draft.clone(),
+        })
+    }
+}
+
+/// The command line, with Tab completion of commands and a history that is browsed with the up and
+/// down keys.
+pub struct CommandLine {
+    view: EditView,
+    completer: Completer,
+    history: History,
+    /// The completions that Tab cycles through, and the one that is shown.
+    completions: Vec<String>,
+    completion: Option<usize>,
+}
+
+impl CommandLine {
+    pub fn new(view: EditView, completer: Completer, history: History) -> Self {
+        Self {
+            view,
+            completer,
+            history,
+            completions: Vec::new(),
+            completion: None,
+        }
+    }
+
+    pub fn get_content(&self) -> Arc<String> {
+        self.view.get_content()
+    }
+
+    pub fn set_content<S: Into<String>>(&mut self, content: S) {
+        self.view.set_content(content);
+        self.completions.clear();
+        self.completion = None;
+    }
+
+    /// The completions of the word that is typed, if there is more than one.
+    pub fn completions(&self) -> &[String] {
+        match self.completions.len() {
+            0 | 1 => &[],
+            _ => &self.completions,
+        }
+    }
+
+    /// Complete the word that is typed, or show the next completion if `forward` and the previous
+    /// one otherwise.
+    fn complete(&mut self, forward: bool) {
+        if !self.completions.is_empty() {
+            let count = self.completions.len();
+            let index = match (self.completion, forward) {
+                (None, true) => 0,
+                (None, false) => count - 1,
+                (Some(index), true) => (index + 1) % count,
+                (Some(index), false) => (index + count - 1) % count,
+            };
+            self.completion = Some(index);
+            self.view.set_content(self.completions[index].clone());
+            return;
+        }
+
+        // Searches with "/" can't be completed.
+        let content = self.view.get_content();
+        let mut chars = content.chars();
+        if chars.next() == Some('/') {
+            return;
+        }
+        let prefix = &content[..content.len() - chars.as_str().len()];
+
+        let completions: Vec<String> = self
+            .completer
+            .complete(chars.as_str())
+            .into_iter()
+            .map(|completion| format!("{prefix}{completion}"))
+            .collect();
+        match completions.as_slice() {
+            [] => {}
+            [completion] if completion.ends_with('/') => {
+                self.view.set_content(completion.clone());
+            }
+            [completion] => {
+                self.view.set_content(format!("{completion} "));
+            }
+            _ => {
+                let common = common_prefix(&completions);
+                if common.len() > content.len() {
+                    self.view.set_content(common);
+                } else {
+                    self.completion = Some(0);
+                    self.view.set_content(completions[0].clone());
+                }
+                self.completions = completions;
+            }
+        }
+    }
+}
+
+impl ViewWrapper for CommandLine {
+    wrap_impl!(self.view: EditView);
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Key(Key::Tab) => {
+                self.complete(true);
+                EventResult::consumed()
+            }
+            Event::Shift(Key::Tab) => {
+                self.complete(false);
+                EventResult::consumed()
+            }
+            Event::Key(Key::Up) => {
+                let content = self.view.get_content();
+                if let Some(entry) = self.history.previous(&content) {
+                    self.set_content(entry);
+                }
+                EventResult::consumed()
+            }
+            Event::Key(Key::Down) => {
+                if let Some(entry) = self.history.next() {
+                    self.set_content(entry);
+                }
+                EventResult::consumed()
+            }
+            Event::Key(Key::Enter) => {
+                let content = self.view.get_content();
+                self.history.push(&content);
+                self.completions.clear();
+                self.completion = None;
+                self.view.on_event(event)
+            }
+            event => {
+                self.history.position = None;
+                self.completions.clear();
+                self.completion = None;
+                self.view.on_event(event)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn browses_history_by_prefix() {
+        let mut history = History {
+            entries: vec![":shuffle on".into(), "/beatles".into(), ":seek +10s".into()],
+            position: None,
+            draft: String::new(),
+            path: PathBuf::new(),
+        };
+
+        assert_eq!(history.previous(":"), Some(":seek +10s".into()));
+        assert_eq!(history.previous(":seek +10s"), Some(":shuffle on".into()));
+        assert_eq!(history.previous(":shuffle on"), None);
+        assert_eq!(history.next(), Some(":seek +10s".into()));
+        assert_eq!(history.next(), Some(":".into()));
+        assert_eq!(history.next(), None);
+    }
+}
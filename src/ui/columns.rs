@@ -0,0 +1,152 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::config::{Column, ColumnAlign, ColumnTruncate};
+use crate::template::truncate;
+
+/// The widths of `columns` in a list that is `width` columns wide. The columns are separated by a
+/// space, and the ones without a width share the space that is left.
+pub fn widths(columns: &[Column], width: usize) -> Vec<usize> {
+    let fixed: usize = columns.iter().filter_map(Column::width).sum();
+    let flexible = columns.iter().filter(|c| c.width().is_none()).count();
+    let separators = columns.len().saturating_sub(1);
+    let left = width.saturating_sub(fixed + separators);
+
+    let mut shared = 0;
+    columns
+        .iter()
+        .map(|column| {
+            column.width().unwrap_or_else(|| {
+                shared += 1;
+                // The first columns get the columns that can't be split evenly.
+                left / flexible + usize::from(shared <= left % flexible)
+            })
+        })
+        .collect()
+}
+
+/// The index of the column that column `x` of a row is in, unless it's between two columns.
+pub fn column_at(widths: &[usize], x: usize) -> Option<usize> {
+    let mut start = 0;
+    for (index, width) in widths.iter().enumerate() {
+        if (start..start + width).contains(&x) {
+            return Some(index);
+        }
+        start += width + 1;
+    }
+    None
+}
+
+/// `text` padded or shortened to exactly `width` columns.
+pub fn fit(text: &str, width: usize, align: ColumnAlign, shorten: ColumnTruncate) -> String {
+    let text = if text.width() <= width {
+        text.to_string()
+    } else {
+        match shorten {
+            ColumnTruncate::End => truncate(text, width),
+            ColumnTruncate::Start => truncate_start(text, width),
+            ColumnTruncate::Clip => clip(text, width),
+        }
+    };
+
+    let padding = width.saturating_sub(text.width());
+    let before = match align {
+        ColumnAlign::Left => 0,
+        ColumnAlign::Center => padding / 2,
+        ColumnAlign::Right => padding,
+    };
+    format!(
+        "{}{text}{}",
+        " ".repeat(before),
+        " ".repeat(padding - before)
+    )
+}
+
+/// A row with the values of `columns` in `cells`, which are as wide as `widths`.
+pub fn row(columns: &[Column], widths: &[usize], cells: &[String]) -> String {
+    columns
+        .iter()
+        .zip(widths)
+        .zip(cells)
+        .map(|((column, width), cell)| fit(cell, *width, column.align(), column.truncate()))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Shorten `text` to `max` columns, with an ellipsis at the start.
+fn truncate_start(text: &str, max: usize) -> String {
+    if max == 0 {
+        return String::new();
+    }
+    let mut width = 0;
+    let mut chars: Vec<char> = text
+        .chars()
+        .rev()
+        .take_while(|c| {
+            width += c.width().unwrap_or(0);
+            width < max
+        })
+        .collect();
+    chars.push('…');
+    chars.into_iter().rev().collect()
+}
+
+/// Cut `text` off after `max` columns.
+fn clip(text: &str, max: usize) -> String {
+    let mut width = 0;
+    text.chars()
+        .take_while(|c| {
+            width += c.width().unwrap_or(0);
+            width <= max
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ColumnField;
+
+    #[test]
+    fn lays_out_columns() {
+        let columns = [
+            Column::Field(ColumnField::Index),
+            Column::Field(ColumnField::Title),
+            Column::Field(ColumnField::Artists),
+            Column::Styled {
+                field: ColumnField::Duration,
+                width: Some(5),
+                align: None,
+                truncate: None,
+            },
+        ];
+        let widths = widths(&columns, 40);
+        assert_eq!(widths, vec![4, 14, 14, 5]);
+        assert_eq!(column_at(&widths, 4), None);
+        assert_eq!(column_at(&widths, 5), Some(1));
+        assert_eq!(column_at(&widths, 39), Some(3));
+    }
+
+    #[test]
+    fn fits_values() {
+        assert_eq!(
+            fit("42", 4, ColumnAlign::Right, ColumnTruncate::End),
+            "  42"
+        );
+        assert_eq!(
+            fit("ab", 5, ColumnAlign::Center, ColumnTruncate::End),
+            " ab  "
+        );
+        assert_eq!(
+            fit("Title", 4, ColumnAlign::Left, ColumnTruncate::End),
+            "Tit…"
+        );
+        assert_eq!(
+            fit("Title", 4, ColumnAlign::Left, ColumnTruncate::Start),
+            "…tle"
+        );
+        assert_eq!(
+            fit("Title", 4, ColumnAlign::Left, ColumnTruncate::Clip),
+            "Titl"
+        );
+    }
+}
@@ -53,11 +53,16 @@ enum ContextMenuAction {
 }
 
 impl ContextMenu {
+    /// A dialog that adds `tracks` to a playlist that is chosen from the user's playlists.
     pub fn add_track_dialog(
         library: Arc<Library>,
         spotify: Spotify,
-        track: Track,
+        tracks: Vec<Track>,
     ) -> NamedView<AddToPlaylistMenu> {
+        let title = match tracks.len() {
+            1 => "Add track to playlist".to_string(),
+            count => format!("Add {count} tracks to playlist"),
+        };
         let mut list_select: SelectView<Playlist> = SelectView::new();
         let current_user_id = library.user_id.as_ref().unwrap();
 
@@ -68,18 +73,21 @@ impl ContextMenu {
         }
 
         list_select.set_on_submit(move |s, selected| {
-            let track = track.clone();
+            let playables: Vec<Playable> = tracks.iter().cloned().map(Playable::Track).collect();
             let mut playlist = selected.clone();
             let spotify = spotify.clone();
             let library = library.clone();
 
-            if playlist.has_track(track.id.as_ref().unwrap_or(&String::new())) {
-                let mut already_added_dialog = Self::track_already_added();
+            if tracks
+                .iter()
+                .any(|track| playlist.has_track(track.id.as_ref().unwrap_or(&String::new())))
+            {
+                let mut already_added_dialog = Self::track_already_added(tracks.len());
 
                 already_added_dialog.add_button("Add anyway", move |c| {
                     let mut playlist = playlist.clone();
 
-                    playlist.append_tracks(&[Playable::Track(track.clone())], &spotify, &library);
+                    playlist.append_tracks(&playables, &spotify, &library);
                     c.pop_layer();
 
                     // Close add_track_dialog too
@@ -89,13 +97,13 @@ impl ContextMenu {
                 let modal = Modal::new(already_added_dialog);
                 s.add_layer(modal);
             } else {
-                playlist.append_tracks(&[Playable::Track(track)], &spotify, &library);
+                playlist.append_tracks(&playables, &spotify, &library);
                 s.pop_layer();
             }
         });
 
         let dialog = Dialog::new()
-            .title("Add track to playlist")
+            .title(title)
             .dismiss_button("Close")
             .padding(Margins::lrtb(1, 1, 1, 0))
             .content(ScrollView::new(list_select.with_name("addplaylist_select")));
@@ -191,8 +199,12 @@ impl ContextMenu {
         .with_name("selectartistaction")
     }
 
-    fn track_already_added() -> Dialog {
-        Dialog::text("This track is already in your playlist")
+    fn track_already_added(count: usize) -> Dialog {
+        let text = match count {
+            1 => "This track is already in your playlist",
+            _ => "Some of these tracks are already in your playlist",
+        };
+        Dialog::text(text)
             .title("Track already exists")
             .padding(Margins::lrtb(1, 1, 1, 0))
             .dismiss_button("Close")
@@ -308,8 +320,11 @@ impl ContextMenu {
                         write_share(url.to_string()).ok();
                     }
                     ContextMenuAction::AddToPlaylist(track) => {
-                        let dialog =
-                            Self::add_track_dialog(library, queue.get_spotify(), *track.clone());
+                        let dialog = Self::add_track_dialog(
+                            library,
+                            queue.get_spotify(),
+                            vec![*track.clone()],
+                        );
                         s.add_layer(dialog);
                     }
                     ContextMenuAction::ShowRecommendations(item) => {
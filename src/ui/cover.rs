@@ -6,31 +6,48 @@ use std::process::{Child, Stdio};
 use std::sync::{Arc, RwLock};
 
 use cursive::theme::{ColorStyle, ColorType, PaletteColor};
-use cursive::{Cursive, Printer, Vec2, View};
+use cursive::{CbSink, Cursive, Printer, Vec2, View};
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
 use ioctl_rs::{ioctl, TIOCGWINSZ};
 use log::{debug, error};
 
 use crate::command::{Command, GotoMode};
 use crate::commands::CommandResult;
-use crate::config::Config;
+use crate::config::{Config, CoverProtocol};
 use crate::library::Library;
 use crate::queue::Queue;
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
 use crate::ui::album::AlbumView;
 use crate::ui::artist::ArtistView;
+use crate::ui::graphics;
+
+/// Where an image was written to the terminal: the URL of the cover, and the offset and size of
+/// the area in cells.
+type Placement = (String, Vec2, Vec2);
 
 pub struct CoverView {
     queue: Arc<Queue>,
     library: Arc<Library>,
+    protocol: CoverProtocol,
     loading: Arc<RwLock<HashSet<String>>>,
     last_size: RwLock<Vec2>,
     drawn_url: RwLock<Option<String>>,
     ueberzug: RwLock<Option<Child>>,
     font_size: Vec2,
+    /// The decoded cover, and its URL.
+    image: RwLock<Option<(String, Arc<RgbaImage>)>>,
+    /// The cover scaled for half blocks, and the URL and the size in cells it was scaled for.
+    halfblocks: RwLock<Option<(String, Vec2, RgbaImage)>>,
+    /// The image that was written with a graphics protocol.
+    placed: RwLock<Option<Placement>>,
+    /// The image that is written on the next draw.
+    pending: RwLock<Option<Placement>>,
+    cb_sink: CbSink,
 }
 
 impl CoverView {
-    pub fn new(queue: Arc<Queue>, library: Arc<Library>, config: &Config) -> Self {
+    pub fn new(queue: Arc<Queue>, library: Arc<Library>, config: &Config, cb_sink: CbSink) -> Self {
         // Determine size of window both in pixels and chars
         let (rows, cols, mut xpixels, mut ypixels) = unsafe {
             let mut query: (u16, u16, u16, u16) = (0, 0, 0, 0);
@@ -48,19 +65,149 @@ impl CoverView {
         xpixels = ((xpixels as f32) / scale) as u16;
         ypixels = ((ypixels as f32) / scale) as u16;
 
-        let font_size = Vec2::new((xpixels / cols) as usize, (ypixels / rows) as usize);
+        let mut font_size = Vec2::new(
+            (xpixels / cols.max(1)) as usize,
+            (ypixels / rows.max(1)) as usize,
+        );
+        // Not every terminal reports its size in pixels.
+        if font_size.x == 0 || font_size.y == 0 {
+            font_size = Vec2::new(8, 16);
+        }
 
         debug!("Determined font size: {}x{}", font_size.x, font_size.y);
 
+        let protocol = match config.values().cover_protocol.unwrap_or_default() {
+            CoverProtocol::Auto => graphics::detect(),
+            protocol => protocol,
+        };
+        debug!("Drawing covers with {:?}", protocol);
+
         Self {
             queue,
             library,
+            protocol,
             ueberzug: RwLock::new(None),
             loading: Arc::new(RwLock::new(HashSet::new())),
             last_size: RwLock::new(Vec2::new(0, 0)),
             drawn_url: RwLock::new(None),
             font_size,
+            image: RwLock::new(None),
+            halfblocks: RwLock::new(None),
+            placed: RwLock::new(None),
+            pending: RwLock::new(None),
+            cb_sink,
+        }
+    }
+
+    /// Forget the image that was written to the terminal, after the screen was cleared.
+    pub fn reset(&mut self) {
+        *self.placed.write().unwrap() = None;
+        *self.pending.write().unwrap() = None;
+    }
+
+    /// The decoded cover at `url`, or None while it's downloaded.
+    fn image(&self, url: &str) -> Option<Arc<RgbaImage>> {
+        if let Some((image_url, image)) = self.image.read().unwrap().as_ref() {
+            if image_url == url {
+                return Some(image.clone());
+            }
+        }
+
+        let path = self.cache_path(url.to_string())?;
+        let image = match image::open(&path) {
+            Ok(image) => Arc::new(image.to_rgba8()),
+            Err(e) => {
+                error!("Failed to decode cover {:?}: {}", path, e);
+                return None;
+            }
+        };
+        *self.image.write().unwrap() = Some((url.to_string(), image.clone()));
+        Some(image)
+    }
+
+    /// Draw the cover with Unicode half blocks, centered in the area of `printer`.
+    fn draw_halfblocks(&self, url: String, printer: &Printer<'_, '_>) {
+        let mut halfblocks = self.halfblocks.write().unwrap();
+        let cached = halfblocks
+            .as_ref()
+            .is_some_and(|(cached_url, size, _)| *cached_url == url && *size == printer.size);
+        if !cached {
+            let Some(image) = self.image(&url) else {
+                return;
+            };
+            let image_size = Vec2::new(image.width() as usize, image.height() as usize);
+            let (cells, _) = graphics::fit(image_size, printer.size, self.font_size);
+            if cells.x == 0 || cells.y == 0 {
+                return;
+            }
+            let scaled = imageops::resize(
+                image.as_ref(),
+                cells.x as u32,
+                cells.y as u32 * 2,
+                FilterType::Triangle,
+            );
+            *halfblocks = Some((url, printer.size, scaled));
+        }
+
+        if let Some((_, _, image)) = halfblocks.as_ref() {
+            let cells = Vec2::new(image.width() as usize, image.height().div_ceil(2) as usize);
+            let offset = printer.size.saturating_sub(cells) / 2;
+            graphics::draw_halfblocks(printer, offset, image);
+        }
+    }
+
+    /// Write the cover to the terminal with a graphics protocol, centered in the area of `size`
+    /// cells at `offset`.
+    fn draw_graphics(&self, url: String, offset: Vec2, size: Vec2) {
+        if size.x <= 1 || size.y <= 1 {
+            return;
+        }
+
+        let target = (url, offset, size);
+        if self.placed.read().unwrap().as_ref() == Some(&target) {
+            return;
+        }
+
+        // Sixel and iTerm2 images are overwritten by text, so they're only written once the blank
+        // area was drawn by the previous frame. Images that were written before are cleared by
+        // redrawing the whole screen.
+        if self.pending.read().unwrap().as_ref() != Some(&target) {
+            let replaced = self.placed.write().unwrap().take().is_some();
+            *self.pending.write().unwrap() = Some(target);
+            if replaced && self.protocol != CoverProtocol::Kitty {
+                self.cb_sink.send(Box::new(Cursive::clear)).ok();
+            } else {
+                self.cb_sink.send(Box::new(Cursive::noop)).ok();
+            }
+            return;
+        }
+
+        let Some(image) = self.image(&target.0) else {
+            return;
+        };
+
+        let image_size = Vec2::new(image.width() as usize, image.height() as usize);
+        let (cells, pixels) = graphics::fit(image_size, size, self.font_size);
+        let scaled = imageops::resize(
+            image.as_ref(),
+            pixels.x as u32,
+            pixels.y as u32,
+            FilterType::Triangle,
+        );
+        let data = match self.protocol {
+            CoverProtocol::Kitty => graphics::kitty(&scaled, cells),
+            CoverProtocol::Iterm2 => graphics::iterm2(&scaled, cells),
+            _ => graphics::sixel(&scaled),
+        };
+
+        let position = offset + size.saturating_sub(cells) / 2;
+        if let Err(e) = graphics::write_at(position, &data) {
+            error!("Failed to write the cover: {}", e);
+            return;
         }
+
+        *self.pending.write().unwrap() = None;
+        *self.placed.write().unwrap() = Some(target);
     }
 
     fn draw_cover(&self, url: String, mut draw_offset: Vec2, draw_size: Vec2) {
@@ -136,12 +283,28 @@ impl CoverView {
     }
 
     fn clear_cover(&self) {
-        let mut drawn_url = self.drawn_url.write().unwrap();
-        *drawn_url = None;
+        *self.pending.write().unwrap() = None;
+        let placed = self.placed.write().unwrap().take().is_some();
 
-        let cmd = "{\"action\": \"remove\", \"identifier\": \"cover\"}\n";
-        if let Err(e) = self.run_ueberzug_cmd(cmd) {
-            error!("Failed to run Ueberzug: {}", e);
+        match self.protocol {
+            CoverProtocol::Kitty if placed => {
+                if let Err(e) = graphics::write_at(Vec2::zero(), &graphics::kitty_delete()) {
+                    error!("Failed to remove the cover: {}", e);
+                }
+            }
+            CoverProtocol::Sixel | CoverProtocol::Iterm2 if placed => {
+                self.cb_sink.send(Box::new(Cursive::clear)).ok();
+            }
+            CoverProtocol::Ueberzug | CoverProtocol::Auto => {
+                let mut drawn_url = self.drawn_url.write().unwrap();
+                *drawn_url = None;
+
+                let cmd = "{\"action\": \"remove\", \"identifier\": \"cover\"}\n";
+                if let Err(e) = self.run_ueberzug_cmd(cmd) {
+                    error!("Failed to run Ueberzug: {}", e);
+                }
+            }
+            _ => {}
         }
     }
 
@@ -206,10 +369,13 @@ impl View for CoverView {
 
         let cover_url = self.queue.get_current().and_then(|t| t.cover_url());
 
-        if let Some(url) = cover_url {
-            self.draw_cover(url, printer.offset, printer.size);
-        } else {
-            self.clear_cover();
+        match (cover_url, self.protocol) {
+            (Some(url), CoverProtocol::Ueberzug | CoverProtocol::Auto) => {
+                self.draw_cover(url, printer.offset, printer.size)
+            }
+            (Some(url), CoverProtocol::Halfblocks) => self.draw_halfblocks(url, printer),
+            (Some(url), _) => self.draw_graphics(url, printer.offset, printer.size),
+            (None, _) => self.clear_cover(),
         }
     }
 
@@ -0,0 +1,265 @@
+use std::collections::BTreeSet;
+use std::env;
+use std::fmt::Write as _;
+use std::io::{self, Cursor, Write};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use cursive::theme::{Color, ColorStyle};
+use cursive::{Printer, Vec2};
+use image::{ImageFormat, RgbaImage};
+
+use crate::config::CoverProtocol;
+
+/// The id of the image that is placed with the kitty graphics protocol. There's only one cover.
+const KITTY_IMAGE_ID: u32 = 1;
+
+/// The most base64 bytes in a single kitty graphics escape sequence.
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// Detect how the terminal can draw images. Terminals that don't support a graphics protocol get
+/// Ueberzug if it's installed and the session is local, and half blocks otherwise.
+pub fn detect() -> CoverProtocol {
+    let var = |name: &str| env::var(name).unwrap_or_default();
+    let term = var("TERM");
+    let program = var("TERM_PROGRAM");
+
+    if env::var_os("KITTY_WINDOW_ID").is_some()
+        || term.contains("kitty")
+        || term.contains("ghostty")
+        || program == "ghostty"
+    {
+        CoverProtocol::Kitty
+    } else if program == "iTerm.app" || program == "WezTerm" || var("LC_TERMINAL") == "iTerm2" {
+        CoverProtocol::Iterm2
+    } else if term.starts_with("foot")
+        || term.contains("mlterm")
+        || term.contains("sixel")
+        || program == "contour"
+    {
+        CoverProtocol::Sixel
+    } else if env::var_os("SSH_CONNECTION").is_none()
+        && (env::var_os("DISPLAY").is_some() || env::var_os("WAYLAND_DISPLAY").is_some())
+        && is_installed("ueberzug")
+    {
+        CoverProtocol::Ueberzug
+    } else {
+        CoverProtocol::Halfblocks
+    }
+}
+
+/// Return whether `program` is in one of the directories of `PATH`.
+fn is_installed(program: &str) -> bool {
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+/// The size in cells and the size in pixels at which an image of `size` pixels fits into `area`
+/// cells of `cell` pixels each. Images aren't scaled up.
+pub fn fit(size: Vec2, area: Vec2, cell: Vec2) -> (Vec2, Vec2) {
+    if size.x == 0 || size.y == 0 || cell.x == 0 || cell.y == 0 {
+        return (Vec2::zero(), Vec2::zero());
+    }
+    let ratio = f32::min(
+        f32::min(
+            (area.x * cell.x) as f32 / size.x as f32,
+            (area.y * cell.y) as f32 / size.y as f32,
+        ),
+        1.0,
+    );
+    let pixels = Vec2::new(
+        ((size.x as f32 * ratio) as usize).max(1),
+        ((size.y as f32 * ratio) as usize).max(1),
+    );
+    let cells = Vec2::new(
+        pixels.x.div_ceil(cell.x).min(area.x),
+        pixels.y.div_ceil(cell.y).min(area.y),
+    );
+    (cells, pixels)
+}
+
+/// Write `data` to the terminal with the cursor at `position`, and put the cursor back.
+pub fn write_at(position: Vec2, data: &str) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    write!(
+        stdout,
+        "\x1b7\x1b[{};{}H{data}\x1b8",
+        position.y + 1,
+        position.x + 1
+    )?;
+    stdout.flush()
+}
+
+fn png(image: &RgbaImage) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    if let Err(e) = image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png) {
+        log::error!("could not encode the cover: {e}");
+    }
+    bytes
+}
+
+/// The kitty graphics escape sequences that show `image` stretched over `cells`, below the text.
+pub fn kitty(image: &RgbaImage, cells: Vec2) -> String {
+    let payload = BASE64.encode(png(image));
+    let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+
+    let mut output = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(index + 1 < chunks.len());
+        if index == 0 {
+            write!(
+                output,
+                "\x1b_Ga=T,f=100,i={KITTY_IMAGE_ID},c={},r={},C=1,z=-1,q=2,m={more};",
+                cells.x, cells.y
+            )
+            .ok();
+        } else {
+            write!(output, "\x1b_Gm={more};").ok();
+        }
+        output.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+        output.push_str("\x1b\\");
+    }
+    output
+}
+
+/// The kitty graphics escape sequence that removes the image shown by [kitty].
+pub fn kitty_delete() -> String {
+    format!("\x1b_Ga=d,d=I,i={KITTY_IMAGE_ID},q=2\x1b\\")
+}
+
+/// The iTerm2 inline image escape sequence that shows `image` in `cells`.
+pub fn iterm2(image: &RgbaImage, cells: Vec2) -> String {
+    let bytes = png(image);
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+        bytes.len(),
+        cells.x,
+        cells.y,
+        BASE64.encode(&bytes)
+    )
+}
+
+/// The index of the color closest to `rgb` in a palette of 6 levels per channel.
+fn palette_index([r, g, b]: [u8; 3]) -> usize {
+    let level = |channel: u8| (channel as usize * 5 + 127) / 255;
+    level(r) * 36 + level(g) * 6 + level(b)
+}
+
+/// The sixel escape sequence that shows `image` at its size in pixels, with a palette of 216
+/// colors.
+pub fn sixel(image: &RgbaImage) -> String {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let indexes: Vec<Option<usize>> = image
+        .pixels()
+        .map(|pixel| (pixel[3] >= 128).then(|| palette_index([pixel[0], pixel[1], pixel[2]])))
+        .collect();
+
+    let mut output = format!("\x1bPq\"1;1;{width};{height}");
+    let used: BTreeSet<usize> = indexes.iter().flatten().copied().collect();
+    for &index in &used {
+        let percent = |level: usize| level * 100 / 5;
+        write!(
+            output,
+            "#{index};2;{};{};{}",
+            percent(index / 36),
+            percent(index / 6 % 6),
+            percent(index % 6)
+        )
+        .ok();
+    }
+
+    for band in (0..height).step_by(6) {
+        let rows = band..(band + 6).min(height);
+        let colors: BTreeSet<usize> = rows
+            .clone()
+            .flat_map(|y| indexes[y * width..(y + 1) * width].iter().flatten())
+            .copied()
+            .collect();
+
+        for color in colors {
+            write!(output, "#{color}").ok();
+            let mut run: Option<(char, usize)> = None;
+            for x in 0..width {
+                let bits = rows
+                    .clone()
+                    .filter(|y| indexes[y * width + x] == Some(color))
+                    .fold(0, |bits, y| bits | 1 << (y - band));
+                let sixel = char::from(63 + bits as u8);
+                run = match run {
+                    Some((previous, count)) if previous == sixel => Some((sixel, count + 1)),
+                    Some(previous) => {
+                        push_run(&mut output, previous);
+                        Some((sixel, 1))
+                    }
+                    None => Some((sixel, 1)),
+                };
+            }
+            if let Some(run) = run {
+                push_run(&mut output, run);
+            }
+            // Go back to the start of the band for the next color.
+            output.push('$');
+        }
+        output.push('-');
+    }
+
+    output.push_str("\x1b\\");
+    output
+}
+
+/// Append `count` times `sixel` to `output`, with a repeat introducer if that's shorter.
+fn push_run(output: &mut String, (sixel, count): (char, usize)) {
+    if count > 3 {
+        write!(output, "!{count}{sixel}").ok();
+    } else {
+        output.extend(std::iter::repeat_n(sixel, count));
+    }
+}
+
+/// Draw `image` with upper half blocks, two pixels per cell. The image should be twice as high as
+/// the amount of rows it's drawn on.
+pub fn draw_halfblocks(printer: &Printer<'_, '_>, offset: Vec2, image: &RgbaImage) {
+    let color = |x: u32, y: u32| {
+        image
+            .get_pixel_checked(x, y)
+            .map(|pixel| Color::Rgb(pixel[0], pixel[1], pixel[2]))
+    };
+
+    for y in 0..image.height().div_ceil(2) {
+        for x in 0..image.width() {
+            let Some(top) = color(x, y * 2) else {
+                continue;
+            };
+            let bottom = color(x, y * 2 + 1).unwrap_or(top);
+            printer.with_color(ColorStyle::new(top, bottom), |printer| {
+                printer.print(offset + (x as usize, y as usize), "▀");
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fits_image_into_cells() {
+        let (cells, pixels) = fit(Vec2::new(640, 640), Vec2::new(80, 20), Vec2::new(8, 16));
+        assert_eq!(pixels, Vec2::new(320, 320));
+        assert_eq!(cells, Vec2::new(40, 20));
+
+        let (cells, pixels) = fit(Vec2::new(64, 64), Vec2::new(80, 20), Vec2::new(8, 16));
+        assert_eq!(pixels, Vec2::new(64, 64));
+        assert_eq!(cells, Vec2::new(8, 4));
+    }
+
+    #[test]
+    fn encodes_sixel() {
+        let image = RgbaImage::from_pixel(8, 2, image::Rgba([255, 0, 0, 255]));
+        assert_eq!(
+            sixel(&image),
+            "\x1bPq\"1;1;8;2#180;2;100;0;0#180!8B$-\x1b\\"
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use cursive::theme::Effect;
 use cursive::utils::markup::StyledString;
@@ -7,18 +7,22 @@ use cursive::views::{ScrollView, TextView};
 use cursive::Cursive;
 use ncspot::CONFIGURATION_FILE_NAME;
 
-use crate::command::{Command, MoveAmount, MoveMode};
-use crate::commands::CommandResult;
+use crate::command::Command;
+use crate::commands::{BindingContext, CommandResult};
 use crate::config::config_path;
 use crate::traits::ViewExt;
-use cursive::view::scroll::Scroller;
+use crate::ui::scroll::scroll;
 
 pub struct HelpView {
     view: ScrollView<TextView>,
 }
 
 impl HelpView {
-    pub fn new(bindings: HashMap<String, Vec<Command>>) -> Self {
+    pub fn new(
+        bindings: HashMap<String, Vec<Command>>,
+        context_bindings: HashMap<BindingContext, HashMap<String, Vec<Command>>>,
+        aliases: BTreeMap<String, String>,
+    ) -> Self {
         let mut text = StyledString::styled("Keybindings\n\n", Effect::Bold);
 
         let note = format!(
@@ -29,6 +33,29 @@ impl HelpView {
         );
         text.append(StyledString::styled(note, Effect::Italic));
 
+        Self::append_bindings(&mut text, &bindings);
+
+        let contexts: BTreeMap<_, _> = context_bindings.into_iter().collect();
+        for (context, bindings) in contexts {
+            let heading = format!("\nKeybindings ({context})\n\n");
+            text.append(StyledString::styled(heading, Effect::Bold));
+            Self::append_bindings(&mut text, &bindings);
+        }
+
+        if !aliases.is_empty() {
+            text.append(StyledString::styled("\nAliases\n\n", Effect::Bold));
+            for (name, commands) in aliases {
+                text.append(format!("{name} -> {commands}\n"));
+            }
+        }
+
+        Self {
+            view: ScrollView::new(TextView::new(text)),
+        }
+    }
+
+    /// Append a line for every keybinding in `bindings`, sorted by key.
+    fn append_bindings(text: &mut StyledString, bindings: &HashMap<String, Vec<Command>>) {
         let mut keys: Vec<&String> = bindings.keys().collect();
         keys.sort();
 
@@ -45,10 +72,6 @@ impl HelpView {
             );
             text.append(binding);
         }
-
-        Self {
-            view: ScrollView::new(TextView::new(text)),
-        }
     }
 }
 
@@ -65,41 +88,8 @@ impl ViewExt for HelpView {
         match cmd {
             Command::Help => Ok(CommandResult::Consumed(None)),
             Command::Move(mode, amount) => {
-                let scroller = self.view.get_scroller_mut();
-                let viewport = scroller.content_viewport();
-                match mode {
-                    MoveMode::Up => {
-                        match amount {
-                            MoveAmount::Extreme => {
-                                self.view.scroll_to_top();
-                            }
-                            MoveAmount::Float(scale) => {
-                                let amount = (viewport.height() as f32) * scale;
-                                scroller
-                                    .scroll_to_y(viewport.top().saturating_sub(amount as usize));
-                            }
-                            MoveAmount::Integer(amount) => scroller
-                                .scroll_to_y(viewport.top().saturating_sub(*amount as usize)),
-                        };
-                        Ok(CommandResult::Consumed(None))
-                    }
-                    MoveMode::Down => {
-                        match amount {
-                            MoveAmount::Extreme => {
-                                self.view.scroll_to_bottom();
-                            }
-                            MoveAmount::Float(scale) => {
-                                let amount = (viewport.height() as f32) * scale;
-                                scroller
-                                    .scroll_to_y(viewport.bottom().saturating_add(amount as usize));
-                            }
-                            MoveAmount::Integer(amount) => scroller
-                                .scroll_to_y(viewport.bottom().saturating_add(*amount as usize)),
-                        };
-                        Ok(CommandResult::Consumed(None))
-                    }
-                    _ => Ok(CommandResult::Consumed(None)),
-                }
+                scroll(&mut self.view, mode, amount);
+                Ok(CommandResult::Consumed(None))
             }
             _ => Ok(CommandResult::Ignored),
         }
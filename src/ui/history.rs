@@ -0,0 +1,71 @@
+use std::sync::{Arc, RwLock};
+
+use cursive::view::{View, ViewWrapper};
+use cursive::{Cursive, Vec2};
+
+use crate::command::Command;
+use crate::commands::CommandResult;
+use crate::library::Library;
+use crate::play_history::HistoryEntry;
+use crate::queue::Queue;
+use crate::traits::ViewExt;
+use crate::ui::listview::ListView;
+
+/// The play history, most recently played first.
+pub struct HistoryView {
+    list: ListView<HistoryEntry>,
+    content: Arc<RwLock<Vec<HistoryEntry>>>,
+    /// The amount of history entries that `content` was built from.
+    shown: usize,
+    library: Arc<Library>,
+}
+
+impl HistoryView {
+    pub fn new(queue: Arc<Queue>, library: Arc<Library>) -> Self {
+        let content = Arc::new(RwLock::new(Vec::new()));
+        let mut view = Self {
+            list: ListView::new(content.clone(), queue, library.clone()),
+            content,
+            shown: 0,
+            library,
+        };
+        view.refresh();
+        view
+    }
+
+    /// Rebuild the list content if items were played since it was last built.
+    fn refresh(&mut self) {
+        let len = self.library.play_history.len();
+        if len == self.shown {
+            return;
+        }
+        self.shown = len;
+
+        let mut entries = self.library.play_history.entries();
+        entries.reverse();
+        *self.content.write().unwrap() = entries;
+    }
+}
+
+impl ViewWrapper for HistoryView {
+    wrap_impl!(self.list: ListView<HistoryEntry>);
+
+    fn wrap_layout(&mut self, size: Vec2) {
+        self.refresh();
+        self.list.layout(size);
+    }
+}
+
+impl ViewExt for HistoryView {
+    fn title(&self) -> String {
+        "History".to_string()
+    }
+
+    fn title_sub(&self) -> String {
+        format!("{} plays", self.shown)
+    }
+
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        self.list.on_command(s, cmd)
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use cursive::event::{Event, EventResult, Key};
+use cursive::traits::Nameable;
+use cursive::view::ViewWrapper;
+use cursive::views::{Dialog, TextView};
+use cursive::Cursive;
+
+use crate::application::UserData;
+use crate::command::Command;
+use crate::commands::BindingContext;
+
+/// The name of the popup that is shown while a key sequence is typed.
+const NAME: &str = "keysequence";
+
+/// A sequence of keys and the commands it runs, i.e. `g g` or `<leader> p s`.
+#[derive(Clone, Debug)]
+pub struct KeySequence {
+    /// The keys as they were configured, used for display.
+    pub keys: Vec<String>,
+    pub events: Vec<Event>,
+    pub commands: Vec<Command>,
+}
+
+/// The key sequences that apply globally and in every [BindingContext].
+pub struct Keymap {
+    global: Arc<Vec<KeySequence>>,
+    /// The sequences of contexts with their own keybindings, including the inherited global ones.
+    contexts: HashMap<BindingContext, Arc<Vec<KeySequence>>>,
+    timeout: Duration,
+}
+
+impl Keymap {
+    pub fn new(
+        global: Arc<Vec<KeySequence>>,
+        contexts: HashMap<BindingContext, Arc<Vec<KeySequence>>>,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            global,
+            contexts,
+            timeout,
+        }
+    }
+
+    /// All sequences, of all contexts.
+    pub fn all_sequences(&self) -> impl Iterator<Item = &KeySequence> {
+        self.global.iter().chain(
+            self.contexts
+                .values()
+                .flat_map(|sequences| sequences.iter()),
+        )
+    }
+
+    /// The sequences that apply in `context`.
+    fn sequences(&self, context: Option<BindingContext>) -> Arc<Vec<KeySequence>> {
+        context
+            .and_then(|context| self.contexts.get(&context))
+            .unwrap_or(&self.global)
+            .clone()
+    }
+
+    /// Handle the key `event` in `context`: run the commands bound to it, or start a key
+    /// sequence if it is the first key of one or a count prefix.
+    pub fn press(&self, s: &mut Cursive, context: Option<BindingContext>, event: Event) {
+        let sequences = self.sequences(context);
+        let mut matching = sequences
+            .iter()
+            .filter(|sequence| sequence.events[0] == event);
+
+        match (matching.next(), matching.next()) {
+            (Some(sequence), None) if sequence.events.len() == 1 => {
+                finish(s, Outcome::Run(sequence.commands.clone()))
+            }
+            // Digits that aren't bound otherwise start a count prefix, as in "5j".
+            (None, _) if !matches!(event, Event::Char('1'..='9')) => {}
+            _ => KeySequenceView::start(s, sequences, self.timeout, event),
+        }
+    }
+}
+
+/// What to do after a key was pressed.
+enum Outcome {
+    /// Wait for more keys.
+    Pending,
+    /// The sequence is complete, run its commands.
+    Run(Vec<Command>),
+    /// The keys don't match any sequence.
+    Cancel,
+}
+
+/// A which-key style popup that collects a count prefix and the keys of a sequence, and lists the
+/// keys that can follow.
+pub struct KeySequenceView {
+    sequences: Arc<Vec<KeySequence>>,
+    timeout: Duration,
+    count: Option<u32>,
+    typed: Vec<Event>,
+    /// Incremented on every key press, so timeouts of earlier presses can be told apart.
+    generation: usize,
+    view: Dialog,
+}
+
+impl KeySequenceView {
+    /// Start a key sequence with the first key `event`, or with the first digit of a count prefix.
+    pub fn start(
+        s: &mut Cursive,
+        sequences: Arc<Vec<KeySequence>>,
+        timeout: Duration,
+        event: Event,
+    ) {
+        let mut view = Self {
+            sequences,
+            timeout,
+            count: None,
+            typed: Vec::new(),
+            generation: 0,
+            view: Dialog::new(),
+        };
+
+        match view.press(event) {
+            Outcome::Pending => {
+                view.update();
+                let generation = view.generation;
+                s.add_layer(view.with_name(NAME));
+                schedule_timeout(s, generation, timeout);
+            }
+            outcome => finish(s, outcome),
+        }
+    }
+
+    fn press(&mut self, event: Event) -> Outcome {
+        self.generation += 1;
+
+        if let Event::Char(c @ '0'..='9') = event {
+            if self.typed.is_empty() && (self.count.is_some() || c != '0') {
+                let digit = c.to_digit(10).unwrap_or_default();
+                self.count = Some(
+                    self.count
+                        .unwrap_or_default()
+                        .saturating_mul(10)
+                        .saturating_add(digit),
+                );
+                return Outcome::Pending;
+            }
+        }
+
+        self.typed.push(event);
+        let mut exact = None;
+        let mut longer = false;
+        for sequence in self.candidates() {
+            if sequence.events.len() == self.typed.len() {
+                exact = Some(sequence.commands.clone());
+            } else {
+                longer = true;
+            }
+        }
+
+        match (exact, longer) {
+            (_, true) => Outcome::Pending,
+            (Some(commands), false) => Outcome::Run(self.counted(commands)),
+            (None, false) => Outcome::Cancel,
+        }
+    }
+
+    /// Called when no key was pressed for a while after the key press `generation`. Returns
+    /// `None` if a key was pressed since then.
+    fn expire(&mut self, generation: usize) -> Option<Outcome> {
+        // A count prefix waits for its command indefinitely.
+        if generation != self.generation || self.typed.is_empty() {
+            return None;
+        }
+
+        let exact = self
+            .candidates()
+            .find(|sequence| sequence.events.len() == self.typed.len())
+            .map(|sequence| sequence.commands.clone());
+        Some(match exact {
+            Some(commands) => Outcome::Run(self.counted(commands)),
+            None => Outcome::Cancel,
+        })
+    }
+
+    /// The sequences that start with the keys that were typed so far.
+    fn candidates(&self) -> impl Iterator<Item = &KeySequence> {
+        self.sequences
+            .iter()
+            .filter(|sequence| sequence.events.starts_with(&self.typed))
+    }
+
+    /// Apply the count prefix to `commands`.
+    fn counted(&self, commands: Vec<Command>) -> Vec<Command> {
+        match self.count {
+            Some(count) => commands
+                .into_iter()
+                .flat_map(|command| command.with_count(count))
+                .collect(),
+            None => commands,
+        }
+    }
+
+    /// Show the typed keys and the keys that can follow them.
+    fn update(&mut self) {
+        let mut title: Vec<String> = self.count.iter().map(u32::to_string).collect();
+        if let Some(sequence) = self.candidates().next() {
+            title.extend(sequence.keys[..self.typed.len()].iter().cloned());
+        }
+
+        let mut continuations = BTreeMap::new();
+        for sequence in self.candidates() {
+            if let Some(key) = sequence.keys.get(self.typed.len()) {
+                let description = if sequence.keys.len() > self.typed.len() + 1 {
+                    "+more".to_string()
+                } else {
+                    sequence
+                        .commands
+                        .iter()
+                        .map(Command::to_string)
+                        .collect::<Vec<_>>()
+                        .join("; ")
+                };
+                continuations.entry(key.clone()).or_insert(description);
+            }
+        }
+
+        let content = if self.typed.is_empty() {
+            "Press a key to repeat its command".to_string()
+        } else {
+            let width = continuations.keys().map(String::len).max().unwrap_or(0);
+            continuations
+                .iter()
+                .map(|(key, description)| format!("{key:width$}  {description}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        self.view = Dialog::around(TextView::new(content)).title(title.join(" "));
+    }
+}
+
+impl ViewWrapper for KeySequenceView {
+    wrap_impl!(self.view: Dialog);
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        // Ignore mouse movements and the like, which aren't part of a key sequence.
+        if matches!(event, Event::Mouse { .. } | Event::Refresh) {
+            return EventResult::Consumed(None);
+        }
+
+        let outcome = match event {
+            Event::Key(Key::Esc) => Outcome::Cancel,
+            event => self.press(event),
+        };
+
+        match outcome {
+            Outcome::Pending => {
+                self.update();
+                let generation = self.generation;
+                let timeout = self.timeout;
+                EventResult::with_cb(move |s| schedule_timeout(s, generation, timeout))
+            }
+            outcome => EventResult::with_cb_once(move |s| finish(s, outcome)),
+        }
+    }
+}
+
+/// Close the popup after `timeout`, unless another key was pressed after the key press
+/// `generation`.
+fn schedule_timeout(s: &mut Cursive, generation: usize, timeout: Duration) {
+    let sink = s.cb_sink().clone();
+    thread::spawn(move || {
+        thread::sleep(timeout);
+        let _ = sink.send(Box::new(move |s| {
+            let outcome = s
+                .call_on_name(NAME, |view: &mut KeySequenceView| view.expire(generation))
+                .flatten();
+            if let Some(outcome) = outcome {
+                finish(s, outcome);
+            }
+        }));
+    });
+}
+
+/// Close the popup and run the commands of a completed sequence.
+fn finish(s: &mut Cursive, outcome: Outcome) {
+    if let Some(position) = s.screen_mut().find_layer_from_name(NAME) {
+        s.screen_mut().remove_layer(position);
+    }
+
+    if let Outcome::Run(commands) = outcome {
+        if let Some(data) = s.user_data::<UserData>().cloned() {
+            for command in commands {
+                data.cmd.handle(s, command);
+            }
+        }
+    }
+}
@@ -14,19 +14,72 @@ use cursive::{Cursive, Printer};
 use unicode_width::UnicodeWidthStr;
 
 use crate::application::UserData;
-use crate::command::{self, Command, JumpMode};
-use crate::commands::CommandResult;
-use crate::config::{self, Config};
+use crate::command::{self, Command, JumpMode, PaneMode};
+use crate::commands::{BindingContext, CommandResult};
+use crate::completion::Completer;
+use crate::config::{self, Config, SplitOrientation};
 use crate::events;
 use crate::ext_traits::CursiveExt;
 use crate::traits::{IntoBoxedViewExt, ViewExt};
+use crate::ui::cmdline::{CommandLine, History, CMDLINE_HISTORY_FILE_NAME};
+
+/// The area that a screen is drawn in, including its title.
+#[derive(Debug, PartialEq)]
+struct Pane {
+    screen: String,
+    offset: Vec2,
+    size: Vec2,
+}
+
+/// Divide an area of `width` by `height` evenly between `screens` in the given `orientation`.
+fn split(
+    screens: Vec<String>,
+    orientation: &SplitOrientation,
+    width: usize,
+    height: usize,
+) -> Vec<Pane> {
+    let count = screens.len().max(1);
+    screens
+        .into_iter()
+        .enumerate()
+        .map(|(index, screen)| match orientation {
+            SplitOrientation::Horizontal => {
+                // Panes side by side are separated by a line.
+                let available = width.saturating_sub(count - 1);
+                let start = index * available / count;
+                let end = (index + 1) * available / count;
+                Pane {
+                    screen,
+                    offset: Vec2::new(start + index, 0),
+                    size: Vec2::new(end - start, height),
+                }
+            }
+            SplitOrientation::Vertical => {
+                let start = index * height / count;
+                let end = (index + 1) * height / count;
+                Pane {
+                    screen,
+                    offset: Vec2::new(0, start),
+                    size: Vec2::new(width, end - start),
+                }
+            }
+        })
+        .collect()
+}
 
 pub struct Layout {
     screens: HashMap<String, Box<dyn ViewExt>>,
     stack: HashMap<String, Vec<Box<dyn ViewExt>>>,
     statusbar: Box<dyn View>,
+    /// The queue entries and key hints that are shown above the statusbar in the mini-player.
+    miniplayer: Box<dyn View>,
+    /// Whether the screens are hidden in favour of the mini-player.
+    mini: bool,
     focus: Option<String>,
-    cmdline: EditView,
+    /// The screens that are shown next to each other while one of them is focused.
+    panes: Vec<String>,
+    orientation: SplitOrientation,
+    cmdline: CommandLine,
     cmdline_focus: bool,
     result: Result<Option<String>, String>,
     result_time: Option<SystemTime>,
@@ -37,11 +90,13 @@ pub struct Layout {
 }
 
 impl Layout {
-    pub fn new<T: IntoBoxedView>(
+    pub fn new<T: IntoBoxedView, M: IntoBoxedView>(
         status: T,
+        miniplayer: M,
         ev: &events::EventManager,
         theme: Theme,
         configuration: Arc<Config>,
+        completer: Completer,
     ) -> Self {
         let style = ColorStyle::new(
             ColorType::Color(*theme.palette.custom("cmdline_bg").unwrap()),
@@ -89,12 +144,18 @@ impl Layout {
             event_manager.trigger();
         });
 
-        Self {
+        let history = History::load(config::cache_path(CMDLINE_HISTORY_FILE_NAME));
+
+        let mut layout = Self {
             screens: HashMap::new(),
             stack: HashMap::new(),
             statusbar: status.into_boxed_view(),
+            miniplayer: miniplayer.into_boxed_view(),
+            mini: false,
             focus: None,
-            cmdline: command_line_input,
+            panes: Vec::new(),
+            orientation: SplitOrientation::default(),
+            cmdline: CommandLine::new(command_line_input, completer, history),
             cmdline_focus: false,
             result: Ok(None),
             result_time: None,
@@ -102,7 +163,9 @@ impl Layout {
             ev: ev.clone(),
             theme,
             configuration,
-        }
+        };
+        layout.reload_split();
+        layout
     }
 
     pub fn enable_cmdline(&mut self, prefix: char) {
@@ -144,7 +207,17 @@ impl Layout {
             view.on_leave();
         }
 
+        // A screen that isn't part of the split layout replaces the focused pane.
         let s = id.into();
+        if !self.panes.contains(&s) {
+            if let Some(pane) = self
+                .panes
+                .iter_mut()
+                .find(|pane| self.focus.as_ref() == Some(*pane))
+            {
+                *pane = s.clone();
+            }
+        }
         self.focus = Some(s);
         self.cmdline_focus = false;
 
@@ -152,6 +225,80 @@ impl Layout {
         self.ev.trigger();
     }
 
+    /// Arrange the screens as configured in `[split]`.
+    pub fn reload_split(&mut self) {
+        let split = self
+            .configuration
+            .values()
+            .split
+            .clone()
+            .unwrap_or_default();
+        self.panes.clear();
+        for pane in split.panes.unwrap_or_default() {
+            if !self.panes.contains(&pane) {
+                self.panes.push(pane);
+            }
+        }
+        self.orientation = split.orientation.unwrap_or_default();
+    }
+
+    /// Focus the next or previous pane of the split layout.
+    fn cycle_pane(&mut self, mode: &PaneMode) {
+        let screens = self.visible_screens();
+        let count = screens.len();
+        let index = self
+            .focus
+            .as_ref()
+            .and_then(|focus| screens.iter().position(|screen| screen == focus));
+        if let Some(index) = index {
+            let next = match mode {
+                PaneMode::Next => (index + 1) % count,
+                PaneMode::Previous => (index + count - 1) % count,
+            };
+            self.set_screen(screens[next].clone());
+        }
+    }
+
+    /// The screens that are shown: all panes of the split layout if one of them is focused, and
+    /// only the focused screen otherwise.
+    fn visible_screens(&self) -> Vec<String> {
+        match &self.focus {
+            Some(focus) if self.panes.contains(focus) => self
+                .panes
+                .iter()
+                .filter(|pane| self.screens.contains_key(*pane))
+                .cloned()
+                .collect(),
+            Some(focus) => vec![focus.clone()],
+            None => Vec::new(),
+        }
+    }
+
+    /// Where the visible screens are drawn, in an area of `width` by `height`.
+    fn panes(&self, width: usize, height: usize) -> Vec<Pane> {
+        split(self.visible_screens(), &self.orientation, width, height)
+    }
+
+    /// Show the mini-player instead of the screens, or leave it.
+    pub fn set_mini(&mut self, mini: bool) {
+        self.mini = mini;
+    }
+
+    /// The height of the command line and the result of the last command.
+    fn cmdline_height(&self) -> usize {
+        let cmdline_visible = self.cmdline.get_content().len() > 0;
+        let mut cmdline_height = usize::from(cmdline_visible);
+        if self
+            .get_result()
+            .as_ref()
+            .map(Option::is_some)
+            .unwrap_or(true)
+        {
+            cmdline_height += 1;
+        }
+        cmdline_height
+    }
+
     pub fn set_result(&mut self, result: Result<Option<String>, String>) {
         self.result = result;
         self.result_time = Some(SystemTime::now());
@@ -191,11 +338,9 @@ impl Layout {
         self.get_focussed_stack_mut().map(|stack| stack.pop());
     }
 
-    #[allow(clippy::borrowed_box)]
-    fn get_current_screen(&self) -> Option<&Box<dyn ViewExt>> {
-        self.focus
-            .as_ref()
-            .and_then(|focus| self.screens.get(focus))
+    /// The keybinding context of the focused view.
+    pub fn binding_context(&self) -> Option<BindingContext> {
+        self.get_top_view().and_then(|view| view.binding_context())
     }
 
     fn get_focussed_stack_mut(&mut self) -> Option<&mut Vec<Box<dyn ViewExt>>> {
@@ -207,43 +352,83 @@ impl Layout {
         }
     }
 
-    fn get_focussed_stack(&self) -> Option<&Vec<Box<dyn ViewExt>>> {
-        self.focus.as_ref().and_then(|focus| self.stack.get(focus))
+    /// The view on top of the stack of `screen`, or the screen itself.
+    #[allow(clippy::borrowed_box)]
+    fn top_view(&self, screen: &str) -> Option<&Box<dyn ViewExt>> {
+        self.stack
+            .get(screen)
+            .and_then(|stack| stack.last())
+            .or_else(|| self.screens.get(screen))
     }
 
-    fn is_current_stack_empty(&self) -> bool {
-        self.get_focussed_stack()
-            .map(|s| s.is_empty())
-            .unwrap_or(false)
+    fn top_view_mut(&mut self, screen: &str) -> Option<&mut Box<dyn ViewExt>> {
+        let last_view = self
+            .stack
+            .get_mut(screen)
+            .filter(|stack| !stack.is_empty())
+            .and_then(|stack| stack.last_mut());
+        if last_view.is_some() {
+            last_view
+        } else {
+            self.screens.get_mut(screen)
+        }
     }
 
     #[allow(clippy::borrowed_box)]
     fn get_top_view(&self) -> Option<&Box<dyn ViewExt>> {
-        let focussed_stack = self.get_focussed_stack();
-        if focussed_stack.map(|s| s.len()).unwrap_or_default() > 0 {
-            focussed_stack.unwrap().last()
-        } else if let Some(id) = &self.focus {
-            self.screens.get(id)
-        } else {
-            None
-        }
+        self.focus.as_ref().and_then(|focus| self.top_view(focus))
     }
 
     fn get_current_view_mut(&mut self) -> Option<&mut Box<dyn ViewExt>> {
-        if let Some(focus) = &self.focus {
-            let last_view = self
-                .stack
-                .get_mut(focus)
-                .filter(|stack| !stack.is_empty())
-                .and_then(|stack| stack.last_mut());
-            if last_view.is_some() {
-                last_view
-            } else {
-                self.screens.get_mut(focus)
-            }
-        } else {
-            None
+        let focus = self.focus.clone()?;
+        self.top_view_mut(&focus)
+    }
+
+    /// Draw the title and the top view of `pane`.
+    fn draw_pane(&self, printer: &Printer<'_, '_>, pane: &Pane) {
+        let Some(view) = self.top_view(&pane.screen) else {
+            return;
+        };
+        let focused = self.focus.as_ref() == Some(&pane.screen);
+
+        // back button + title
+        if self
+            .stack
+            .get(&pane.screen)
+            .is_some_and(|stack| !stack.is_empty())
+        {
+            let screen_title = self
+                .screens
+                .get(&pane.screen)
+                .map(|screen| screen.title())
+                .unwrap_or_default();
+            printer.with_color(ColorStyle::title_secondary(), |printer| {
+                printer.print((1, 0), &format!("< {screen_title}"));
+            });
         }
+
+        // view title, the titles of unfocused panes are dimmed
+        let title_style = if focused {
+            ColorStyle::title_primary()
+        } else {
+            ColorStyle::title_secondary()
+        };
+        printer.with_color(title_style, |printer| {
+            let offset = HAlign::Center.get_offset(view.title().width(), printer.size.x);
+            printer.print((offset, 0), &view.title());
+        });
+
+        printer.with_color(ColorStyle::secondary(), |printer| {
+            let offset = HAlign::Right.get_offset(view.title_sub().width(), printer.size.x);
+            printer.print((offset, 0), &view.title_sub());
+        });
+
+        // screen content
+        let printer = &printer
+            .offset((0, 1))
+            .cropped((printer.size.x, printer.size.y.saturating_sub(1)))
+            .focused(focused);
+        view.draw(printer);
     }
 
     /// Propagate the given event to the command line.
@@ -255,6 +440,16 @@ impl Layout {
             self.clear_cmdline();
         }
 
+        // List the completions of the typed word, if there are several.
+        let completions = self.cmdline.completions();
+        if !completions.is_empty() {
+            let words: Vec<&str> = completions
+                .iter()
+                .filter_map(|completion| completion.split_whitespace().last())
+                .collect();
+            self.set_result(Ok(Some(words.join("  "))));
+        }
+
         if is_left_right_event {
             EventResult::consumed()
         } else {
@@ -266,43 +461,22 @@ impl Layout {
 impl View for Layout {
     fn draw(&self, printer: &Printer<'_, '_>) {
         let result = self.get_result();
-
         let cmdline_visible = self.cmdline.get_content().len() > 0;
-        let mut cmdline_height = usize::from(cmdline_visible);
-        if result.as_ref().map(Option::is_some).unwrap_or(true) {
-            cmdline_height += 1;
-        }
-
-        let screen_title = self
-            .get_current_screen()
-            .map(|screen| screen.title())
-            .unwrap_or_default();
+        let cmdline_height = self.cmdline_height();
 
-        if let Some(view) = self.get_top_view() {
-            // back button + title
-            if !self.is_current_stack_empty() {
-                printer.with_color(ColorStyle::title_secondary(), |printer| {
-                    printer.print((1, 0), &format!("< {screen_title}"));
-                });
+        let height = printer.size.y.saturating_sub(2 + cmdline_height);
+        if self.mini {
+            self.miniplayer
+                .draw(&printer.cropped((printer.size.x, height)));
+        } else {
+            for (index, pane) in self.panes(printer.size.x, height).iter().enumerate() {
+                if index > 0 && self.orientation == SplitOrientation::Horizontal {
+                    printer.with_color(ColorStyle::secondary(), |printer| {
+                        printer.print_vline((pane.offset.x - 1, 0), height, "│");
+                    });
+                }
+                self.draw_pane(&printer.offset(pane.offset).cropped(pane.size), pane);
             }
-
-            // view title
-            printer.with_color(ColorStyle::title_primary(), |printer| {
-                let offset = HAlign::Center.get_offset(view.title().width(), printer.size.x);
-                printer.print((offset, 0), &view.title());
-            });
-
-            printer.with_color(ColorStyle::secondary(), |printer| {
-                let offset = HAlign::Right.get_offset(view.title_sub().width(), printer.size.x);
-                printer.print((offset, 0), &view.title_sub());
-            });
-
-            // screen content
-            let printer = &printer
-                .offset((0, 1))
-                .cropped((printer.size.x, printer.size.y - 3 - cmdline_height))
-                .focused(true);
-            view.draw(printer);
         }
 
         self.statusbar
@@ -336,8 +510,15 @@ impl View for Layout {
 
         self.cmdline.layout(Vec2::new(size.x, 1));
 
-        if let Some(view) = self.get_current_view_mut() {
-            view.layout(Vec2::new(size.x, size.y - 3));
+        let height = size.y.saturating_sub(2 + self.cmdline_height());
+        if self.mini {
+            self.miniplayer.layout(Vec2::new(size.x, height));
+            return;
+        }
+        for pane in self.panes(size.x, height) {
+            if let Some(view) = self.top_view_mut(&pane.screen) {
+                view.layout(Vec2::new(pane.size.x, pane.size.y.saturating_sub(1)));
+            }
         }
     }
 
@@ -392,30 +573,7 @@ impl View for Layout {
                 event: mouse_event,
                 ..
             } => {
-                // Handle mouse events in the command/jump area.
-                if position.y == 0 {
-                    if mouse_event == MouseEvent::Press(MouseButton::Left)
-                        && !self.is_current_stack_empty()
-                        && position.x
-                            < self
-                                .get_current_screen()
-                                .map(|screen| screen.title())
-                                .unwrap_or_default()
-                                .len()
-                                + 3
-                    {
-                        self.pop_view();
-                    }
-                    return EventResult::consumed();
-                }
-
-                let result = self.get_result();
-
-                let cmdline_visible = self.cmdline.get_content().len() > 0;
-                let mut cmdline_height = usize::from(cmdline_visible);
-                if result.as_ref().map(Option::is_some).unwrap_or(true) {
-                    cmdline_height += 1;
-                }
+                let cmdline_height = self.cmdline_height();
 
                 if position.y >= self.last_size.y.saturating_sub(2 + cmdline_height)
                     && position.y < self.last_size.y - cmdline_height
@@ -426,12 +584,50 @@ impl View for Layout {
                     return EventResult::consumed();
                 }
 
-                if let Some(view) = self.get_current_view_mut() {
-                    view.on_event(event.relativized((0, 1)))
+                if self.mini {
+                    return self.miniplayer.on_event(event);
+                }
+
+                let height = self.last_size.y.saturating_sub(2 + cmdline_height);
+                let pane = self
+                    .panes(self.last_size.x, height)
+                    .into_iter()
+                    .find(|pane| position.fits_in_rect(pane.offset, pane.size));
+                let Some(pane) = pane else {
+                    return EventResult::consumed();
+                };
+
+                // Clicking a pane focuses it.
+                if matches!(mouse_event, MouseEvent::Press(_))
+                    && self.focus.as_ref() != Some(&pane.screen)
+                {
+                    self.set_screen(pane.screen.clone());
+                }
+
+                // Handle mouse events in the title of the pane.
+                if position.y == pane.offset.y {
+                    let back_button_width = self
+                        .screens
+                        .get(&pane.screen)
+                        .map(|screen| screen.title().len() + 3)
+                        .unwrap_or_default();
+                    if mouse_event == MouseEvent::Press(MouseButton::Left)
+                        && self.stack.get(&pane.screen).is_some_and(|s| !s.is_empty())
+                        && position.x - pane.offset.x < back_button_width
+                    {
+                        self.pop_view();
+                    }
+                    return EventResult::consumed();
+                }
+
+                if let Some(view) = self.top_view_mut(&pane.screen) {
+                    view.on_event(event.relativized(pane.offset + (0, 1)))
                 } else {
                     EventResult::Ignored
                 }
             }
+            // The screens are hidden in the mini-player, so they don't get any keys.
+            _ if self.mini => EventResult::Ignored,
             _ => {
                 if let Some(view) = self.get_current_view_mut() {
                     view.on_event(event.relativized((0, 1)))
@@ -443,8 +639,10 @@ impl View for Layout {
     }
 
     fn call_on_any(&mut self, s: &Selector, c: AnyCb<'_>) {
-        if let Some(view) = self.get_current_view_mut() {
-            view.call_on_any(s, c);
+        for screen in self.visible_screens() {
+            if let Some(view) = self.top_view_mut(&screen) {
+                view.call_on_any(s, c);
+            }
         }
     }
 
@@ -475,6 +673,7 @@ impl ViewExt for Layout {
                 }
 
                 if self.screens.keys().any(|k| k == view) {
+                    self.mini = false;
                     self.set_screen(view.clone());
                     let screen = self.screens.get_mut(view).unwrap();
                     screen.on_command(s, cmd)?;
@@ -486,6 +685,16 @@ impl ViewExt for Layout {
                 self.pop_view();
                 Ok(CommandResult::Consumed(None))
             }
+            Command::Pane(mode) => {
+                self.cycle_pane(mode);
+                Ok(CommandResult::Consumed(None))
+            }
+            Command::Mini(mini) => {
+                self.set_mini(mini.unwrap_or(!self.mini));
+                Ok(CommandResult::Consumed(None))
+            }
+            // Commands for the hidden screens would act on items that can't be seen.
+            _ if self.mini => Ok(CommandResult::Ignored),
             _ => {
                 if let Some(view) = self.get_current_view_mut() {
                     view.on_command(s, cmd)
@@ -496,3 +705,71 @@ impl ViewExt for Layout {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pane(screen: &str, offset: (usize, usize), size: (usize, usize)) -> Pane {
+        Pane {
+            screen: screen.into(),
+            offset: offset.into(),
+            size: size.into(),
+        }
+    }
+
+    fn screens(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn splits_horizontally_with_separators() {
+        let panes = split(screens(&["a", "b"]), &SplitOrientation::Horizontal, 81, 20);
+        assert_eq!(
+            panes,
+            [pane("a", (0, 0), (40, 20)), pane("b", (41, 0), (40, 20))]
+        );
+
+        let panes = split(
+            screens(&["a", "b", "c"]),
+            &SplitOrientation::Horizontal,
+            10,
+            5,
+        );
+        assert_eq!(
+            panes,
+            [
+                pane("a", (0, 0), (2, 5)),
+                pane("b", (3, 0), (3, 5)),
+                pane("c", (7, 0), (3, 5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_vertically() {
+        let panes = split(
+            screens(&["a", "b", "c"]),
+            &SplitOrientation::Vertical,
+            30,
+            10,
+        );
+        assert_eq!(
+            panes,
+            [
+                pane("a", (0, 0), (30, 3)),
+                pane("b", (0, 3), (30, 3)),
+                pane("c", (0, 6), (30, 4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn single_screen_fills_the_area() {
+        for orientation in [SplitOrientation::Horizontal, SplitOrientation::Vertical] {
+            let panes = split(screens(&["a"]), &orientation, 30, 10);
+            assert_eq!(panes, [pane("a", (0, 0), (30, 10))]);
+        }
+        assert!(split(Vec::new(), &SplitOrientation::Horizontal, 30, 10).is_empty());
+    }
+}
@@ -1,22 +1,25 @@
 use std::sync::Arc;
 
-use cursive::view::ViewWrapper;
+use cursive::view::{Nameable, ViewWrapper};
 use cursive::Cursive;
 use strum::IntoEnumIterator;
 
 use crate::command::Command;
-use crate::commands::CommandResult;
-use crate::config::LibraryTab;
-use crate::library::Library;
+use crate::commands::{BindingContext, CommandResult};
+use crate::config::{LibraryTab, SortingOrder};
+use crate::library::{Library, TRACKS_ORDER_ID};
 use crate::queue::Queue;
 use crate::traits::ViewExt;
 use crate::ui::browse::BrowseView;
 use crate::ui::listview::ListView;
-use crate::ui::playlists::PlaylistsView;
+use crate::ui::playlists::{self, PlaylistsView};
 use crate::ui::tabbedview::TabbedView;
 
 pub struct LibraryView {
     tabs: TabbedView,
+    /// The kind of each tab in `tabs`, in the same order.
+    tab_kinds: Vec<LibraryTab>,
+    library: Arc<Library>,
     display_name: Option<String>,
 }
 
@@ -30,11 +33,13 @@ impl LibraryView {
             .clone()
             .unwrap_or_else(|| Vec::from_iter(LibraryTab::iter()));
 
-        for tab in selected_tabs {
+        for tab in selected_tabs.iter() {
             match tab {
                 LibraryTab::Tracks => tabview.add_tab(
                     "Tracks",
-                    ListView::new(library.tracks.clone(), queue.clone(), library.clone()),
+                    ListView::new(library.tracks.clone(), queue.clone(), library.clone())
+                        .with_columns("library")
+                        .with_sort_order(TRACKS_ORDER_ID),
                 ),
                 LibraryTab::Albums => tabview.add_tab(
                     "Albums",
@@ -46,7 +51,7 @@ impl LibraryView {
                 ),
                 LibraryTab::Playlists => tabview.add_tab(
                     "Playlists",
-                    PlaylistsView::new(queue.clone(), library.clone()),
+                    PlaylistsView::new(queue.clone(), library.clone()).with_name(playlists::NAME),
                 ),
                 LibraryTab::Podcasts => tabview.add_tab(
                     "Podcasts",
@@ -60,6 +65,8 @@ impl LibraryView {
 
         Self {
             tabs: tabview,
+            tab_kinds: selected_tabs,
+            library: library.clone(),
             display_name: {
                 let hide_username = library.cfg.values().hide_display_names.unwrap_or(false);
                 if hide_username {
@@ -85,7 +92,26 @@ impl ViewExt for LibraryView {
         }
     }
 
+    fn binding_context(&self) -> Option<BindingContext> {
+        Some(BindingContext::Library)
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        if let Command::Sort(keys, direction) = cmd {
+            let order = SortingOrder::new(keys, direction.clone());
+            match (self.tab_kinds.get(self.tabs.selected_index()), order) {
+                (Some(LibraryTab::Tracks), Some(order)) => {
+                    self.library.sort_tracks(order);
+                    return Ok(CommandResult::Consumed(None));
+                }
+                (Some(LibraryTab::Albums), Some(order)) => {
+                    self.library.sort_albums(order);
+                    return Ok(CommandResult::Consumed(None));
+                }
+                _ => {}
+            }
+        }
+
         self.tabs.on_command(s, cmd)
     }
 }
@@ -1,18 +1,24 @@
 use cursive::view::scroll::Scroller;
 use log::info;
 use std::cmp::{max, min, Ordering};
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::{Arc, RwLock};
 
 use cursive::align::HAlign;
 use cursive::event::{Callback, Event, EventResult, MouseButton, MouseEvent};
-use cursive::theme::{ColorStyle, ColorType, PaletteColor};
+use cursive::theme::{ColorStyle, ColorType, Effect, PaletteColor};
 use cursive::traits::View;
 use cursive::view::scroll;
 use cursive::{Cursive, Printer, Rect, Vec2};
 use unicode_width::UnicodeWidthStr;
 
-use crate::command::{Command, GotoMode, InsertSource, JumpMode, MoveAmount, MoveMode, TargetMode};
+use crate::application::UserData;
+use crate::command::{
+    Command, GotoMode, InsertSource, JumpMode, MarkMode, MoveAmount, MoveMode, SortDirection,
+    SortKey, TargetMode,
+};
 use crate::commands::CommandResult;
+use crate::config::{Column, ColumnField};
 use crate::ext_traits::CursiveExt;
 use crate::library::Library;
 use crate::model::album::Album;
@@ -29,13 +35,70 @@ use crate::spotify::UriType;
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
 use crate::ui::album::AlbumView;
 use crate::ui::artist::ArtistView;
+use crate::ui::columns;
 use crate::ui::contextmenu::ContextMenu;
 use crate::ui::pagination::Pagination;
 
+/// Identifies an item of a list, see [Marks].
+type ItemKey = (Option<String>, String);
+
+/// The marked items of a list. Marks are remembered by index together with a key of the item, so
+/// that they don't apply to other items once the content of the list changes.
+#[derive(Default)]
+struct Marks<K> {
+    marked: BTreeMap<usize, K>,
+    /// Where visual mode was started. The items from here to the selected one are marked too.
+    anchor: Option<(usize, K)>,
+}
+
+impl<K: PartialEq> Marks<K> {
+    /// The marked indexes in a list of `len` items where `selected` is selected. `key_at` returns
+    /// the key of the item at an index. Marks on items that were replaced are left out.
+    fn indexes(
+        &self,
+        selected: usize,
+        len: usize,
+        key_at: impl Fn(usize) -> Option<K>,
+    ) -> BTreeSet<usize> {
+        let holds = |index: usize, key: &K| key_at(index).is_some_and(|k| k == *key);
+        let mut indexes: BTreeSet<usize> = self
+            .marked
+            .iter()
+            .filter(|(index, key)| holds(**index, key))
+            .map(|(index, _)| *index)
+            .collect();
+        if let Some((anchor, key)) = &self.anchor {
+            if holds(*anchor, key) {
+                let range = min(*anchor, selected)..=max(*anchor, selected);
+                indexes.extend(range.filter(|index| *index < len));
+            }
+        }
+        indexes
+    }
+
+    /// Replace the marks with `marked` and leave visual mode.
+    fn set(&mut self, marked: impl IntoIterator<Item = (usize, K)>) {
+        self.marked = marked.into_iter().collect();
+        self.anchor = None;
+    }
+
+    /// Mark the item at `index` with `key`, or unmark it if it's already marked.
+    fn toggle(&mut self, index: usize, key: K) {
+        match self.marked.remove(&index) {
+            Some(marked) if marked == key => {}
+            _ => {
+                self.marked.insert(index, key);
+            }
+        }
+    }
+}
+
 pub struct ListView<I: ListItem> {
     content: Arc<RwLock<Vec<I>>>,
     last_content_len: usize,
     selected: usize,
+    /// The items that were marked, which commands apply to instead of the selected item.
+    marks: Marks<ItemKey>,
     search_query: String,
     search_indexes: Vec<usize>,
     search_selected_index: usize,
@@ -45,6 +108,13 @@ pub struct ListView<I: ListItem> {
     library: Arc<Library>,
     pagination: Pagination<I>,
     title: String,
+    /// The view whose `[columns]` the items are shown in, if this is a track list.
+    columns_view: Option<&'static str>,
+    /// The id under which the sorting order of the list is remembered in the user state, if the
+    /// owning view sorts it on [Command::Sort]. Only then the header can be clicked to sort.
+    sort_order_id: Option<String>,
+    /// Whether the items that were added to the queue by autoplay are marked.
+    show_auto_added: bool,
 }
 
 impl<I: ListItem> Scroller for ListView<I> {
@@ -63,6 +133,7 @@ impl<I: ListItem + Clone> ListView<I> {
             content,
             last_content_len: 0,
             selected: 0,
+            marks: Marks::default(),
             search_query: String::new(),
             search_indexes: Vec::new(),
             search_selected_index: 0,
@@ -72,6 +143,9 @@ impl<I: ListItem + Clone> ListView<I> {
             library,
             pagination: Pagination::default(),
             title: "".to_string(),
+            columns_view: None,
+            sort_order_id: None,
+            show_auto_added: false,
         };
         result.try_paginate();
         result
@@ -82,6 +156,121 @@ impl<I: ListItem + Clone> ListView<I> {
         self
     }
 
+    /// Show the items in the columns that are configured for `view` in `[columns]`, if there
+    /// are any.
+    pub fn with_columns(mut self, view: &'static str) -> Self {
+        self.columns_view = Some(view);
+        self
+    }
+
+    /// Make the header clickable to sort the list. The view that owns the list has to handle
+    /// [Command::Sort] and remember the order under `id` in the user state.
+    pub fn with_sort_order(mut self, id: &str) -> Self {
+        self.sort_order_id = Some(id.to_string());
+        self
+    }
+
+    /// The column that the list is sorted on and the direction, if the order was stored.
+    fn sorted(&self) -> Option<(SortKey, SortDirection)> {
+        let id = self.sort_order_id.as_ref()?;
+        let state = self.library.cfg.state();
+        let order = state.playlist_orders.get(id)?;
+        Some((order.key.clone(), order.direction.clone()))
+    }
+
+    /// Mark the items that were added to the queue by autoplay. Only meaningful if this lists the
+    /// queue itself.
+    pub fn with_auto_added(mut self) -> Self {
+        self.show_auto_added = true;
+        self
+    }
+
+    /// The columns that the items are shown in, if any are configured for this list.
+    fn columns(&self) -> Option<Vec<Column>> {
+        let view = self.columns_view?;
+        let config = self.library.cfg.values();
+        config.columns.as_ref()?.get(view).cloned()
+    }
+
+    /// The amount of rows above the items, which is 1 if the header of the columns is shown.
+    fn header_rows(&self) -> usize {
+        let header = self
+            .library
+            .cfg
+            .values()
+            .columns
+            .as_ref()
+            .and_then(|columns| columns.header)
+            .unwrap_or(true);
+        usize::from(header && self.columns().is_some())
+    }
+
+    /// The values of `columns` for `item` at `index`, or None if it isn't shown in columns.
+    fn cells(&self, item: &I, index: usize, columns: &[Column]) -> Option<Vec<String>> {
+        columns
+            .iter()
+            .map(|column| {
+                let value = item.column(column.field(), &self.library)?;
+                Some(match column.field() {
+                    ColumnField::Index => (index + 1).to_string(),
+                    ColumnField::Auto
+                        if self.show_auto_added && self.queue.is_auto_added(index) =>
+                    {
+                        "auto".to_string()
+                    }
+                    _ => value,
+                })
+            })
+            .collect()
+    }
+
+    fn draw_header(&self, printer: &Printer<'_, '_>, columns: &[Column]) {
+        let sorted = self.sorted();
+        let titles: Vec<String> = columns
+            .iter()
+            .map(|column| match &sorted {
+                Some((key, direction)) if column.field().sort_key().as_ref() == Some(key) => {
+                    let arrow = match direction {
+                        SortDirection::Ascending => "▲",
+                        SortDirection::Descending => "▼",
+                    };
+                    format!("{} {arrow}", column.field().header())
+                }
+                _ => column.field().header().to_string(),
+            })
+            .collect();
+        let width = self.scroller.inner_size().x;
+        let header = columns::row(columns, &columns::widths(columns, width), &titles);
+        printer.with_effect(Effect::Bold, |printer| {
+            printer.print((0, 0), &header);
+        });
+    }
+
+    /// Sort the list on the column at `x` in the header, or reverse the order if it's already
+    /// sorted on that column.
+    fn click_header(&mut self, x: usize) -> EventResult {
+        let Some(columns) = self.columns().filter(|_| self.sort_order_id.is_some()) else {
+            return EventResult::Ignored;
+        };
+        let widths = columns::widths(&columns, self.scroller.inner_size().x);
+        let Some(field) = columns::column_at(&widths, x).map(|index| columns[index].field()) else {
+            return EventResult::consumed();
+        };
+        let Some(key) = field.sort_key() else {
+            return EventResult::consumed();
+        };
+
+        let direction = match self.sorted() {
+            Some((sorted, SortDirection::Ascending)) if sorted == key => SortDirection::Descending,
+            _ => SortDirection::Ascending,
+        };
+        EventResult::Consumed(Some(Callback::from_fn_once(move |s| {
+            if let Some(data) = s.user_data::<UserData>().cloned() {
+                data.cmd.handle(s, Command::Sort(vec![key], direction));
+            }
+        })))
+    }
+
     pub fn get_pagination(&self) -> &Pagination<I> {
         &self.pagination
     }
@@ -134,6 +323,60 @@ impl<I: ListItem + Clone> ListView<I> {
         self.selected
     }
 
+    /// The key that identifies `item` for [Marks].
+    fn item_key(&self, item: &I) -> ItemKey {
+        (item.share_url(), item.display_left(&self.library))
+    }
+
+    fn key_at(&self, index: usize) -> Option<ItemKey> {
+        let content = self.content.read().unwrap();
+        content.get(index).map(|item| self.item_key(item))
+    }
+
+    /// The indexes of the marked items and the items in the visual range.
+    fn marked_indexes(&self, content: &[I]) -> BTreeSet<usize> {
+        self.marks.indexes(self.selected, content.len(), |index| {
+            content.get(index).map(|item| self.item_key(item))
+        })
+    }
+
+    /// Return the sorted indexes of the items that commands apply to. These are the marked items
+    /// and the items in the visual range, or the selected item if there are none.
+    pub fn get_selected_indexes(&self) -> Vec<usize> {
+        let content = self.content.read().unwrap();
+        selected_indexes(self.marked_indexes(&content), self.selected, content.len())
+    }
+
+    /// Return whether any items are marked or visual mode is active.
+    pub fn has_marked(&self) -> bool {
+        !self
+            .marked_indexes(&self.content.read().unwrap())
+            .is_empty()
+    }
+
+    /// Replace the marked items with `indexes` and leave visual mode.
+    pub fn set_marked(&mut self, indexes: impl IntoIterator<Item = usize>) {
+        let marked: Vec<(usize, ItemKey)> = indexes
+            .into_iter()
+            .filter_map(|index| Some((index, self.key_at(index)?)))
+            .collect();
+        self.marks.set(marked);
+    }
+
+    /// Unmark all items and leave visual mode.
+    pub fn clear_marked(&mut self) {
+        self.set_marked([]);
+    }
+
+    /// Return clones of the items that commands apply to, see [ListView::get_selected_indexes].
+    fn selected_items(&self) -> Vec<I> {
+        let content = self.content.read().unwrap();
+        self.get_selected_indexes()
+            .into_iter()
+            .filter_map(|index| content.get(index).cloned())
+            .collect()
+    }
+
     pub fn get_indexes_of(&self, query: &str) -> Vec<usize> {
         let content = self.content.read().unwrap();
         content
@@ -148,6 +391,17 @@ impl<I: ListItem + Clone> ListView<I> {
             .collect()
     }
 
+    /// The index of the item at `position` in the view, unless that's on the scrollbar or below
+    /// the last item.
+    pub fn item_at(&self, position: Vec2) -> Option<usize> {
+        if self.has_visible_scrollbars() && position.x >= self.scroller.inner_size().x {
+            return None;
+        }
+        let row = position.y.checked_sub(self.header_rows())?
+            + self.scroller.content_viewport().top_left().y;
+        (row < self.content_len(false)).then_some(row)
+    }
+
     pub fn move_focus_to(&mut self, target: usize) {
         let len = self.content_len(false).saturating_sub(1);
         self.selected = min(target, len);
@@ -188,12 +442,20 @@ impl<I: ListItem + Clone> ListView<I> {
         }
     }
 
-    /// Remove the item at `index` from the list.
+    /// Replace the items of the list. The marked items are unmarked.
+    pub fn set_content(&mut self, content: Vec<I>) {
+        self.clear_marked();
+        *self.content.write().unwrap() = content;
+        self.move_focus_to(self.selected);
+    }
+
+    /// Remove the item at `index` from the list. The marked items are unmarked.
     ///
     /// # Panics
     ///
     /// Panics if `index` is out of bounds.
     pub fn remove(&mut self, index: usize) {
+        self.clear_marked();
         let mut c = self.content.write().unwrap();
         c.remove(index);
         if self.selected >= c.len() {
@@ -202,9 +464,25 @@ impl<I: ListItem + Clone> ListView<I> {
     }
 }
 
+/// The sorted `marked` indexes, or `selected` if none are marked, in a list of `len` items.
+fn selected_indexes(marked: BTreeSet<usize>, selected: usize, len: usize) -> Vec<usize> {
+    if marked.is_empty() && selected < len {
+        vec![selected]
+    } else {
+        marked.into_iter().filter(|index| *index < len).collect()
+    }
+}
+
 impl<I: ListItem + Clone> View for ListView<I> {
     fn draw(&self, printer: &Printer<'_, '_>) {
         let content = self.content.read().unwrap();
+        let marked = self.marked_indexes(&content);
+        let columns = self.columns();
+        let header_rows = self.header_rows();
+        if let (Some(columns), 1) = (&columns, header_rows) {
+            self.draw_header(printer, columns);
+        }
+        let printer = &printer.offset((0, header_rows));
 
         scroll::draw_lines(self, printer, |_, printer, i| {
             // draw paginator after content
@@ -237,6 +515,18 @@ impl<I: ListItem + Clone> View for ListView<I> {
                     } else {
                         ColorStyle::highlight()
                     }
+                } else if marked.contains(&i) {
+                    if currently_playing {
+                        ColorStyle::new(
+                            ColorType::Color(*printer.theme.palette.custom("playing").unwrap()),
+                            ColorType::Palette(PaletteColor::HighlightInactive),
+                        )
+                    } else {
+                        ColorStyle::new(
+                            ColorType::Palette(PaletteColor::HighlightText),
+                            ColorType::Palette(PaletteColor::HighlightInactive),
+                        )
+                    }
                 } else if currently_playing {
                     ColorStyle::new(
                         ColorType::Color(*printer.theme.palette.custom("playing").unwrap()),
@@ -248,10 +538,14 @@ impl<I: ListItem + Clone> View for ListView<I> {
                     ColorStyle::primary()
                 };
 
-                let left = item.display_left(&self.library);
-                let center = item.display_center(&self.library);
-                let right = item.display_right(&self.library);
-                let draw_center = !center.is_empty();
+                let row = columns.as_ref().and_then(|columns| {
+                    let cells = self.cells(item, i, columns)?;
+                    let widths = columns::widths(columns, printer.size.x);
+                    Some(columns::row(columns, &widths, &cells))
+                });
+                let left = row
+                    .clone()
+                    .unwrap_or_else(|| item.display_left(&self.library));
 
                 // draw left string
                 printer.with_color(style, |printer| {
@@ -278,6 +572,17 @@ impl<I: ListItem + Clone> View for ListView<I> {
                     }
                 }
 
+                if row.is_some() {
+                    return;
+                }
+
+                let center = item.display_center(&self.library);
+                let mut right = item.display_right(&self.library);
+                if self.show_auto_added && self.queue.is_auto_added(i) {
+                    right = format!("auto {right}");
+                }
+                let draw_center = !center.is_empty();
+
                 // left string cut off indicator
                 let center_offset = printer.size.x / 2;
                 let left_max_length = if draw_center {
@@ -328,7 +633,7 @@ impl<I: ListItem + Clone> View for ListView<I> {
 
         scroll::layout(
             self,
-            size,
+            size.saturating_sub((0, self.header_rows())),
             relayout_scroller,
             |_, _| {},
             |s, c| Vec2::new(c.x, s.content_len(true)),
@@ -344,6 +649,34 @@ impl<I: ListItem + Clone> View for ListView<I> {
     }
 
     fn on_event(&mut self, e: Event) -> EventResult {
+        // Mouse events below the header are handled as if the header wasn't there.
+        let header_rows = self.header_rows();
+        let e = match e {
+            Event::Mouse {
+                event,
+                position,
+                offset,
+            } if header_rows > 0 => {
+                if position
+                    .checked_sub(offset)
+                    .is_some_and(|p| p.y < header_rows)
+                {
+                    return match event {
+                        MouseEvent::Press(MouseButton::Left) => {
+                            self.click_header(position.x.saturating_sub(offset.x))
+                        }
+                        _ => EventResult::consumed(),
+                    };
+                }
+                Event::Mouse {
+                    event,
+                    position,
+                    offset: offset + (0, header_rows),
+                }
+            }
+            e => e,
+        };
+
         match e {
             Event::Mouse {
                 event: MouseEvent::WheelUp,
@@ -469,7 +802,7 @@ impl<I: ListItem + Clone> View for ListView<I> {
 
     fn important_area(&self, view_size: Vec2) -> Rect {
         if self.content_len(false) > 0 {
-            Rect::from_point((view_size.x, self.selected))
+            Rect::from_point((view_size.x, self.selected + self.header_rows()))
         } else {
             Rect::from_point((0, 0))
         }
@@ -484,71 +817,106 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
     fn on_command(&mut self, _s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         match cmd {
             Command::Play => {
-                self.queue.clear();
-
-                if !self.attempt_play_all_tracks() {
-                    self.play_current_item();
-                }
+                let queue = self.queue.clone();
+                queue.record(|| {
+                    self.queue.clear();
+
+                    if self.has_marked() {
+                        // Play the first of the marked items and queue the others after it.
+                        let mut items = self.selected_items().into_iter();
+                        if let Some(mut item) = items.next() {
+                            item.play(&self.queue);
+                        }
+                        for mut item in items {
+                            item.queue(&self.queue);
+                        }
+                        self.clear_marked();
+                    } else if !self.attempt_play_all_tracks() {
+                        self.play_current_item();
+                    }
+                });
 
                 return Ok(CommandResult::Consumed(None));
             }
             Command::PlayNext => {
                 info!("played next");
-                let mut content = self.content.write().unwrap();
-                if let Some(item) = content.get_mut(self.selected) {
-                    item.play_next(&self.queue);
-                }
+                // Every item is inserted after the current one, so the last one goes first.
+                self.queue.record(|| {
+                    for mut item in self.selected_items().into_iter().rev() {
+                        item.play_next(&self.queue);
+                    }
+                });
+                self.clear_marked();
 
                 return Ok(CommandResult::Consumed(None));
             }
             Command::Queue => {
-                let mut content = self.content.write().unwrap();
-                if let Some(item) = content.get_mut(self.selected) {
-                    item.queue(&self.queue);
-                }
+                self.queue.record(|| {
+                    for mut item in self.selected_items() {
+                        item.queue(&self.queue);
+                    }
+                });
+                self.clear_marked();
 
                 return Ok(CommandResult::Consumed(None));
             }
             Command::Save => {
-                let mut item = {
-                    let content = self.content.read().unwrap();
-                    content.get(self.selected).cloned()
-                };
-
-                if let Some(item) = item.as_mut() {
+                for mut item in self.selected_items() {
                     item.save(&self.library);
                 }
+                self.clear_marked();
 
                 return Ok(CommandResult::Consumed(None));
             }
             Command::Add => {
-                let item = {
-                    let content = self.content.read().unwrap();
-                    content.get(self.selected).cloned()
-                };
-
-                if let Some(track) = item {
-                    if let Some(track) = track.track() {
-                        let dialog = ContextMenu::add_track_dialog(
-                            self.library.clone(),
-                            self.queue.get_spotify(),
-                            track,
-                        );
-                        return Ok(CommandResult::Modal(Box::new(dialog)));
-                    }
+                let tracks: Vec<Track> = self
+                    .selected_items()
+                    .iter()
+                    .filter_map(ListItem::track)
+                    .collect();
+                self.clear_marked();
+
+                if !tracks.is_empty() {
+                    let dialog = ContextMenu::add_track_dialog(
+                        self.library.clone(),
+                        self.queue.get_spotify(),
+                        tracks,
+                    );
+                    return Ok(CommandResult::Modal(Box::new(dialog)));
                 }
 
                 return Ok(CommandResult::Consumed(None));
             }
             Command::Delete => {
-                let mut item = {
-                    let content = self.content.read().unwrap();
-                    content.get(self.selected).cloned()
-                };
-
-                if let Some(item) = item.as_mut() {
+                for mut item in self.selected_items() {
                     item.unsave(&self.library);
                 }
+                self.clear_marked();
+
+                return Ok(CommandResult::Consumed(None));
+            }
+            Command::Mark(mode) => {
+                match mode {
+                    MarkMode::Toggle => {
+                        if let Some(key) = self.key_at(self.selected) {
+                            self.marks.toggle(self.selected, key);
+                        }
+                    }
+                    MarkMode::All => self.set_marked(0..self.content_len(false)),
+                    MarkMode::None => self.clear_marked(),
+                }
+
+                return Ok(CommandResult::Consumed(None));
+            }
+            Command::Visual => {
+                match self.marks.anchor {
+                    // Leaving visual mode keeps the range marked.
+                    Some(_) => self.set_marked(self.get_selected_indexes()),
+                    None => {
+                        self.marks.anchor =
+                            self.key_at(self.selected).map(|key| (self.selected, key))
+                    }
+                }
 
                 return Ok(CommandResult::Consumed(None));
             }
@@ -791,3 +1159,60 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
         Ok(CommandResult::Ignored)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indexes(marks: &Marks<char>, selected: usize, content: &str) -> Vec<usize> {
+        let marked = marks.indexes(selected, content.len(), |index| content.chars().nth(index));
+        selected_indexes(marked, selected, content.len())
+    }
+
+    #[test]
+    fn selects_selected_item_without_marks() {
+        let marks = Marks::default();
+        assert_eq!(indexes(&marks, 2, "abcd"), [2]);
+        assert!(indexes(&marks, 0, "").is_empty());
+    }
+
+    #[test]
+    fn selects_marked_items_in_order() {
+        let mut marks = Marks::default();
+        marks.toggle(3, 'd');
+        marks.toggle(1, 'b');
+        marks.toggle(2, 'c');
+        marks.toggle(2, 'c');
+        assert_eq!(indexes(&marks, 0, "abcd"), [1, 3]);
+    }
+
+    #[test]
+    fn selects_visual_range_and_marks() {
+        let mut marks = Marks::default();
+        marks.set([(0, 'a')]);
+        marks.anchor = Some((4, 'e'));
+        assert_eq!(indexes(&marks, 2, "abcdef"), [0, 2, 3, 4]);
+        assert_eq!(indexes(&marks, 5, "abcdef"), [0, 4, 5]);
+    }
+
+    #[test]
+    fn drops_marks_of_replaced_items() {
+        let mut marks = Marks::default();
+        marks.set([(1, 'b'), (3, 'd')]);
+        // An item was inserted at the start, so neither index holds its item anymore.
+        assert_eq!(indexes(&marks, 0, "xabcd"), [0]);
+        // Items were removed, so the marked indexes are out of range.
+        assert_eq!(indexes(&marks, 0, "a"), [0]);
+
+        marks.anchor = Some((2, 'c'));
+        assert_eq!(indexes(&marks, 0, "abxd"), [1, 3]);
+    }
+
+    #[test]
+    fn toggling_replaced_item_marks_it() {
+        let mut marks = Marks::default();
+        marks.toggle(1, 'b');
+        marks.toggle(1, 'x');
+        assert_eq!(indexes(&marks, 0, "axc"), [1]);
+    }
+}
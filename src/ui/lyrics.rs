@@ -0,0 +1,245 @@
+use std::cmp::min;
+use std::sync::Arc;
+
+use cursive::align::HAlign;
+use cursive::event::{Event, EventResult, MouseButton, MouseEvent};
+use cursive::theme::{ColorStyle, ColorType};
+use cursive::{Cursive, Printer, Vec2, View};
+use unicode_width::UnicodeWidthStr;
+
+use crate::command::{Command, MoveAmount, MoveMode};
+use crate::commands::CommandResult;
+use crate::config::Config;
+use crate::lyrics::{providers, Lyrics, LyricsProvider};
+use crate::model::playable::Playable;
+use crate::queue::Queue;
+use crate::traits::ViewExt;
+
+/// Shows the lyrics of the playing track. Synced lyrics follow the playback, and selecting a line
+/// seeks to it.
+pub struct LyricsView {
+    queue: Arc<Queue>,
+    providers: Vec<Box<dyn LyricsProvider>>,
+    /// The URI of the track whose lyrics were looked up, and the lyrics if any were found.
+    loaded: Option<(String, Option<Lyrics>)>,
+    /// The line that was selected with the cursor keys. Until then, the view follows the playing
+    /// line.
+    selected: Option<usize>,
+    last_size: Vec2,
+}
+
+impl LyricsView {
+    pub fn new(queue: Arc<Queue>, config: &Config) -> Self {
+        Self {
+            queue,
+            providers: providers(config),
+            loaded: None,
+            selected: None,
+            last_size: Vec2::zero(),
+        }
+    }
+
+    /// Look up the lyrics of the playing track, unless they were already.
+    fn load(&mut self) {
+        let track = self
+            .queue
+            .get_current()
+            .and_then(|playable| match playable {
+                Playable::Track(track) => Some(track),
+                Playable::Episode(_) => None,
+            });
+        let Some(track) = track else {
+            self.loaded = None;
+            return;
+        };
+        if self.loaded.as_ref().map(|(uri, _)| uri) == Some(&track.uri) {
+            return;
+        }
+
+        let lyrics = self
+            .providers
+            .iter()
+            .find_map(|provider| provider.lyrics(&track));
+        self.loaded = Some((track.uri.clone(), lyrics));
+        self.selected = None;
+    }
+
+    fn lyrics(&self) -> Option<&Lyrics> {
+        self.loaded.as_ref().and_then(|(_, lyrics)| lyrics.as_ref())
+    }
+
+    /// The line that is sung right now.
+    fn playing_line(&self) -> Option<usize> {
+        let progress = self.queue.get_spotify().get_current_progress();
+        self.lyrics()?.current_line(progress)
+    }
+
+    /// The line that is kept in the middle of the view.
+    fn focused_line(&self) -> usize {
+        self.selected.or_else(|| self.playing_line()).unwrap_or(0)
+    }
+
+    /// The index of the line at the top of a view that is `height` rows high.
+    fn first_line(&self, height: usize) -> usize {
+        let len = self.lyrics().map(|lyrics| lyrics.lines.len()).unwrap_or(0);
+        min(
+            self.focused_line().saturating_sub(height / 2),
+            len.saturating_sub(height),
+        )
+    }
+
+    fn move_selection(&mut self, delta: i32) {
+        let len = self.lyrics().map(|lyrics| lyrics.lines.len()).unwrap_or(0);
+        let line = self.focused_line() as i32 + delta;
+        self.selected = Some(line.clamp(0, len.saturating_sub(1) as i32) as usize);
+    }
+
+    /// Seek to the time at which `line` is sung, and follow the playback from there.
+    fn seek_to(&mut self, line: usize) {
+        let time = self
+            .lyrics()
+            .and_then(|lyrics| lyrics.lines.get(line))
+            .and_then(|line| line.time);
+        if let Some(time) = time {
+            self.queue.get_spotify().seek(time.as_millis() as u32);
+            self.selected = None;
+        }
+    }
+}
+
+impl View for LyricsView {
+    fn draw(&self, printer: &Printer<'_, '_>) {
+        let lyrics = match &self.loaded {
+            Some((_, Some(lyrics))) => lyrics,
+            loaded => {
+                let text = match (loaded, self.queue.get_current()) {
+                    (Some(_), _) => "No lyrics found",
+                    (None, Some(Playable::Episode(_))) => "Episodes have no lyrics",
+                    (None, _) => "Nothing is playing",
+                };
+                printer.with_color(ColorStyle::secondary(), |printer| {
+                    printer.print(
+                        (HAlign::Center.get_offset(text.width(), printer.size.x), 0),
+                        text,
+                    );
+                });
+                return;
+            }
+        };
+
+        let playing = self.playing_line();
+        let first = self.first_line(printer.size.y);
+        for (row, (index, line)) in lyrics
+            .lines
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(printer.size.y)
+            .enumerate()
+        {
+            let style = if self.selected == Some(index) {
+                ColorStyle::highlight()
+            } else if playing == Some(index) {
+                ColorStyle::new(
+                    ColorType::Color(*printer.theme.palette.custom("playing").unwrap()),
+                    ColorType::Color(*printer.theme.palette.custom("playing_bg").unwrap()),
+                )
+            } else if lyrics.is_synced() && line.time.is_none() {
+                ColorStyle::secondary()
+            } else {
+                ColorStyle::primary()
+            };
+
+            let offset = HAlign::Center.get_offset(line.text.width(), printer.size.x);
+            printer.with_color(style, |printer| {
+                printer.print((offset, row), &line.text);
+            });
+        }
+    }
+
+    fn layout(&mut self, size: Vec2) {
+        self.last_size = size;
+        self.load();
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        constraint
+    }
+
+    fn on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Mouse {
+                event: MouseEvent::Press(MouseButton::Left),
+                position,
+                offset,
+            } => {
+                if let Some(position) = position.checked_sub(offset) {
+                    let line = self.first_line(self.last_size.y) + position.y;
+                    self.seek_to(line);
+                }
+                EventResult::consumed()
+            }
+            Event::Mouse {
+                event: MouseEvent::WheelUp,
+                ..
+            } => {
+                self.move_selection(-3);
+                EventResult::consumed()
+            }
+            Event::Mouse {
+                event: MouseEvent::WheelDown,
+                ..
+            } => {
+                self.move_selection(3);
+                EventResult::consumed()
+            }
+            _ => EventResult::Ignored,
+        }
+    }
+}
+
+impl ViewExt for LyricsView {
+    fn title(&self) -> String {
+        "Lyrics".to_string()
+    }
+
+    fn title_sub(&self) -> String {
+        match self.queue.get_current() {
+            Some(Playable::Track(track)) => {
+                format!("{} - {}", track.artists.join(", "), track.title)
+            }
+            _ => "".to_string(),
+        }
+    }
+
+    fn on_command(&mut self, _s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            Command::Play => {
+                if let Some(line) = self.selected {
+                    self.seek_to(line);
+                }
+                return Ok(CommandResult::Consumed(None));
+            }
+            Command::Move(MoveMode::Playing, _) => {
+                self.selected = None;
+                return Ok(CommandResult::Consumed(None));
+            }
+            Command::Move(mode @ (MoveMode::Up | MoveMode::Down), amount) => {
+                let len = self.lyrics().map(|lyrics| lyrics.lines.len()).unwrap_or(0);
+                let delta = match amount {
+                    MoveAmount::Integer(amount) => *amount,
+                    MoveAmount::Float(scale) => (self.last_size.y as f32 * scale) as i32,
+                    MoveAmount::Extreme => len as i32,
+                };
+                match mode {
+                    MoveMode::Up => self.move_selection(-delta),
+                    _ => self.move_selection(delta),
+                }
+                return Ok(CommandResult::Consumed(None));
+            }
+            _ => {}
+        }
+
+        Ok(CommandResult::Ignored)
+    }
+}
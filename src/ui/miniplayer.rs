@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cursive::align::HAlign;
+use cursive::event::{Event, EventResult, MouseButton, MouseEvent};
+use cursive::theme::ColorStyle;
+use cursive::traits::View;
+use cursive::vec::Vec2;
+use cursive::Printer;
+use unicode_width::UnicodeWidthStr;
+
+use crate::command::Command;
+use crate::commands::CommandManager;
+use crate::config::{self, Config};
+use crate::library::Library;
+use crate::queue::Queue;
+use crate::traits::ListItem;
+
+/// The amount of upcoming queue entries that are shown by default.
+const DEFAULT_QUEUE_ENTRIES: usize = 3;
+
+/// The commands that are listed in the key hints, with their description.
+const HINTED_COMMANDS: [(Command, &str); 5] = [
+    (Command::TogglePlay, "play/pause"),
+    (Command::Previous, "previous"),
+    (Command::Next, "next"),
+    (Command::Mini(None), "full view"),
+    (Command::Quit, "quit"),
+];
+
+/// The part of the mini-player above the statusbar: the entries of the queue that are played next
+/// and a line with the keys of the most common commands.
+pub struct MiniPlayer {
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+    entries: usize,
+    hints: Option<String>,
+    last_size: Vec2,
+}
+
+impl MiniPlayer {
+    pub fn new(queue: Arc<Queue>, library: Arc<Library>, config: &Config) -> Self {
+        let mini = config.values().mini.clone().unwrap_or_default();
+        let command_key = config
+            .values()
+            .command_key
+            .unwrap_or(config::DEFAULT_COMMAND_KEY);
+        let hints = mini
+            .key_hints
+            .unwrap_or(true)
+            .then(|| key_hints(command_key, &CommandManager::get_bindings(config)));
+
+        Self {
+            queue,
+            library,
+            entries: mini.queue_entries.unwrap_or(DEFAULT_QUEUE_ENTRIES),
+            hints,
+            last_size: Vec2::zero(),
+        }
+    }
+
+    /// The indexes in the queue of the entries that are shown in a view that is `height` rows
+    /// high.
+    fn shown_indexes(&self, height: usize) -> Vec<usize> {
+        let rows = height.saturating_sub(usize::from(self.hints.is_some()));
+        self.queue.upcoming_indexes(self.entries.min(rows))
+    }
+}
+
+/// A line with the keys that are bound to the [HINTED_COMMANDS] in `bindings`, and the key that
+/// opens the command line.
+fn key_hints(command_key: char, bindings: &HashMap<String, Vec<Command>>) -> String {
+    let mut hints: Vec<String> = HINTED_COMMANDS
+        .iter()
+        .filter_map(|(command, description)| {
+            let command = command.to_string();
+            bindings
+                .iter()
+                .filter(|(_, commands)| {
+                    matches!(commands.as_slice(), [bound] if bound.to_string() == command)
+                })
+                .map(|(key, _)| key)
+                .min_by_key(|key| (key.len(), key.to_string()))
+                .map(|key| format!("{key} {description}"))
+        })
+        .collect();
+    hints.push(format!("{command_key} command"));
+    hints.join("  ")
+}
+
+impl View for MiniPlayer {
+    fn draw(&self, printer: &Printer<'_, '_>) {
+        let indexes = self.shown_indexes(printer.size.y);
+        if indexes.is_empty() {
+            printer.with_color(ColorStyle::secondary(), |printer| {
+                printer.print((1, 0), "Nothing queued");
+            });
+        }
+
+        let queue = self.queue.queue.read().unwrap();
+        for (row, playable) in indexes
+            .iter()
+            .filter_map(|&index| queue.get(index))
+            .enumerate()
+        {
+            let right = playable.display_right(&self.library);
+            let offset = HAlign::Right.get_offset(right.width() + 1, printer.size.x);
+            printer.print((1, row), &playable.display_left(&self.library));
+            printer.with_color(ColorStyle::secondary(), |printer| {
+                printer.print_hline((offset.saturating_sub(1), row), right.width() + 2, " ");
+                printer.print((offset, row), &right);
+            });
+        }
+
+        if let Some(hints) = &self.hints {
+            let row = indexes.len().max(1);
+            printer.with_color(ColorStyle::secondary(), |printer| {
+                printer.print((1, row), hints);
+            });
+        }
+    }
+
+    fn layout(&mut self, size: Vec2) {
+        self.last_size = size;
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        constraint
+    }
+
+    fn on_event(&mut self, event: Event) -> EventResult {
+        if let Event::Mouse {
+            event: MouseEvent::Press(MouseButton::Left),
+            position,
+            offset,
+        } = event
+        {
+            // Clicking an upcoming entry plays it.
+            if let Some(position) = position.checked_sub(offset) {
+                let indexes = self.shown_indexes(self.last_size.y);
+                if let Some(&index) = indexes.get(position.y) {
+                    self.queue.play(index, false, false);
+                }
+            }
+            return EventResult::consumed();
+        }
+        EventResult::Ignored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_hinted_keys() {
+        let mut bindings = HashMap::new();
+        bindings.insert("Shift+p".to_string(), vec![Command::TogglePlay]);
+        bindings.insert("p".to_string(), vec![Command::TogglePlay]);
+        bindings.insert(">".to_string(), vec![Command::Next]);
+        bindings.insert("q".to_string(), vec![Command::Quit, Command::Noop]);
+
+        assert_eq!(key_hints(':', &bindings), "p play/pause  > next  : command");
+    }
+}
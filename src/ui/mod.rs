@@ -4,24 +4,34 @@ use ncspot::BIN_NAME;
 pub mod album;
 pub mod artist;
 pub mod browse;
+pub mod cmdline;
+pub mod columns;
 pub mod contextmenu;
 pub mod help;
+pub mod history;
+pub mod keysequence;
 pub mod layout;
 pub mod library;
 pub mod listview;
+pub mod lyrics;
+pub mod miniplayer;
 pub mod modal;
 pub mod pagination;
 pub mod playlist;
 pub mod playlists;
 pub mod queue;
+pub mod scroll;
 pub mod search;
 pub mod search_results;
 pub mod show;
+pub mod stats;
 pub mod statusbar;
 pub mod tabbedview;
 
 #[cfg(feature = "cover")]
 pub mod cover;
+#[cfg(feature = "cover")]
+pub mod graphics;
 
 /// Create a CursiveRunner which implements the drawing logic and event loop.
 /// 创建cursive界面
@@ -4,7 +4,8 @@ use cursive::view::ViewWrapper;
 use cursive::Cursive;
 
 use crate::command::Command;
-use crate::commands::CommandResult;
+use crate::commands::{BindingContext, CommandResult};
+use crate::config::SortingOrder;
 use crate::library::Library;
 use crate::model::playable::Playable;
 use crate::model::playlist::Playlist;
@@ -27,8 +28,11 @@ impl PlaylistView {
         let mut playlist = playlist.clone();
         playlist.load_tracks(&queue.get_spotify());
 
-        if let Some(order) = library.cfg.state().playlist_orders.get(&playlist.id) {
-            playlist.sort(&order.key, &order.direction);
+        {
+            let state = library.cfg.state();
+            if let Some(order) = state.playlist_orders.get(&playlist.id) {
+                playlist.sort(order, &state.play_counts);
+            }
         }
 
         let tracks = if let Some(t) = playlist.tracks.as_ref() {
@@ -42,7 +46,9 @@ impl PlaylistView {
             Arc::new(RwLock::new(tracks)),
             queue.clone(),
             library.clone(),
-        );
+        )
+        .with_columns("playlist")
+        .with_sort_order(&playlist.id);
 
         Self {
             playlist,
@@ -52,6 +58,31 @@ impl PlaylistView {
             queue,
         }
     }
+
+    /// Reload the tracks from the library version of the playlist, i.e. after an undo.
+    fn reload(&mut self) {
+        let stored = self
+            .library
+            .playlists
+            .read()
+            .unwrap()
+            .iter()
+            .find(|p| p.id == self.playlist.id && p.tracks.is_some())
+            .cloned();
+
+        if let Some(mut playlist) = stored {
+            {
+                let state = self.library.cfg.state();
+                if let Some(order) = state.playlist_orders.get(&playlist.id) {
+                    playlist.sort(order, &state.play_counts);
+                }
+            }
+
+            let tracks = playlist.tracks.clone().unwrap_or_default();
+            self.playlist = playlist;
+            self.list.set_content(tracks);
+        }
+    }
 }
 
 impl ViewWrapper for PlaylistView {
@@ -77,39 +108,54 @@ impl ViewExt for PlaylistView {
         }
     }
 
+    fn binding_context(&self) -> Option<BindingContext> {
+        Some(BindingContext::Playlist)
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         if let Command::Delete = cmd {
-            let pos = self.list.get_selected_index();
-
-            return if self
-                .playlist
-                .delete_track(pos, self.spotify.clone(), &self.library)
-            {
+            if self.playlist.is_smart {
+                return Err("Can't delete tracks from a smart playlist.".to_string());
+            }
+
+            // Deleting from the end keeps the positions of the other tracks valid.
+            for pos in self.list.get_selected_indexes().into_iter().rev() {
+                if !self
+                    .playlist
+                    .delete_track(pos, self.spotify.clone(), &self.library)
+                {
+                    return Err("Could not delete track.".to_string());
+                }
                 self.list.remove(pos);
-                Ok(CommandResult::Consumed(None))
+            }
+            return Ok(CommandResult::Consumed(None));
+        }
+
+        if let Command::Undo | Command::Redo = cmd {
+            let result = if let Command::Undo = cmd {
+                self.library.history.undo(&self.queue, &self.library)
             } else {
-                Err("Could not delete track.".to_string())
+                self.library.history.redo(&self.queue, &self.library)
             };
+            self.reload();
+            return result.map(|message| CommandResult::Consumed(Some(message)));
         }
 
-        if let Command::Sort(key, direction) = cmd {
+        if let Command::Sort(keys, direction) = cmd {
+            let Some(order) = SortingOrder::new(keys, direction.clone()) else {
+                return Ok(CommandResult::Consumed(None));
+            };
+
             self.library.cfg.with_state_mut(|state| {
-                let order = crate::config::SortingOrder {
-                    key: key.clone(),
-                    direction: direction.clone(),
-                };
                 state
                     .playlist_orders
-                    .insert(self.playlist.id.clone(), order);
+                    .insert(self.playlist.id.clone(), order.clone());
             });
 
-            self.playlist.sort(key, direction);
+            self.playlist
+                .sort(&order, &self.library.cfg.state().play_counts);
             let tracks = self.playlist.tracks.as_ref().unwrap_or(&Vec::new()).clone();
-            self.list = ListView::new(
-                Arc::new(RwLock::new(tracks)),
-                self.queue.clone(),
-                self.library.clone(),
-            );
+            self.list.set_content(tracks);
             return Ok(CommandResult::Consumed(None));
         }
 
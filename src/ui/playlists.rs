@@ -13,6 +13,9 @@ use crate::traits::ViewExt;
 use crate::ui::listview::ListView;
 use crate::ui::modal::Modal;
 
+/// The name of the [PlaylistsView] in the library.
+pub const NAME: &str = "playlists";
+
 pub struct PlaylistsView {
     list: ListView<Playlist>,
     library: Arc<Library>,
@@ -21,30 +24,45 @@ pub struct PlaylistsView {
 impl PlaylistsView {
     pub fn new(queue: Arc<Queue>, library: Arc<Library>) -> Self {
         Self {
-            list: ListView::new(library.playlists.clone(), queue, library.clone()),
+            list: ListView::new(library.listed_playlists.clone(), queue, library.clone()),
             library,
         }
     }
 
+    /// A dialog that asks to delete the marked playlists, or the selected one if none are marked.
+    /// Smart playlists are left out.
     pub fn delete_dialog(&mut self) -> Option<Modal<Dialog>> {
-        let playlists = self.library.playlists.read().unwrap();
-        let current = playlists.get(self.list.get_selected_index());
+        let playlists = self.library.listed_playlists.read().unwrap();
+        let ids: Vec<String> = self
+            .list
+            .get_selected_indexes()
+            .into_iter()
+            .filter_map(|index| playlists.get(index))
+            .filter(|playlist| !playlist.is_smart)
+            .map(|playlist| playlist.id.clone())
+            .collect();
 
-        if let Some(playlist) = current {
-            let library = self.library.clone();
-            let id = playlist.id.clone();
-            let dialog = Dialog::text("Are you sure you want to delete this playlist?")
-                .padding(Margins::lrtb(1, 1, 1, 0))
-                .title("Delete playlist")
-                .dismiss_button("No")
-                .button("Yes", move |s: &mut Cursive| {
-                    library.delete_playlist(&id);
-                    s.pop_layer();
-                });
-            Some(Modal::new(dialog))
-        } else {
-            None
+        if ids.is_empty() {
+            return None;
         }
+
+        let library = self.library.clone();
+        let text = match ids.len() {
+            1 => "Are you sure you want to delete this playlist?".to_string(),
+            count => format!("Are you sure you want to delete these {count} playlists?"),
+        };
+        let dialog = Dialog::text(text)
+            .padding(Margins::lrtb(1, 1, 1, 0))
+            .title("Delete playlist")
+            .dismiss_button("No")
+            .button("Yes", move |s: &mut Cursive| {
+                for id in &ids {
+                    library.delete_playlist(id);
+                }
+                s.call_on_name(NAME, |view: &mut Self| view.list.clear_marked());
+                s.pop_layer();
+            });
+        Some(Modal::new(dialog))
     }
 }
 
@@ -1,13 +1,15 @@
-use cursive::traits::{Nameable, Resizable};
+use cursive::event::{Event, EventResult, MouseButton, MouseEvent};
+use cursive::traits::{Nameable, Resizable, View};
 use cursive::view::{Margins, ViewWrapper};
 use cursive::views::{Dialog, EditView, ScrollView, SelectView};
 use cursive::Cursive;
 
-use std::cmp::min;
+use log::error;
+use std::cmp::{max, min};
 use std::sync::Arc;
 
 use crate::command::{Command, MoveMode, ShiftMode};
-use crate::commands::CommandResult;
+use crate::commands::{BindingContext, CommandResult};
 use crate::library::Library;
 use crate::model::playable::Playable;
 use crate::queue::Queue;
@@ -19,16 +21,24 @@ pub struct QueueView {
     list: ListView<Playable>,
     library: Arc<Library>,
     queue: Arc<Queue>,
+    /// The index of the item that is dragged with the mouse.
+    dragged: Option<usize>,
+    /// A click on the selected item, which plays it once the mouse is released without dragging.
+    pending_click: Option<Event>,
 }
 
 impl QueueView {
     pub fn new(queue: Arc<Queue>, library: Arc<Library>) -> Self {
-        let list = ListView::new(queue.queue.clone(), queue.clone(), library.clone());
+        let list = ListView::new(queue.queue.clone(), queue.clone(), library.clone())
+            .with_columns("queue")
+            .with_auto_added();
 
         Self {
             list,
             library,
             queue,
+            dragged: None,
+            pending_click: None,
         }
     }
 
@@ -82,10 +92,117 @@ impl QueueView {
             .content(ScrollView::new(list_select));
         Modal::new(dialog)
     }
+
+    /// A dialog that lists the saved queues and loads the selected one.
+    pub fn saved_queues_dialog(queue: Arc<Queue>) -> Modal<Dialog> {
+        let mut list_select: SelectView<String> = SelectView::new().autojump();
+
+        for (name, count) in queue.saved_queues() {
+            list_select.add_item(format!("{name} ({count} tracks)"), name);
+        }
+
+        list_select.set_on_submit(move |s, name: &String| {
+            if let Err(e) = queue.load_saved(name) {
+                error!("could not load saved queue: {e}");
+            }
+            s.pop_layer();
+        });
+
+        let dialog = Dialog::new()
+            .title("Load saved queue")
+            .dismiss_button("Cancel")
+            .padding(Margins::lrtb(1, 1, 1, 0))
+            .content(ScrollView::new(list_select));
+        Modal::new(dialog)
+    }
+}
+
+/// The shifts that move the items at the sorted `indexes` by `amount` towards `mode`, as pairs of
+/// the index of an item and its new index, to be applied in order. The items keep their order and
+/// stop at the start or end of a queue of `len` items.
+fn shift_targets(
+    indexes: &[usize],
+    mode: &ShiftMode,
+    amount: usize,
+    len: usize,
+) -> Vec<(usize, usize)> {
+    let mut shifts = Vec::new();
+    match mode {
+        ShiftMode::Up => {
+            let mut first_free = 0;
+            for &index in indexes {
+                let target = max(index.saturating_sub(amount), first_free);
+                if target != index {
+                    shifts.push((index, target));
+                }
+                first_free = target + 1;
+            }
+        }
+        ShiftMode::Down => {
+            let mut last_free = len.saturating_sub(1);
+            for &index in indexes.iter().rev() {
+                let target = min(index + amount, last_free);
+                if target != index {
+                    shifts.push((index, target));
+                }
+                last_free = target.saturating_sub(1);
+            }
+        }
+    }
+    shifts
 }
 
 impl ViewWrapper for QueueView {
     wrap_impl!(self.list: ListView<Playable>);
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Mouse {
+                event: MouseEvent::Press(MouseButton::Left),
+                position,
+                offset,
+            } => {
+                let item = position
+                    .checked_sub(offset)
+                    .and_then(|position| self.list.item_at(position));
+                self.dragged = item;
+                if item.is_some() && item == Some(self.list.get_selected_index()) {
+                    self.pending_click = Some(event);
+                    return EventResult::consumed();
+                }
+            }
+            Event::Mouse {
+                event: MouseEvent::Hold(MouseButton::Left),
+                position,
+                offset,
+            } if self.dragged.is_some() => {
+                // The selection shows where the dragged item is dropped.
+                if let Some(target) = position
+                    .checked_sub(offset)
+                    .and_then(|position| self.list.item_at(position))
+                {
+                    self.list.move_focus_to(target);
+                }
+                return EventResult::consumed();
+            }
+            Event::Mouse {
+                event: MouseEvent::Release(MouseButton::Left),
+                ..
+            } => {
+                let click = self.pending_click.take();
+                if let Some(from) = self.dragged.take() {
+                    let to = self.list.get_selected_index();
+                    if from != to {
+                        self.queue.shift(from, to);
+                    } else if let Some(click) = click {
+                        return self.list.on_event(click);
+                    }
+                }
+            }
+            _ => {}
+        }
+        self.list.on_event(event)
+    }
 }
 
 impl ViewExt for QueueView {
@@ -116,10 +233,17 @@ impl ViewExt for QueueView {
         }
     }
 
+    fn binding_context(&self) -> Option<BindingContext> {
+        Some(BindingContext::Queue)
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         match cmd {
             Command::Play => {
-                self.queue.play(self.list.get_selected_index(), true, false);
+                if let Some(&index) = self.list.get_selected_indexes().first() {
+                    self.list.clear_marked();
+                    self.queue.play(index, true, false);
+                }
                 return Ok(CommandResult::Consumed(None));
             }
             Command::PlayNext => {
@@ -129,12 +253,12 @@ impl ViewExt for QueueView {
                 return Ok(CommandResult::Ignored);
             }
             Command::Delete => {
-                let selected = self.list.get_selected_index();
-                let len = self.queue.len();
+                let indexes = self.list.get_selected_indexes();
+                self.list.clear_marked();
 
-                self.queue.remove(selected);
-                if selected == len.saturating_sub(1) {
-                    self.list.move_focus(-1);
+                self.queue.remove_many(&indexes);
+                if let Some(&first) = indexes.first() {
+                    self.list.move_focus_to(first);
                 }
                 return Ok(CommandResult::Consumed(None));
             }
@@ -144,23 +268,28 @@ impl ViewExt for QueueView {
                     _ => 1,
                 };
 
-                let selected = self.list.get_selected_index();
-                let len = self.queue.len();
+                let indexes = self.list.get_selected_indexes();
+                let shifts =
+                    shift_targets(&indexes, mode, amount.max(0) as usize, self.queue.len());
+                if !shifts.is_empty() {
+                    self.queue.shift_many(&shifts);
 
-                match mode {
-                    ShiftMode::Up if selected > 0 => {
-                        self.queue
-                            .shift(selected, (selected as i32).saturating_sub(amount) as usize);
-                        self.list.move_focus(-amount);
-                        return Ok(CommandResult::Consumed(None));
+                    // The marked items stay marked at their new positions.
+                    if self.list.has_marked() {
+                        let mut marked = indexes.clone();
+                        for (from, to) in &shifts {
+                            if let Some(index) = marked.iter_mut().find(|index| **index == *from) {
+                                *index = *to;
+                            }
+                        }
+                        self.list.set_marked(marked);
                     }
-                    ShiftMode::Down if selected < len.saturating_sub(1) => {
-                        self.queue
-                            .shift(selected, min(selected + amount as usize, len - 1));
-                        self.list.move_focus(amount);
-                        return Ok(CommandResult::Consumed(None));
+
+                    let selected = self.list.get_selected_index();
+                    if let Some((_, to)) = shifts.iter().find(|(from, _)| *from == selected) {
+                        self.list.move_focus_to(*to);
                     }
-                    _ => {}
+                    return Ok(CommandResult::Consumed(None));
                 }
             }
             Command::SaveQueue => {
@@ -180,3 +309,19 @@ impl ViewExt for QueueView {
         self.with_view_mut(move |v| v.on_command(s, cmd)).unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shifts_marked_items_together() {
+        let up = shift_targets(&[1, 2, 5], &ShiftMode::Up, 2, 8);
+        assert_eq!(up, vec![(1, 0), (2, 1), (5, 3)]);
+
+        let down = shift_targets(&[4, 6, 7], &ShiftMode::Down, 1, 8);
+        assert_eq!(down, vec![(4, 5)]);
+
+        assert!(shift_targets(&[0, 1], &ShiftMode::Up, 1, 8).is_empty());
+    }
+}
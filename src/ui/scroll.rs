@@ -0,0 +1,37 @@
+use cursive::view::scroll::Scroller;
+use cursive::views::ScrollView;
+
+use crate::command::{MoveAmount, MoveMode};
+
+/// Scroll a text view in response to a [Command::Move].
+pub fn scroll<V: cursive::View>(view: &mut ScrollView<V>, mode: &MoveMode, amount: &MoveAmount) {
+    let scroller = view.get_scroller_mut();
+    let viewport = scroller.content_viewport();
+    match mode {
+        MoveMode::Up => match amount {
+            MoveAmount::Extreme => {
+                view.scroll_to_top();
+            }
+            MoveAmount::Float(scale) => {
+                let amount = (viewport.height() as f32) * scale;
+                scroller.scroll_to_y(viewport.top().saturating_sub(amount as usize));
+            }
+            MoveAmount::Integer(amount) => {
+                scroller.scroll_to_y(viewport.top().saturating_sub(*amount as usize))
+            }
+        },
+        MoveMode::Down => match amount {
+            MoveAmount::Extreme => {
+                view.scroll_to_bottom();
+            }
+            MoveAmount::Float(scale) => {
+                let amount = (viewport.height() as f32) * scale;
+                scroller.scroll_to_y(viewport.bottom().saturating_add(amount as usize));
+            }
+            MoveAmount::Integer(amount) => {
+                scroller.scroll_to_y(viewport.bottom().saturating_add(*amount as usize))
+            }
+        },
+        _ => {}
+    }
+}
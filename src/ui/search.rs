@@ -10,7 +10,7 @@ use std::cell::RefCell;
 use std::sync::{Arc, Mutex, RwLock};
 
 use crate::command::{Command, MoveMode};
-use crate::commands::CommandResult;
+use crate::commands::{BindingContext, CommandResult};
 use crate::events::EventManager;
 use crate::library::Library;
 use crate::model::album::Album;
@@ -113,6 +113,10 @@ impl ViewExt for SearchView {
         "Search".to_string()
     }
 
+    fn binding_context(&self) -> Option<BindingContext> {
+        Some(BindingContext::Search)
+    }
+
     fn on_command(&mut self, _s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         if let Command::Focus(_) = cmd {
             self.edit_focused = true;
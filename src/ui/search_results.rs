@@ -1,6 +1,6 @@
 use crate::application::ASYNC_RUNTIME;
 use crate::command::Command;
-use crate::commands::CommandResult;
+use crate::commands::{BindingContext, CommandResult};
 use crate::events::EventManager;
 use crate::library::Library;
 use crate::model::album::Album;
@@ -58,7 +58,8 @@ impl SearchResultsView {
         let results_shows = Arc::new(RwLock::new(Vec::new()));
         let results_episodes = Arc::new(RwLock::new(Vec::new()));
 
-        let list_tracks = ListView::new(results_tracks.clone(), queue.clone(), library.clone());
+        let list_tracks = ListView::new(results_tracks.clone(), queue.clone(), library.clone())
+            .with_columns("search");
         let pagination_tracks = list_tracks.get_pagination().clone();
         let list_albums = ListView::new(results_albums.clone(), queue.clone(), library.clone());
         let pagination_albums = list_albums.get_pagination().clone();
@@ -69,7 +70,8 @@ impl SearchResultsView {
         let pagination_playlists = list_playlists.get_pagination().clone();
         let list_shows = ListView::new(results_shows.clone(), queue.clone(), library.clone());
         let pagination_shows = list_shows.get_pagination().clone();
-        let list_episodes = ListView::new(results_episodes.clone(), queue.clone(), library);
+        let list_episodes =
+            ListView::new(results_episodes.clone(), queue.clone(), library).with_columns("search");
         let pagination_episodes = list_episodes.get_pagination().clone();
 
         let mut tabs = TabbedView::new();
@@ -563,6 +565,10 @@ impl ViewExt for SearchResultsView {
     fn title(&self) -> String {
         format!("Search: {}", self.search_term)
     }
+    fn binding_context(&self) -> Option<BindingContext> {
+        Some(BindingContext::Search)
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         self.tabs.on_command(s, cmd)
     }
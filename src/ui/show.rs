@@ -24,7 +24,7 @@ impl ShowView {
 
         let list = {
             let results = spotify.api.show_episodes(&show.id);
-            let view = ListView::new(results.items.clone(), queue, library);
+            let view = ListView::new(results.items.clone(), queue, library).with_columns("show");
             results.apply_pagination(view.get_pagination());
 
             view
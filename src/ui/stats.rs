@@ -0,0 +1,204 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Local;
+use cursive::theme::Effect;
+use cursive::utils::markup::StyledString;
+use cursive::view::ViewWrapper;
+use cursive::views::{ScrollView, TextView};
+use cursive::Cursive;
+use unicode_width::UnicodeWidthStr;
+
+use crate::command::Command;
+use crate::commands::CommandResult;
+use crate::library::Library;
+use crate::stats::{Report, StatsOrder, StatsPeriod};
+use crate::traits::ViewExt;
+use crate::ui::scroll::scroll;
+use crate::utils::format_duration;
+
+/// The amount of entries in every top list of the view.
+const TOP_LIST_LENGTH: usize = 10;
+
+/// Listening statistics computed from the play history.
+pub struct StatsView {
+    view: ScrollView<TextView>,
+    library: Arc<Library>,
+    period: StatsPeriod,
+}
+
+impl StatsView {
+    pub fn new(library: Arc<Library>, period: StatsPeriod) -> Self {
+        let mut view = Self {
+            view: ScrollView::new(TextView::new("")),
+            library,
+            period,
+        };
+        view.refresh();
+        view
+    }
+
+    /// Recompute the statistics for the current period.
+    fn refresh(&mut self) {
+        let report = Report::new(
+            &self.library.play_history.entries(),
+            &self.period,
+            StatsOrder::Plays,
+            TOP_LIST_LENGTH,
+            Local::now(),
+        );
+
+        let listened = |secs: u64| format_duration(&Duration::from_secs(secs));
+        let mut text = StyledString::styled(
+            format!("Listening statistics ({})\n\n", report.period),
+            Effect::Bold,
+        );
+        table(
+            &mut text,
+            &[],
+            vec![
+                vec!["Plays".into(), report.plays.to_string()],
+                vec!["Listening time".into(), listened(report.listened_secs)],
+                vec![
+                    "Longest streak".into(),
+                    format!("{} days", report.longest_streak_days),
+                ],
+                vec![
+                    "Current streak".into(),
+                    format!("{} days", report.current_streak_days),
+                ],
+            ],
+        );
+
+        for (title, list) in [
+            ("Top tracks", &report.top_tracks),
+            ("Top artists", &report.top_artists),
+            ("Top albums", &report.top_albums),
+        ] {
+            text.append(StyledString::styled(format!("\n{title}\n\n"), Effect::Bold));
+            let rows = list
+                .iter()
+                .enumerate()
+                .map(|(index, ranked)| {
+                    vec![
+                        (index + 1).to_string(),
+                        ranked.name.clone(),
+                        ranked.plays.to_string(),
+                        listened(ranked.listened_secs),
+                    ]
+                })
+                .collect();
+            table(&mut text, &["#", "Name", "Plays", "Listening time"], rows);
+        }
+
+        text.append(StyledString::styled(
+            "\nPlays by hour of day\n\n",
+            Effect::Bold,
+        ));
+        let max = report
+            .plays_per_hour
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let rows = report
+            .plays_per_hour
+            .iter()
+            .enumerate()
+            .map(|(hour, plays)| {
+                let bar = "█".repeat((*plays as usize * 20).div_ceil(max as usize));
+                vec![format!("{hour:02}:00"), plays.to_string(), bar]
+            })
+            .collect();
+        table(&mut text, &["Hour", "Plays", ""], rows);
+
+        self.view.get_inner_mut().set_content(text);
+        self.view.scroll_to_top();
+    }
+}
+
+/// Append `rows` to `text` as a table with aligned columns, below a bold `header` row if there
+/// is one.
+fn table(text: &mut StyledString, header: &[&str], rows: Vec<Vec<String>>) {
+    let header: Vec<String> = header.iter().map(|title| title.to_string()).collect();
+    let mut widths: Vec<usize> = Vec::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        for (column, cell) in row.iter().enumerate() {
+            let width = cell.width();
+            match widths.get_mut(column) {
+                Some(max) => *max = (*max).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+
+    let line = |row: &[String]| {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell}{}", " ".repeat(width - cell.width())))
+            .collect();
+        format!("{}\n", cells.join("  ").trim_end())
+    };
+
+    if !header.is_empty() {
+        text.append(StyledString::styled(line(&header), Effect::Bold));
+    }
+    for row in &rows {
+        text.append(line(row));
+    }
+}
+
+impl ViewWrapper for StatsView {
+    wrap_impl!(self.view: ScrollView<TextView>);
+}
+
+impl ViewExt for StatsView {
+    fn title(&self) -> String {
+        "Stats".to_string()
+    }
+
+    fn title_sub(&self) -> String {
+        self.period.to_string()
+    }
+
+    fn on_command(&mut self, _s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            Command::Stats(period) => {
+                if let Some(period) = period {
+                    self.period = period.clone();
+                }
+                self.refresh();
+                Ok(CommandResult::Consumed(None))
+            }
+            Command::Move(mode, amount) => {
+                scroll(&mut self.view, mode, amount);
+                Ok(CommandResult::Consumed(None))
+            }
+            _ => Ok(CommandResult::Ignored),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aligns_table_columns() {
+        let mut text = StyledString::new();
+        table(
+            &mut text,
+            &["#", "Name", "Plays"],
+            vec![
+                vec!["1".into(), "Ünïcödé".into(), "12".into()],
+                vec!["10".into(), "A".into(), "3".into()],
+            ],
+        );
+        assert_eq!(
+            text.source(),
+            "#   Name     Plays\n1   Ünïcödé  12\n10  A        3\n"
+        );
+    }
+}
@@ -1,19 +1,48 @@
 use std::sync::Arc;
 
-use cursive::align::HAlign;
 use cursive::event::{Event, EventResult, MouseButton, MouseEvent};
-use cursive::theme::{ColorStyle, ColorType, PaletteColor};
+use cursive::theme::{Color, ColorStyle, ColorType, Effect, PaletteColor};
 use cursive::traits::View;
 use cursive::vec::Vec2;
 use cursive::Printer;
 use unicode_width::UnicodeWidthStr;
 
+use crate::config::{statusbar_placeholders, StatusbarSegment};
 use crate::library::Library;
 use crate::model::playable::Playable;
 use crate::queue::{Queue, RepeatSetting};
 use crate::spotify::{PlayerEvent, Spotify};
+use crate::template::Template;
 use crate::utils::ms_to_hms;
 
+/// The segments on the left of the status line by default.
+const DEFAULT_LEFT: [&str; 2] = ["%playback", "%track"];
+
+/// The segments on the right of the status line by default.
+const DEFAULT_RIGHT: [&str; 7] = [
+    "%updating",
+    "%repeat",
+    "%shuffle",
+    "%stop_after_current",
+    "%sleep",
+    "%elapsed / %duration",
+    "%volume",
+];
+
+/// The placeholders of settings that can be toggled by clicking them. They're dimmed while the
+/// setting is off.
+const TOGGLES: [&str; 3] = ["repeat", "shuffle", "stop_after_current"];
+
+/// A segment of the status line as it's drawn.
+struct Segment {
+    /// The column that the segment starts at.
+    x: usize,
+    config: StatusbarSegment,
+    text: String,
+    /// Whether all the settings that the segment shows are off.
+    dimmed: bool,
+}
+
 pub struct StatusBar {
     queue: Arc<Queue>,
     spotify: Spotify,
@@ -66,11 +95,171 @@ impl StatusBar {
 
     fn volume_display(&self) -> String {
         format!(
-            " [{}%]",
+            "[{}%]",
             (self.spotify.volume() as f64 / 65535_f64 * 100.0).round() as u16
         )
     }
 
+    /// Whether `name` is one of the [TOGGLES] and its setting is off.
+    fn is_off(&self, name: &str) -> bool {
+        match name {
+            "repeat" => self.queue.get_repeat() == RepeatSetting::None,
+            "shuffle" => !self.queue.get_shuffle(),
+            "stop_after_current" => !self.queue.get_stop_after_current(),
+            _ => false,
+        }
+    }
+
+    /// The value of the placeholder `name`. Indicators of states that are inactive are empty,
+    /// except for the [TOGGLES], which have to stay visible to be clicked.
+    fn placeholder(&self, name: &str) -> String {
+        let nerdfont = self.use_nerdfont();
+        let current = self.queue.get_current();
+        let elapsed = self.spotify.get_current_progress();
+
+        match name {
+            "playback" => self.playback_indicator().to_string(),
+            "track" => current
+                .as_ref()
+                .map(|t| self.format_track(t))
+                .unwrap_or_default(),
+            "updating" => match (*self.library.is_done.read().unwrap(), nerdfont) {
+                (true, _) => "",
+                (false, true) => "\u{f04e6}",
+                (false, false) => "[U]",
+            }
+            .to_string(),
+            "sync" => self
+                .library
+                .update_progress()
+                .map(|(done, total)| format!("{}%", done * 100 / total))
+                .unwrap_or_default(),
+            "repeat" => match (self.queue.get_repeat(), nerdfont) {
+                (RepeatSetting::None | RepeatSetting::RepeatPlaylist, true) => "\u{f0456}",
+                (RepeatSetting::RepeatTrack, true) => "\u{f0458}",
+                (RepeatSetting::None | RepeatSetting::RepeatPlaylist, false) => "[R]",
+                (RepeatSetting::RepeatTrack, false) => "[R1]",
+            }
+            .to_string(),
+            "shuffle" if nerdfont => "\u{f049d}".to_string(),
+            "shuffle" => "[Z]".to_string(),
+            "stop_after_current" if nerdfont => "\u{f04d}1".to_string(),
+            "stop_after_current" => "[S1]".to_string(),
+            "sleep" => match self.queue.sleep_status() {
+                Some(remaining) if nerdfont => format!("\u{f04b2} {remaining}"),
+                Some(remaining) => format!("[zZ {remaining}]"),
+                None => String::new(),
+            },
+            "elapsed" => current
+                .as_ref()
+                .map(|_| ms_to_hms(elapsed.as_millis().try_into().unwrap_or(0)))
+                .unwrap_or_default(),
+            "remaining" => current
+                .as_ref()
+                .map(|t| {
+                    let remaining = t.duration().saturating_sub(elapsed.as_millis() as u32);
+                    format!("-{}", ms_to_hms(remaining))
+                })
+                .unwrap_or_default(),
+            "position" => self
+                .queue
+                .get_current_index()
+                .map(|index| format!("{}/{}", index + 1, self.queue.len()))
+                .unwrap_or_default(),
+            "volume" => self.volume_display(),
+            // The actual bitrate of the stream isn't known, only the one that was asked for.
+            "bitrate_setting" => current
+                .as_ref()
+                .map(|_| format!("{} kbps", self.library.cfg.values().bitrate.unwrap_or(320)))
+                .unwrap_or_default(),
+            "backend_device" => self
+                .library
+                .cfg
+                .values()
+                .backend_device
+                .clone()
+                .unwrap_or_default(),
+            name => current
+                .as_ref()
+                .map(|t| t.field(name, &self.library))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The segments of the status line in a status bar that is `width` columns wide, with the
+    /// column they start at and their text, in the order in which they're drawn.
+    fn segments(&self, width: usize) -> Vec<Segment> {
+        let config = self
+            .library
+            .cfg
+            .values()
+            .statusbar
+            .clone()
+            .unwrap_or_default();
+        let defaults = |formats: &[&str]| {
+            formats
+                .iter()
+                .map(|format| StatusbarSegment::Format(format.to_string()))
+                .collect()
+        };
+        let filled = |segments: Vec<StatusbarSegment>| -> Vec<Segment> {
+            segments
+                .into_iter()
+                .filter_map(|config| {
+                    let template = self
+                        .library
+                        .cfg
+                        .template(config.format(), &statusbar_placeholders());
+                    let (text, dimmed) = match template {
+                        Some(template) => (
+                            fill(&template, |name| self.placeholder(name))?,
+                            is_dimmed(&template, |name| self.is_off(name)),
+                        ),
+                        None => (config.format().to_string(), false),
+                    };
+                    Some(Segment {
+                        x: 0,
+                        config,
+                        text,
+                        dimmed,
+                    })
+                })
+                .collect()
+        };
+        // The width of segments that are separated by a space.
+        let total_width = |segments: &[Segment]| {
+            let widths: usize = segments.iter().map(|segment| segment.text.width()).sum();
+            widths + segments.len().saturating_sub(1)
+        };
+
+        let left = filled(config.left.unwrap_or_else(|| defaults(&DEFAULT_LEFT)));
+        let center = filled(config.center.unwrap_or_default());
+        let right = filled(config.right.unwrap_or_else(|| defaults(&DEFAULT_RIGHT)));
+
+        let mut positioned = Vec::new();
+        for (mut x, segments) in [
+            (1, left),
+            (width.saturating_sub(total_width(&center)) / 2, center),
+            (width.saturating_sub(total_width(&right)), right),
+        ] {
+            for segment in segments {
+                let next = x + segment.text.width() + 1;
+                positioned.push(Segment { x, ..segment });
+                x = next;
+            }
+        }
+        positioned
+    }
+
+    /// Seek to the position of column `x` on the progress bar.
+    fn seek_to_column(&self, x: usize) {
+        if let Some(playable) = self.queue.get_current() {
+            let f: f32 = x as f32 / self.last_size.x as f32;
+            let new = playable.duration() as f32 * f;
+            self.spotify.seek(new as u32);
+        }
+    }
+
     fn format_track(&self, t: &Playable) -> String {
         let format = self
             .library
@@ -119,76 +308,25 @@ impl View for StatusBar {
             );
         });
 
-        printer.with_color(style, |printer| {
-            printer.print((1, 1), self.playback_indicator());
-        });
-
-        let updating = if !*self.library.is_done.read().unwrap() {
-            if self.use_nerdfont() {
-                "\u{f04e6} "
-            } else {
-                "[U] "
-            }
-        } else {
-            ""
-        };
-
-        let repeat = if self.use_nerdfont() {
-            match self.queue.get_repeat() {
-                RepeatSetting::None => "",
-                RepeatSetting::RepeatPlaylist => "\u{f0456} ",
-                RepeatSetting::RepeatTrack => "\u{f0458} ",
-            }
-        } else {
-            match self.queue.get_repeat() {
-                RepeatSetting::None => "",
-                RepeatSetting::RepeatPlaylist => "[R] ",
-                RepeatSetting::RepeatTrack => "[R1] ",
-            }
-        };
-
-        let shuffle = if self.queue.get_shuffle() {
-            if self.use_nerdfont() {
-                "\u{f049d} "
-            } else {
-                "[Z] "
-            }
-        } else {
-            ""
-        };
-
-        let volume = self.volume_display();
-
         printer.with_color(style_bar_bg, |printer| {
             printer.print((0, 0), &"┉".repeat(printer.size.x));
         });
 
-        let elapsed = self.spotify.get_current_progress();
-        let elapsed_ms = elapsed.as_millis() as u32;
-
-        let formatted_elapsed = ms_to_hms(elapsed.as_millis().try_into().unwrap_or(0));
-
-        let playback_duration_status = match self.queue.get_current() {
-            Some(ref t) => format!("{} / {}", formatted_elapsed, t.duration_str()),
-            None => "".to_string(),
-        };
-
-        let right = updating.to_string()
-            + repeat
-            + shuffle
-            // + saved
-            + &playback_duration_status
-            + &volume;
-        let offset = HAlign::Right.get_offset(right.width(), printer.size.x);
-
-        printer.with_color(style, |printer| {
-            if let Some(ref t) = self.queue.get_current() {
-                printer.print((4, 1), &self.format_track(t));
-            }
-            printer.print((offset, 1), &right);
-        });
+        for segment in self.segments(printer.size.x) {
+            printer.with_color(segment_style(&segment.config, style), |printer| {
+                let effect = if segment.dimmed {
+                    Effect::Dim
+                } else {
+                    Effect::Simple
+                };
+                printer.with_effect(effect, |printer| {
+                    printer.print((segment.x, 1), &segment.text);
+                });
+            });
+        }
 
         if let Some(t) = self.queue.get_current() {
+            let elapsed_ms = self.spotify.get_current_progress().as_millis() as u32;
             printer.with_color(style_bar, |printer| {
                 let duration_width =
                     (((printer.size.x as u32) * elapsed_ms) / t.duration()) as usize;
@@ -206,56 +344,154 @@ impl View for StatusBar {
     }
 
     fn on_event(&mut self, event: Event) -> EventResult {
-        if let Event::Mouse {
+        let Event::Mouse {
             offset,
             position,
             event,
         } = event
-        {
-            let position = position - offset;
-            let volume_len = self.volume_display().len();
+        else {
+            return EventResult::Ignored;
+        };
+        let Some(position) = position.checked_sub(offset) else {
+            return EventResult::Ignored;
+        };
 
-            if position.y == 0 {
-                if event == MouseEvent::WheelUp {
-                    self.spotify.seek_relative(-500);
+        if position.y == 0 {
+            match event {
+                MouseEvent::WheelUp => self.spotify.seek_relative(-500),
+                MouseEvent::WheelDown => self.spotify.seek_relative(500),
+                // Clicking or dragging on the progress bar seeks.
+                MouseEvent::Press(MouseButton::Left) | MouseEvent::Hold(MouseButton::Left) => {
+                    self.seek_to_column(position.x)
                 }
+                _ => {}
+            }
+            return EventResult::Consumed(None);
+        }
 
-                if event == MouseEvent::WheelDown {
-                    self.spotify.seek_relative(500);
-                }
+        // Segments react to clicks depending on their placeholders.
+        let format = self
+            .segments(self.last_size.x)
+            .into_iter()
+            .find(|segment| (segment.x..segment.x + segment.text.width()).contains(&position.x))
+            .map(|segment| segment.config.format().to_string())
+            .unwrap_or_default();
+        let has = |placeholder: &str| format.contains(placeholder);
+
+        match event {
+            MouseEvent::WheelUp if has("%volume") => {
+                let volume = self
+                    .spotify
+                    .volume()
+                    .saturating_add(crate::spotify::VOLUME_PERCENT);
+
+                self.spotify.set_volume(volume, true);
+            }
+            MouseEvent::WheelDown if has("%volume") => {
+                let volume = self
+                    .spotify
+                    .volume()
+                    .saturating_sub(crate::spotify::VOLUME_PERCENT);
 
-                if event == MouseEvent::Press(MouseButton::Left) {
-                    if let Some(playable) = self.queue.get_current() {
-                        let f: f32 = position.x as f32 / self.last_size.x as f32;
-                        let new = playable.duration() as f32 * f;
-                        self.spotify.seek(new as u32);
-                    }
-                }
-            } else if self.last_size.x - position.x < volume_len {
-                if event == MouseEvent::WheelUp {
-                    let volume = self
-                        .spotify
-                        .volume()
-                        .saturating_add(crate::spotify::VOLUME_PERCENT);
-
-                    self.spotify.set_volume(volume, true);
-                }
+                self.spotify.set_volume(volume, true);
+            }
+            MouseEvent::Press(MouseButton::Left) if has("%repeat") => {
+                self.queue.set_repeat(match self.queue.get_repeat() {
+                    RepeatSetting::None => RepeatSetting::RepeatPlaylist,
+                    RepeatSetting::RepeatPlaylist => RepeatSetting::RepeatTrack,
+                    RepeatSetting::RepeatTrack => RepeatSetting::None,
+                });
+            }
+            MouseEvent::Press(MouseButton::Left) if has("%shuffle") => {
+                self.queue.set_shuffle(!self.queue.get_shuffle());
+            }
+            MouseEvent::Press(MouseButton::Left) if has("%stop_after_current") => {
+                self.queue
+                    .set_stop_after_current(!self.queue.get_stop_after_current());
+            }
+            MouseEvent::Press(MouseButton::Left) if !has("%volume") => self.queue.toggleplayback(),
+            _ => {}
+        }
 
-                if event == MouseEvent::WheelDown {
-                    let volume = self
-                        .spotify
-                        .volume()
-                        .saturating_sub(crate::spotify::VOLUME_PERCENT);
+        EventResult::Consumed(None)
+    }
+}
 
-                    self.spotify.set_volume(volume, true);
-                }
-            } else if event == MouseEvent::Press(MouseButton::Left) {
-                self.queue.toggleplayback();
-            }
+/// `default` with the colors of `segment`, if it has any that can be parsed.
+fn segment_style(segment: &StatusbarSegment, default: ColorStyle) -> ColorStyle {
+    let StatusbarSegment::Styled { fg, bg, .. } = segment else {
+        return default;
+    };
+    let color = |color: &Option<String>, default: ColorType| {
+        color
+            .as_deref()
+            .and_then(Color::parse)
+            .map(ColorType::Color)
+            .unwrap_or(default)
+    };
+    ColorStyle::new(color(fg, default.front), color(bg, default.back))
+}
 
-            EventResult::Consumed(None)
-        } else {
-            EventResult::Ignored
-        }
+/// Fill in the placeholders of `template` with `value(name)`. If the template has placeholders
+/// and all of them are empty, the segment is hidden and None is returned.
+fn fill(template: &Template, value: impl Fn(&str) -> String) -> Option<String> {
+    let fields = template.fields();
+    if !fields.is_empty() && fields.iter().all(|name| value(name).is_empty()) {
+        return None;
+    }
+    Some(template.render(&value))
+}
+
+/// Whether a segment with `template` is dimmed, because it shows [TOGGLES] and all of them are
+/// off according to `is_off(name)`.
+fn is_dimmed(template: &Template, is_off: impl Fn(&str) -> bool) -> bool {
+    let fields = template.fields();
+    let mut toggles = fields
+        .iter()
+        .filter(|name| TOGGLES.contains(name))
+        .peekable();
+    toggles.peek().is_some() && toggles.all(|name| is_off(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_placeholders() {
+        let value = |name: &str| match name {
+            "elapsed" => "1:00".to_string(),
+            "duration" => "3:00".to_string(),
+            _ => String::new(),
+        };
+        let fill = |format: &str| {
+            fill(
+                &Template::parse(format, &statusbar_placeholders()).unwrap(),
+                value,
+            )
+        };
+        assert_eq!(
+            fill("%elapsed / %duration"),
+            Some("1:00 / 3:00".to_string())
+        );
+        assert_eq!(fill("%sleep"), None);
+        assert_eq!(fill("{?repeat:%repeat }%elapsed"), Some("1:00".to_string()));
+        assert_eq!(fill("100%%"), Some("100%".to_string()));
+    }
+
+    #[test]
+    fn dims_segments_whose_toggles_are_off() {
+        let is_off = |name: &str| name == "repeat" || name == "stop_after_current";
+        let dimmed = |format: &str| {
+            is_dimmed(
+                &Template::parse(format, &statusbar_placeholders()).unwrap(),
+                is_off,
+            )
+        };
+        assert!(dimmed("%repeat"));
+        assert!(dimmed("%repeat%stop_after_current"));
+        assert!(!dimmed("%repeat%shuffle"));
+        assert!(!dimmed("%shuffle"));
+        assert!(!dimmed("%volume"));
     }
 }
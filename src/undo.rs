@@ -0,0 +1,419 @@
+use std::sync::RwLock;
+
+use crate::library::Library;
+use crate::model::playable::Playable;
+use crate::model::playlist::Playlist;
+use crate::queue::{Queue, QueueSnapshot};
+
+/// What changes are applied to when they're undone or redone.
+trait UndoTarget {
+    fn restore_queue(&self, snapshot: &QueueSnapshot);
+    fn insert_playlist_tracks(
+        &self,
+        id: &str,
+        position: usize,
+        tracks: &[Playable],
+    ) -> Result<String, String>;
+    fn remove_playlist_tracks(
+        &self,
+        id: &str,
+        snapshot_id: &str,
+        position: usize,
+        tracks: &[Playable],
+    ) -> Result<String, String>;
+    fn follow_playlist(&self, playlist: Playlist);
+    fn remove_playlist(&self, id: &str) -> Option<Playlist>;
+}
+
+impl UndoTarget for (&Queue, &Library) {
+    fn restore_queue(&self, snapshot: &QueueSnapshot) {
+        self.0.restore(snapshot);
+    }
+
+    fn insert_playlist_tracks(
+        &self,
+        id: &str,
+        position: usize,
+        tracks: &[Playable],
+    ) -> Result<String, String> {
+        self.1.insert_playlist_tracks(id, position, tracks)
+    }
+
+    fn remove_playlist_tracks(
+        &self,
+        id: &str,
+        snapshot_id: &str,
+        position: usize,
+        tracks: &[Playable],
+    ) -> Result<String, String> {
+        self.1
+            .remove_playlist_tracks(id, snapshot_id, position, tracks)
+    }
+
+    fn follow_playlist(&self, playlist: Playlist) {
+        self.1.follow_playlist(playlist);
+    }
+
+    fn remove_playlist(&self, id: &str) -> Option<Playlist> {
+        self.1.remove_playlist(id)
+    }
+}
+
+/// The maximum amount of changes that can be undone.
+const MAX_HISTORY: usize = 50;
+
+/// A reversible change to the queue or to a playlist.
+#[derive(Clone, Debug)]
+pub enum Change {
+    /// The queue changed from `before` to `after`.
+    Queue {
+        before: QueueSnapshot,
+        after: QueueSnapshot,
+    },
+    /// `tracks` were removed from the playlist at `position`. `snapshot_id` is the playlist
+    /// version after the change.
+    PlaylistTracksRemoved {
+        playlist_id: String,
+        snapshot_id: String,
+        position: usize,
+        tracks: Vec<Playable>,
+    },
+    /// `tracks` were added to the playlist at `position`. `snapshot_id` is the playlist version
+    /// after the change.
+    PlaylistTracksAdded {
+        playlist_id: String,
+        snapshot_id: String,
+        position: usize,
+        tracks: Vec<Playable>,
+    },
+    /// The playlist was deleted from the library.
+    PlaylistDeleted(Playlist),
+}
+
+impl Change {
+    /// A short description of the change for status messages.
+    fn describe(&self) -> String {
+        match self {
+            Self::Queue { .. } => "queue change".to_string(),
+            Self::PlaylistTracksRemoved { tracks, .. } => {
+                format!("removal of {} tracks", tracks.len())
+            }
+            Self::PlaylistTracksAdded { tracks, .. } => {
+                format!("addition of {} tracks", tracks.len())
+            }
+            Self::PlaylistDeleted(playlist) => format!("deletion of \"{}\"", playlist.name),
+        }
+    }
+
+    /// Revert this change. Returns the change that has to be applied to redo it.
+    fn revert(self, target: &impl UndoTarget) -> Result<Self, String> {
+        match self {
+            Self::Queue { before, after } => {
+                target.restore_queue(&before);
+                Ok(Self::Queue { before, after })
+            }
+            Self::PlaylistTracksRemoved {
+                playlist_id,
+                position,
+                tracks,
+                ..
+            } => {
+                let snapshot_id = target.insert_playlist_tracks(&playlist_id, position, &tracks)?;
+                Ok(Self::PlaylistTracksRemoved {
+                    playlist_id,
+                    snapshot_id,
+                    position,
+                    tracks,
+                })
+            }
+            Self::PlaylistTracksAdded {
+                playlist_id,
+                snapshot_id,
+                position,
+                tracks,
+            } => {
+                let snapshot_id =
+                    target.remove_playlist_tracks(&playlist_id, &snapshot_id, position, &tracks)?;
+                Ok(Self::PlaylistTracksAdded {
+                    playlist_id,
+                    snapshot_id,
+                    position,
+                    tracks,
+                })
+            }
+            Self::PlaylistDeleted(playlist) => {
+                target.follow_playlist(playlist.clone());
+                Ok(Self::PlaylistDeleted(playlist))
+            }
+        }
+    }
+
+    /// Apply this change again after it was reverted. Returns the change that has to be reverted
+    /// to undo it.
+    fn reapply(self, target: &impl UndoTarget) -> Result<Self, String> {
+        match self {
+            Self::Queue { before, after } => {
+                target.restore_queue(&after);
+                Ok(Self::Queue { before, after })
+            }
+            Self::PlaylistTracksRemoved {
+                playlist_id,
+                snapshot_id,
+                position,
+                tracks,
+            } => {
+                let snapshot_id =
+                    target.remove_playlist_tracks(&playlist_id, &snapshot_id, position, &tracks)?;
+                Ok(Self::PlaylistTracksRemoved {
+                    playlist_id,
+                    snapshot_id,
+                    position,
+                    tracks,
+                })
+            }
+            Self::PlaylistTracksAdded {
+                playlist_id,
+                position,
+                tracks,
+                ..
+            } => {
+                let snapshot_id = target.insert_playlist_tracks(&playlist_id, position, &tracks)?;
+                Ok(Self::PlaylistTracksAdded {
+                    playlist_id,
+                    snapshot_id,
+                    position,
+                    tracks,
+                })
+            }
+            Self::PlaylistDeleted(playlist) => match target.remove_playlist(&playlist.id) {
+                Some(_) => Ok(Self::PlaylistDeleted(playlist)),
+                None => Err(format!("Could not delete playlist \"{}\"", playlist.name)),
+            },
+        }
+    }
+}
+
+/// The undo and redo stacks of changes to the queue and playlists.
+#[derive(Default)]
+pub struct UndoHistory {
+    undo: RwLock<Vec<Change>>,
+    redo: RwLock<Vec<Change>>,
+}
+
+impl UndoHistory {
+    /// Record a new `change` that can be undone. This clears the redo stack.
+    pub fn record(&self, change: Change) {
+        let mut undo = self.undo.write().unwrap();
+        undo.push(change);
+        if undo.len() > MAX_HISTORY {
+            undo.remove(0);
+        }
+        self.redo.write().unwrap().clear();
+    }
+
+    /// Undo the most recent change. Returns a message describing what was undone.
+    pub fn undo(&self, queue: &Queue, library: &Library) -> Result<String, String> {
+        self.undo_on(&(queue, library))
+    }
+
+    fn undo_on(&self, target: &impl UndoTarget) -> Result<String, String> {
+        let change = self.undo.write().unwrap().pop().ok_or("Nothing to undo")?;
+        let description = change.describe();
+
+        match change.clone().revert(target) {
+            Ok(redo) => {
+                self.redo.write().unwrap().push(redo);
+                Ok(format!("Undid {description}"))
+            }
+            Err(e) => {
+                self.undo.write().unwrap().push(change);
+                Err(e)
+            }
+        }
+    }
+
+    /// Redo the most recently undone change. Returns a message describing what was redone.
+    pub fn redo(&self, queue: &Queue, library: &Library) -> Result<String, String> {
+        self.redo_on(&(queue, library))
+    }
+
+    fn redo_on(&self, target: &impl UndoTarget) -> Result<String, String> {
+        let change = self.redo.write().unwrap().pop().ok_or("Nothing to redo")?;
+        let description = change.describe();
+
+        match change.clone().reapply(target) {
+            Ok(undo) => {
+                self.undo.write().unwrap().push(undo);
+                Ok(format!("Redid {description}"))
+            }
+            Err(e) => {
+                self.redo.write().unwrap().push(change);
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::model::track::Track;
+
+    /// A queue and a single playlist held in memory.
+    #[derive(Default)]
+    struct FakeTarget {
+        queue: Mutex<Vec<String>>,
+        playlist: Mutex<Vec<String>>,
+        fail: Mutex<bool>,
+    }
+
+    impl UndoTarget for FakeTarget {
+        fn restore_queue(&self, snapshot: &QueueSnapshot) {
+            *self.queue.lock().unwrap() = snapshot.items.iter().filter_map(|p| p.id()).collect();
+        }
+
+        fn insert_playlist_tracks(
+            &self,
+            _id: &str,
+            position: usize,
+            tracks: &[Playable],
+        ) -> Result<String, String> {
+            if *self.fail.lock().unwrap() {
+                return Err("offline".into());
+            }
+            let mut playlist = self.playlist.lock().unwrap();
+            for (offset, track) in tracks.iter().enumerate() {
+                playlist.insert(position + offset, track.id().unwrap_or_default());
+            }
+            Ok(format!("v{}", playlist.len()))
+        }
+
+        fn remove_playlist_tracks(
+            &self,
+            _id: &str,
+            _snapshot_id: &str,
+            position: usize,
+            tracks: &[Playable],
+        ) -> Result<String, String> {
+            if *self.fail.lock().unwrap() {
+                return Err("offline".into());
+            }
+            let mut playlist = self.playlist.lock().unwrap();
+            playlist.drain(position..position + tracks.len());
+            Ok(format!("v{}", playlist.len()))
+        }
+
+        fn follow_playlist(&self, _playlist: Playlist) {}
+
+        fn remove_playlist(&self, _id: &str) -> Option<Playlist> {
+            None
+        }
+    }
+
+    fn track(id: &str) -> Playable {
+        Playable::Track(Track::test(id, id, "artist", None))
+    }
+
+    fn snapshot(ids: &[&str]) -> QueueSnapshot {
+        QueueSnapshot {
+            items: ids.iter().map(|id| track(id)).collect(),
+            random_order: None,
+            auto_added: Default::default(),
+        }
+    }
+
+    fn queue_change(before: &[&str], after: &[&str]) -> Change {
+        Change::Queue {
+            before: snapshot(before),
+            after: snapshot(after),
+        }
+    }
+
+    fn queue(target: &FakeTarget) -> Vec<String> {
+        target.queue.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn undoes_and_redoes_in_order() {
+        let history = UndoHistory::default();
+        let target = FakeTarget::default();
+        history.record(queue_change(&[], &["a"]));
+        history.record(queue_change(&["a"], &["a", "b"]));
+
+        assert!(history.undo_on(&target).is_ok());
+        assert_eq!(queue(&target), ["a"]);
+        assert!(history.undo_on(&target).is_ok());
+        assert!(queue(&target).is_empty());
+        assert_eq!(history.undo_on(&target), Err("Nothing to undo".into()));
+
+        assert!(history.redo_on(&target).is_ok());
+        assert_eq!(queue(&target), ["a"]);
+        assert!(history.redo_on(&target).is_ok());
+        assert_eq!(queue(&target), ["a", "b"]);
+        assert_eq!(history.redo_on(&target), Err("Nothing to redo".into()));
+    }
+
+    #[test]
+    fn recording_clears_redo() {
+        let history = UndoHistory::default();
+        let target = FakeTarget::default();
+        history.record(queue_change(&[], &["a"]));
+        history.undo_on(&target).unwrap();
+
+        history.record(queue_change(&[], &["b"]));
+        assert_eq!(history.redo_on(&target), Err("Nothing to redo".into()));
+        history.undo_on(&target).unwrap();
+        assert_eq!(history.undo_on(&target), Err("Nothing to undo".into()));
+    }
+
+    #[test]
+    fn undoes_and_redoes_playlist_removal() {
+        let history = UndoHistory::default();
+        let target = FakeTarget::default();
+        *target.playlist.lock().unwrap() = vec!["a".into(), "d".into()];
+        history.record(Change::PlaylistTracksRemoved {
+            playlist_id: "p".into(),
+            snapshot_id: "v2".into(),
+            position: 1,
+            tracks: vec![track("b"), track("c")],
+        });
+
+        history.undo_on(&target).unwrap();
+        assert_eq!(*target.playlist.lock().unwrap(), ["a", "b", "c", "d"]);
+        history.redo_on(&target).unwrap();
+        assert_eq!(*target.playlist.lock().unwrap(), ["a", "d"]);
+    }
+
+    #[test]
+    fn failed_undo_keeps_change() {
+        let history = UndoHistory::default();
+        let target = FakeTarget::default();
+        history.record(Change::PlaylistTracksAdded {
+            playlist_id: "p".into(),
+            snapshot_id: "v1".into(),
+            position: 0,
+            tracks: vec![track("a")],
+        });
+        *target.playlist.lock().unwrap() = vec!["a".into()];
+
+        *target.fail.lock().unwrap() = true;
+        assert_eq!(history.undo_on(&target), Err("offline".into()));
+        *target.fail.lock().unwrap() = false;
+        history.undo_on(&target).unwrap();
+        assert!(target.playlist.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn forgets_oldest_changes() {
+        let history = UndoHistory::default();
+        let target = FakeTarget::default();
+        for _ in 0..MAX_HISTORY + 5 {
+            history.record(queue_change(&[], &["a"]));
+        }
+
+        let undone = std::iter::from_fn(|| history.undo_on(&target).ok()).count();
+        assert_eq!(undone, MAX_HISTORY);
+    }
+}
@@ -43,6 +43,25 @@ pub fn ms_to_hms(duration: u32) -> String {
     formated_time
 }
 
+/// Returns `name` in lowercase without a leading "the" article, so that "The Beatles" sorts
+/// next to "Beatles".
+pub fn without_article(name: &str) -> String {
+    let name = name.to_lowercase();
+    match name.strip_prefix("the ") {
+        Some(stripped) => stripped.to_string(),
+        None => name,
+    }
+}
+
+/// Returns `path` with a leading `~/` replaced by the home directory, or None if that has to be
+/// done but the home directory isn't known.
+pub fn expand_home(path: &str) -> Option<PathBuf> {
+    match path.strip_prefix("~/") {
+        Some(rest) => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(rest)),
+        None => Some(PathBuf::from(path)),
+    }
+}
+
 pub fn cache_path_for_url(url: String) -> std::path::PathBuf {
     let mut path = crate::config::cache_path("covers");
     path.push(url.split('/').last().unwrap());